@@ -0,0 +1,180 @@
+// The client side of the transaction websocket protocol, see src-tauri/src/transactions/protocol.rs for the specification
+// Both are tested against src-tauri/src/transactions/protocol_fixtures.json
+
+export const PROTOCOL_VERSION = 3;
+
+const REQUESTS = {
+	hello: 0x00,
+	cancel: 0x01,
+	pause: 0x02,
+	resume: 0x03,
+	list: 0x04,
+	subscribe: 0x05,
+	subscribe_all: 0x06,
+};
+
+const EVENT_WELCOME = 0x00;
+const EVENT_REJECTED = 0x01;
+const EVENT_LIST = 0x02;
+const EVENT_FINISHED = 0x10;
+const EVENT_ERROR = 0x11;
+const EVENT_DATA = 0x12;
+const EVENT_STATUS = 0x13;
+const EVENT_PROGRESS = 0x14;
+const EVENT_INCR_PROGRESS = 0x15;
+const EVENT_RESET_PROGRESS = 0x16;
+const EVENT_CHILD = 0x17;
+const EVENT_PAUSED = 0x18;
+
+const NO_PARENT = 0xFFFFFFFF;
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder('utf-8', { fatal: true });
+
+// { type: 'hello', version, token } | { type: 'cancel' | 'pause' | 'resume' | 'subscribe', id } | { type: 'list' | 'subscribe_all' }
+export function encodeRequest(request) {
+	const type = REQUESTS[request.type];
+	if (type === undefined) throw new Error('Unknown request type ' + request.type);
+
+	let view;
+	switch (request.type) {
+		case 'hello':
+		{
+			const token = encoder.encode(request.token);
+			view = new DataView(new ArrayBuffer(3 + token.length + 1));
+			view.setUint16(1, request.version);
+			new Uint8Array(view.buffer).set(token, 3);
+		}
+		break;
+
+		case 'cancel':
+		case 'pause':
+		case 'resume':
+		case 'subscribe':
+			view = new DataView(new ArrayBuffer(5));
+			view.setUint32(1, request.id);
+			break;
+
+		default:
+			view = new DataView(new ArrayBuffer(1));
+	}
+	view.setUint8(0, type);
+
+	return new Uint8Array(view.buffer);
+}
+
+class Reader {
+	constructor(bytes) {
+		this.view = bytes instanceof DataView ? bytes : new DataView(bytes instanceof ArrayBuffer ? bytes : bytes.buffer, bytes.byteOffset ?? 0, bytes.byteLength);
+		this.i = 0;
+	}
+
+	need(n) {
+		if (this.i + n > this.view.byteLength) throw new Error('Truncated frame');
+	}
+
+	u8() { this.need(1); return this.view.getUint8(this.i++); }
+	u16() { this.need(2); const value = this.view.getUint16(this.i); this.i += 2; return value; }
+	u32() { this.need(4); const value = this.view.getUint32(this.i); this.i += 4; return value; }
+	f64() { this.need(8); const value = this.view.getFloat64(this.i); this.i += 8; return value; }
+
+	bool() {
+		switch (this.u8()) {
+			case 0: return false;
+			case 1: return true;
+			default: throw new Error('Invalid boolean');
+		}
+	}
+
+	string() {
+		const bytes = new Uint8Array(this.view.buffer, this.view.byteOffset + this.i, this.view.byteLength - this.i);
+		const len = bytes.indexOf(0);
+		if (len === -1) throw new Error('Truncated frame');
+		this.i += len + 1;
+		return decoder.decode(bytes.subarray(0, len));
+	}
+
+	json() {
+		switch (this.u8()) {
+			case 0: return null;
+			case 1: return JSON.parse(this.string());
+			default: throw new Error('Invalid JSON');
+		}
+	}
+
+	finish() {
+		if (this.i !== this.view.byteLength) throw new Error('Trailing bytes after frame');
+	}
+}
+
+// Progress is from 0 to 10000
+export function decodeEvent(bytes) {
+	const reader = new Reader(bytes);
+
+	let event;
+	const type = reader.u8();
+	switch (type) {
+		case EVENT_WELCOME:
+			event = { type: 'welcome', version: reader.u16() };
+			break;
+
+		case EVENT_REJECTED:
+			event = { type: 'rejected', request: reader.u8(), reason: reader.string() };
+			break;
+
+		case EVENT_LIST:
+		{
+			const transactions = [];
+			for (let len = reader.u32(); len > 0; len--) {
+				const id = reader.u32();
+				const parent = reader.u32();
+				const progress = reader.u16();
+				transactions.push({ id, parent: parent === NO_PARENT ? null : parent, progress, paused: reader.bool() });
+			}
+			event = { type: 'transactions', transactions };
+		}
+		break;
+
+		case EVENT_FINISHED:
+			event = { type: 'finished', id: reader.u32(), data: reader.json() };
+			break;
+
+		case EVENT_ERROR:
+			event = { type: 'error', id: reader.u32(), error: reader.string(), data: reader.json() };
+			break;
+
+		case EVENT_DATA:
+			event = { type: 'data', id: reader.u32(), data: reader.json() };
+			break;
+
+		case EVENT_STATUS:
+			event = { type: 'status', id: reader.u32(), status: reader.string() };
+			break;
+
+		case EVENT_PROGRESS:
+			event = { type: 'progress', id: reader.u32(), progress: reader.u16() };
+			break;
+
+		case EVENT_INCR_PROGRESS:
+			event = { type: 'incr_progress', id: reader.u32(), progress: reader.u16() };
+			break;
+
+		case EVENT_RESET_PROGRESS:
+			event = { type: 'reset_progress', id: reader.u32() };
+			break;
+
+		case EVENT_CHILD:
+			event = { type: 'child', id: reader.u32(), child: reader.u32(), weight: reader.f64() };
+			break;
+
+		case EVENT_PAUSED:
+			event = { type: 'paused', id: reader.u32(), paused: reader.bool() };
+			break;
+
+		default:
+			throw new Error('Unknown frame type ' + type);
+	}
+	reader.finish();
+
+	return event;
+}
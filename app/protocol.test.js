@@ -0,0 +1,26 @@
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { readFileSync } from 'node:fs';
+
+import { encodeRequest, decodeEvent } from './protocol.js';
+
+const fixtures = JSON.parse(readFileSync(new URL('../src-tauri/src/transactions/protocol_fixtures.json', import.meta.url)));
+const bytes = hex => Uint8Array.from(hex.split(' ').map(byte => parseInt(byte, 16)));
+
+test('requests encode like the backend decodes them', () => {
+	for (const fixture of fixtures.filter(fixture => fixture.direction === 'request')) {
+		assert.deepEqual(encodeRequest(fixture.message), bytes(fixture.bytes), fixture.message.type);
+	}
+});
+
+test('events decode like the backend encodes them', () => {
+	for (const fixture of fixtures.filter(fixture => fixture.direction === 'event')) {
+		assert.deepEqual(decodeEvent(bytes(fixture.bytes)), fixture.message, fixture.message.type);
+	}
+});
+
+test('malformed events are rejected', () => {
+	assert.throws(() => decodeEvent(new Uint8Array([0xFF])), /Unknown frame type/);
+	assert.throws(() => decodeEvent(new Uint8Array([0x13, 0, 0, 0, 1, 0x61])), /Truncated/);
+	assert.throws(() => decodeEvent(new Uint8Array([0x16, 0, 0, 0, 1, 0])), /Trailing/);
+});
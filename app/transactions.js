@@ -1,6 +1,9 @@
 import { invoke } from '@tauri-apps/api/tauri';
 import { listen } from '@tauri-apps/api/event';
-import { writable } from 'svelte/store';
+import { writable, get } from 'svelte/store';
+import { _ } from 'svelte-i18n';
+
+import { PROTOCOL_VERSION, encodeRequest, decodeEvent } from './protocol.js';
 
 let transactions = {};
 
@@ -58,7 +61,10 @@ class Transaction {
 		this.progress = 0;
 		this.finished = false;
 		this.cancelled = false;
+		this.paused = false;
 		this.unconsumedEvents = [];
+		this.parent = null;
+		this.children = [];
 
 		transactions[id] = this;
 
@@ -75,6 +81,29 @@ class Transaction {
 		return transactions[id];
 	}
 
+	// Children are created when their parent announces them, so use this for transactions that might be someone's child
+	static getOrCreate(id, TASK_statusTextFn) {
+		return transactions[id] ?? new Transaction(id, TASK_statusTextFn);
+	}
+
+	addChild(id, weight) {
+		const child = Transaction.getOrCreate(id);
+		child.parent = this;
+		this.children.push({ transaction: child, weight });
+		this.emit({ child, weight });
+
+		return this;
+	}
+
+	// { transaction, weight, children: [{ transaction, weight, children }, ...] }
+	tree(weight) {
+		return {
+			transaction: this,
+			weight: weight ?? 1,
+			children: this.children.map(({ transaction, weight }) => transaction.tree(weight))
+		};
+	}
+
 	listen(callback) {
 		this.callbacks.push(callback);
 
@@ -105,7 +134,12 @@ class Transaction {
 		this.emit({ cancelled: true });
 		delete transactions[this.id];
 
-		if (!fromBackend) {
+		// The backend cancels children along with their parent
+		for (let i = 0; i < this.children.length; i++) {
+			this.children[i].transaction.cancel(true);
+		}
+
+		if (!fromBackend && !sendRequest({ type: 'cancel', id: this.id })) {
 			invoke('cancel_transaction', {
 				id: this.id
 			});
@@ -114,6 +148,40 @@ class Transaction {
 		return this;
 	}
 
+	// The backend pauses and resumes children along with their parent
+	pause() {
+		if (this.paused || this.cancelled || this.finished) return;
+
+		if (!sendRequest({ type: 'pause', id: this.id })) {
+			invoke('pause_transaction', {
+				id: this.id
+			});
+		}
+
+		return this;
+	}
+
+	resume() {
+		if (!this.paused) return;
+
+		if (!sendRequest({ type: 'resume', id: this.id })) {
+			invoke('resume_transaction', {
+				id: this.id
+			});
+		}
+
+		return this;
+	}
+
+	setPaused(paused) {
+		if (paused !== this.paused) {
+			this.paused = paused;
+			this.emit({ paused });
+		}
+
+		return this;
+	}
+
 	setFinished(data) {
 		this.finished = true;
 		if (this.progress < 100) {
@@ -186,6 +254,10 @@ transactionEvent('Progress', ([ transaction, progress ]) => {
 	if (progress > (transaction.progressInt ?? 0)) transaction.setProgress(progress);
 });
 
+transactionEvent('IncrProgress', ([ transaction, incr ]) => {
+	transaction.setProgress(Math.min((transaction.progressInt ?? 0) + incr, 10000));
+});
+
 transactionEvent('Cancelled', ([ transaction ]) => {
 	//console.log('transactionCancelled', transaction);
 	transaction.cancel(true);
@@ -211,83 +283,107 @@ transactionEvent('Data', ([ transaction, data ]) => {
 	transaction.setData(data);
 });
 
-invoke('websocket').then(port => {
-	const decoder = new TextDecoder('utf-8');
-	const read_nt_string = (byteOffset, view, json) => {
-		let i = byteOffset;
-		if (json) {
-			if (view.getUint8(i++) === 0) return [null, i];
-		}
-		const buffer = [];
-		for (i; i < view.byteLength; i++) {
-			const byte = view.getUint8(i);
-			if (byte === 0) {
-				i++;
-				break;
-			} else {
-				buffer.push(byte);
-			}
+transactionEvent('Child', ([ transaction, child, weight ]) => {
+	transaction.addChild(child, weight);
+});
+
+transactionEvent('Paused', ([ transaction, paused ]) => {
+	transaction.setPaused(paused);
+});
+
+// The webview might have been reloaded while transactions were running, so rebuild them to give their events somewhere to go
+// Progress is from 0 to 10000, and they're sorted by ID, so parents come before their children
+invoke('active_transactions').then(active => {
+	for (const { id, parent, status, progress, paused } of active) {
+		const transaction = Transaction.getOrCreate(id, parent == null ? transaction => get(_)('transaction_restored', { values: { pct: transaction.progress.toFixed(0) } }) : undefined);
+		if (parent != null && !transaction.parent) {
+			Transaction.get(parent)?.addChild(id);
 		}
-		if (json) {
-			return [JSON.parse(decoder.decode(new Uint8Array(buffer))), i];
-		} else {
-			return [decoder.decode(new Uint8Array(buffer)), i];
+		if (status != null) transaction.setStatus(status);
+		transaction.setProgress(progress);
+		transaction.setPaused(paused);
+	}
+});
+
+// [{ id, parent, status, outcome: 'finished' | 'error' | 'cancelled', error, duration, ended }, ...], oldest first
+// Durations are in milliseconds, and ended is a timestamp in milliseconds
+function transactionHistory() {
+	return invoke('transaction_history');
+}
+
+let socket = null;
+function sendRequest(request) {
+	if (socket?.readyState !== WebSocket.OPEN) return false;
+	socket.send(encodeRequest(request));
+	return true;
+}
+
+invoke('websocket').then(server => {
+	if (server == null) return;
+
+	const ws = new WebSocket('ws://localhost:' + server.port, 'gmpublisher');
+	ws.binaryType = 'arraybuffer';
+	ws.addEventListener('open', () => {
+		ws.send(encodeRequest({ type: 'hello', version: PROTOCOL_VERSION, token: server.token }));
+	});
+	ws.addEventListener('message', ({ data: bytes }) => {
+		let event;
+		try {
+			event = decodeEvent(bytes);
+		} catch (err) {
+			console.error('Invalid transaction message', err, bytes);
+			return;
 		}
-	};
-	const read_json = (byteOffset, view) => {
-		return read_nt_string(byteOffset, view, true);
-	};
 
-	const socket = new WebSocket('ws://localhost:' + port, 'gmpublisher');
-	socket.binaryType = 'arraybuffer';
-	socket.addEventListener('message', event => {
-        const view = new DataView(event.data);
-		const message = view.getUint8(0);
-        const id = view.getUint32(1);
-
-		switch(message) {
-			case 0:
-			{
-				const [data, _] = read_json(5, view);
-				receiveTransactionEvent('Finished', [id, data]);
-			}
-			break;
+		switch (event.type) {
+			case 'welcome':
+				socket = ws;
+				break;
 
-			case 1:
-			{
-				const [msg, i] = read_nt_string(5, view);
-				const [data, _] = read_json(i, view);
-				receiveTransactionEvent('Error', [id, msg, data]);
-			}
-			break;
+			case 'rejected':
+				console.warn('Transaction request rejected', event.request, event.reason);
+				break;
 
-			case 2:
-			{
-				const [data, _] = read_json(5, view);
-				receiveTransactionEvent('Data', [id, data]);
-			}
-			break;
+			case 'finished':
+				receiveTransactionEvent('Finished', [event.id, event.data]);
+				break;
 
-			case 3:
-			{
-				const [status, _] = read_nt_string(5, view);
-				receiveTransactionEvent('Status', [id, status]);
-			}
-			break;
+			case 'error':
+				receiveTransactionEvent('Error', [event.id, event.error, event.data]);
+				break;
+
+			case 'data':
+				receiveTransactionEvent('Data', [event.id, event.data]);
+				break;
+
+			case 'status':
+				receiveTransactionEvent('Status', [event.id, event.status]);
+				break;
 
-			case 4:
-			receiveTransactionEvent('Progress', [id, view.getUint16(5)]);
-			break;
+			case 'progress':
+				receiveTransactionEvent('Progress', [event.id, event.progress]);
+				break;
 
-			case 5:
-			receiveTransactionEvent('IncrProgress', [id, view.getUint16(5)]);
-			break;
+			case 'incr_progress':
+				receiveTransactionEvent('IncrProgress', [event.id, event.progress]);
+				break;
+
+			case 'reset_progress':
+				receiveTransactionEvent('ResetProgress', [event.id]);
+				break;
 
-			case 6:
-			receiveTransactionEvent('ResetProgress', [id]);
-			break;
+			case 'child':
+				receiveTransactionEvent('Child', [event.id, event.child, event.weight]);
+				break;
+
+			case 'paused':
+				receiveTransactionEvent('Paused', [event.id, event.paused]);
+				break;
 		}
 	});
+	ws.addEventListener('close', () => {
+		if (socket === ws) socket = null;
+	});
 });
 
-export { Transaction, tasks, taskHeight, tasksMax, tasksNum }
+export { Transaction, transactionHistory, tasks, taskHeight, tasksMax, tasksNum }
@@ -3,5 +3,6 @@ fn main() {
 		println!("cargo:rustc-link-arg=-Wl,-rpath,.");
 	}
 
-	tauri_build::build()
+	#[cfg(feature = "gui")]
+	tauri_build::build();
 }
@@ -0,0 +1,165 @@
+/* C ABI for libgma: https://github.com/WilliamVenner/gmpublisher */
+
+#ifndef LIBGMA_H
+#define LIBGMA_H
+
+/* Generated by cbindgen from libgma-ffi/src/lib.rs. Don't edit it by hand, regenerate it with `UPDATE_GMA_H=1 cargo test -p libgma-ffi`. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum GmaResult {
+  GMA_RESULT_OK = 0,
+  GMA_RESULT_IO_ERROR,
+  GMA_RESULT_FORMAT_ERROR,
+  GMA_RESULT_INVALID_HEADER,
+  GMA_RESULT_ENTRY_NOT_FOUND,
+  GMA_RESULT_LZMA,
+  GMA_RESULT_CANCELLED,
+  // A required pointer was null
+  GMA_RESULT_NULL_POINTER,
+  // A string wasn't valid UTF-8
+  GMA_RESULT_INVALID_UTF8,
+  // The addon.json is missing, malformed or has no title
+  GMA_RESULT_INVALID_METADATA,
+  // Files in the folder only differ by case, so they would be packed into the same entry
+  GMA_RESULT_CASE_COLLISION,
+  // A symbolic link was found where links aren't allowed, see gma_last_error_path
+  GMA_RESULT_SYMLINK,
+  // A file in the folder couldn't be read while packing it, see gma_last_error_path
+  GMA_RESULT_PATH_IO_ERROR,
+} GmaResult;
+
+// What to do with symbolic links in the folder being packed
+typedef enum GmaSymlinkPolicy {
+  // Pack whatever links point to, as long as it's inside the folder
+  GMA_SYMLINK_POLICY_FOLLOW = 0,
+  // Leave links out of the addon
+  GMA_SYMLINK_POLICY_SKIP,
+  // Fail with GMA_RESULT_SYMLINK if the folder contains links
+  GMA_SYMLINK_POLICY_ERROR,
+} GmaSymlinkPolicy;
+
+// An opened GMA, with its metadata and entry list already read
+typedef struct GmaFile GmaFile;
+
+// An entry of an opened GMA. `path` is owned by the GmaFile and lives until it is closed.
+typedef struct GmaEntry {
+  const char *path;
+  uint64_t size;
+  uint32_t crc;
+} GmaEntry;
+
+// Options for gma_create and gma_verify. Passing null instead uses the defaults, which are all zero.
+typedef struct GmaPackOptions {
+  // Leave out files matched by the folder's .gitignore as well as its .gmaignore
+  bool honor_gitignore;
+  enum GmaSymlinkPolicy symlinks;
+} GmaPackOptions;
+
+// Called by gma_verify with each file that isn't allowed by the whitelist, relative to the folder
+typedef void (*GmaVerifyCallback)(const char *path, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Opens the GMA at `path` and reads its metadata and entry list
+//
+// # Safety
+//
+// `path` must be a null terminated string and `out` must point to writable memory for a pointer.
+// On success, `*out` must later be freed with gma_close.
+enum GmaResult gma_open(const char *path, struct GmaFile **out);
+
+// Closes a GMA opened by gma_open. Does nothing if `gma` is null.
+//
+// # Safety
+//
+// `gma` must have come from gma_open and not have been closed already.
+void gma_close(struct GmaFile *gma);
+
+// The title of the addon. The string is owned by `gma` and lives until it is closed.
+//
+// # Safety
+//
+// `gma` must be an open GmaFile.
+const char *gma_title(const struct GmaFile *gma);
+
+// The number of entries in the GMA
+//
+// # Safety
+//
+// `gma` must be an open GmaFile.
+size_t gma_entry_count(const struct GmaFile *gma);
+
+// Gets an entry by its index, from 0 to gma_entry_count. Entries are sorted by path.
+//
+// # Safety
+//
+// `gma` must be an open GmaFile and `out` must point to a writable GmaEntry.
+enum GmaResult gma_entry(const struct GmaFile *gma, size_t index, struct GmaEntry *out);
+
+// Reads the contents of the entry at `entry_path` into a new buffer, which must be freed with gma_free_bytes
+//
+// # Safety
+//
+// `gma` must be an open GmaFile, `entry_path` a null terminated string, and `out_data` and `out_len` must point to writable memory.
+enum GmaResult gma_read_entry(const struct GmaFile *gma,
+                              const char *entry_path,
+                              uint8_t **out_data,
+                              size_t *out_len);
+
+// Frees a buffer returned by gma_read_entry. Does nothing if `data` is null.
+//
+// # Safety
+//
+// `data` and `len` must be exactly what gma_read_entry returned, and not have been freed already.
+void gma_free_bytes(uint8_t *data, size_t len);
+
+// The message of the last libgma error on this thread, or null if there hasn't been one
+//
+// The string is owned by libgma and lives until the next error on the same thread.
+const char *gma_last_error(void);
+
+// The file responsible for the last libgma error on this thread, for GMA_RESULT_PATH_IO_ERROR and GMA_RESULT_SYMLINK, or null
+//
+// The string is owned by libgma and lives until the next error on the same thread.
+const char *gma_last_error_path(void);
+
+// Packs the folder `src_dir` into a GMA at `out_path`
+//
+// The addon's metadata comes from `addon_json`, a JSON string in the format of addon.json, or from `src_dir`/addon.json if it is null.
+// Files ignored by `src_dir`/.gmaignore or not allowed by the whitelist are left out; use gma_verify to find the latter first.
+//
+// # Safety
+//
+// `src_dir` and `out_path` must be null terminated strings. `addon_json` must be a null terminated string or null,
+// and `options` must point to a GmaPackOptions or be null.
+enum GmaResult gma_create(const char *src_dir,
+                          const char *out_path,
+                          const char *addon_json,
+                          const struct GmaPackOptions *options);
+
+// Checks the folder `src_dir` against the addon whitelist, before packing it with gma_create
+//
+// `callback`, if not null, is called with each file that isn't allowed, and `out_invalid`, if not null, is set to how many there are.
+// Files ignored by default, by `src_dir`/addon.json or by `src_dir`/.gmaignore aren't checked.
+//
+// # Safety
+//
+// `src_dir` must be a null terminated string. `options` must point to a GmaPackOptions or be null, and `out_invalid` must be null or point to a writable size_t.
+enum GmaResult gma_verify(const char *src_dir,
+                          const struct GmaPackOptions *options,
+                          GmaVerifyCallback callback,
+                          void *user_data,
+                          size_t *out_invalid);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LIBGMA_H */
@@ -0,0 +1,600 @@
+//! C ABI for libgma
+//!
+//! Every function returns a [`GmaResult`] (or a value that can't fail) and writes its output through pointers.
+//! Strings are null terminated UTF-8. Anything libgma allocates must be handed back to it to be freed.
+
+use std::{
+	cell::RefCell,
+	ffi::{c_char, c_void, CStr, CString},
+	path::{Path, PathBuf},
+	ptr,
+};
+
+use libgma::{whitelist, GMAError, GMAFile, GMAMetadata, PackOptions, SymlinkPolicy};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GmaResult {
+	Ok = 0,
+	IoError,
+	FormatError,
+	InvalidHeader,
+	EntryNotFound,
+	Lzma,
+	Cancelled,
+	/// A required pointer was null
+	NullPointer,
+	/// A string wasn't valid UTF-8
+	InvalidUtf8,
+	/// The addon.json is missing, malformed or has no title
+	InvalidMetadata,
+	/// Files in the folder only differ by case, so they would be packed into the same entry
+	CaseCollision,
+	/// A symbolic link was found where links aren't allowed, see gma_last_error_path
+	Symlink,
+	/// A file in the folder couldn't be read while packing it, see gma_last_error_path
+	PathIoError,
+}
+/// Also records the error for gma_last_error and gma_last_error_path
+impl From<GMAError> for GmaResult {
+	fn from(error: GMAError) -> Self {
+		let path = match &error {
+			GMAError::PathIO(path) => Some(path.to_string_lossy().into_owned()),
+			GMAError::Symlink(path) => Some(path.clone()),
+			_ => None,
+		};
+		LAST_ERROR.with(|last_error| {
+			*last_error.borrow_mut() = Some(LastError {
+				message: c_string_lossy(&error.to_string()),
+				path: path.as_deref().map(c_string_lossy),
+			})
+		});
+
+		match error {
+			GMAError::IOError => GmaResult::IoError,
+			GMAError::PathIO(_) => GmaResult::PathIoError,
+			GMAError::FormatError => GmaResult::FormatError,
+			GMAError::InvalidHeader => GmaResult::InvalidHeader,
+			GMAError::EntryNotFound => GmaResult::EntryNotFound,
+			GMAError::LZMA => GmaResult::Lzma,
+			GMAError::Cancelled => GmaResult::Cancelled,
+			GMAError::CaseCollision(_) => GmaResult::CaseCollision,
+			GMAError::Symlink(_) => GmaResult::Symlink,
+		}
+	}
+}
+
+struct LastError {
+	message: CString,
+	path: Option<CString>,
+}
+
+thread_local! {
+	static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+fn c_string_lossy(str: &str) -> CString {
+	CString::new(str.replace('\0', "")).unwrap()
+}
+
+/// What to do with symbolic links in the folder being packed
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GmaSymlinkPolicy {
+	/// Pack whatever links point to, as long as it's inside the folder
+	Follow = 0,
+	/// Leave links out of the addon
+	Skip,
+	/// Fail with GMA_RESULT_SYMLINK if the folder contains links
+	Error,
+}
+
+/// Options for gma_create and gma_verify. Passing null instead uses the defaults, which are all zero.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GmaPackOptions {
+	/// Leave out files matched by the folder's .gitignore as well as its .gmaignore
+	pub honor_gitignore: bool,
+	pub symlinks: GmaSymlinkPolicy,
+}
+
+unsafe fn pack_options(options: *const GmaPackOptions) -> PackOptions {
+	match options.as_ref() {
+		Some(options) => PackOptions {
+			honor_gitignore: options.honor_gitignore,
+			symlinks: match options.symlinks {
+				GmaSymlinkPolicy::Follow => SymlinkPolicy::Follow,
+				GmaSymlinkPolicy::Skip => SymlinkPolicy::Skip,
+				GmaSymlinkPolicy::Error => SymlinkPolicy::Error,
+			},
+			..Default::default()
+		},
+		None => PackOptions::default(),
+	}
+}
+
+macro_rules! try_ffi {
+	( $x:expr ) => {
+		match $x {
+			Ok(x) => x,
+			Err(err) => return GmaResult::from(err),
+		}
+	};
+}
+
+/// An opened GMA, with its metadata and entry list already read
+pub struct GmaFile {
+	gma: GMAFile,
+	title: CString,
+	entry_paths: Vec<CString>,
+}
+
+/// An entry of an opened GMA. `path` is owned by the GmaFile and lives until it is closed.
+#[repr(C)]
+pub struct GmaEntry {
+	pub path: *const c_char,
+	pub size: u64,
+	pub crc: u32,
+}
+
+/// Called by gma_verify with each file that isn't allowed by the whitelist, relative to the folder
+pub type GmaVerifyCallback = Option<extern "C" fn(path: *const c_char, user_data: *mut c_void)>;
+
+unsafe fn str_arg<'a>(str: *const c_char) -> Result<&'a str, GmaResult> {
+	if str.is_null() {
+		Err(GmaResult::NullPointer)
+	} else {
+		CStr::from_ptr(str).to_str().map_err(|_| GmaResult::InvalidUtf8)
+	}
+}
+
+unsafe fn path_arg(path: *const c_char) -> Result<PathBuf, GmaResult> {
+	str_arg(path).map(PathBuf::from)
+}
+
+fn read_addon_json(src_dir: &Path) -> Result<GMAMetadata, GmaResult> {
+	let json = std::fs::read_to_string(src_dir.join("addon.json")).map_err(|_| GmaResult::InvalidMetadata)?;
+	parse_addon_json(&json)
+}
+
+fn parse_addon_json(json: &str) -> Result<GMAMetadata, GmaResult> {
+	match serde_json::from_str::<GMAMetadata>(json) {
+		Ok(metadata @ GMAMetadata::Standard { .. }) if !metadata.title().is_empty() => Ok(metadata),
+		_ => Err(GmaResult::InvalidMetadata),
+	}
+}
+
+/// Opens the GMA at `path` and reads its metadata and entry list
+///
+/// # Safety
+///
+/// `path` must be a null terminated string and `out` must point to writable memory for a pointer.
+/// On success, `*out` must later be freed with gma_close.
+#[no_mangle]
+pub unsafe extern "C" fn gma_open(path: *const c_char, out: *mut *mut GmaFile) -> GmaResult {
+	if out.is_null() {
+		return GmaResult::NullPointer;
+	}
+	*out = ptr::null_mut();
+
+	let path = try_ffi!(path_arg(path));
+
+	let mut gma = try_ffi!(GMAFile::open(path));
+	try_ffi!(gma.entries());
+
+	let title = CString::new(
+		gma.metadata
+			.as_ref()
+			.map(|metadata| metadata.title())
+			.unwrap_or_default()
+			.replace('\0', ""),
+	)
+	.unwrap();
+
+	let mut entries: Vec<&String> = gma.entries.as_ref().unwrap().keys().collect();
+	entries.sort_unstable();
+	let entry_paths = entries.into_iter().map(|path| c_string_lossy(path)).collect();
+
+	*out = Box::into_raw(Box::new(GmaFile { gma, title, entry_paths }));
+
+	GmaResult::Ok
+}
+
+/// Closes a GMA opened by gma_open. Does nothing if `gma` is null.
+///
+/// # Safety
+///
+/// `gma` must have come from gma_open and not have been closed already.
+#[no_mangle]
+pub unsafe extern "C" fn gma_close(gma: *mut GmaFile) {
+	if !gma.is_null() {
+		drop(Box::from_raw(gma));
+	}
+}
+
+/// The title of the addon. The string is owned by `gma` and lives until it is closed.
+///
+/// # Safety
+///
+/// `gma` must be an open GmaFile.
+#[no_mangle]
+pub unsafe extern "C" fn gma_title(gma: *const GmaFile) -> *const c_char {
+	match gma.as_ref() {
+		Some(gma) => gma.title.as_ptr(),
+		None => ptr::null(),
+	}
+}
+
+/// The number of entries in the GMA
+///
+/// # Safety
+///
+/// `gma` must be an open GmaFile.
+#[no_mangle]
+pub unsafe extern "C" fn gma_entry_count(gma: *const GmaFile) -> usize {
+	match gma.as_ref() {
+		Some(gma) => gma.entry_paths.len(),
+		None => 0,
+	}
+}
+
+/// Gets an entry by its index, from 0 to gma_entry_count. Entries are sorted by path.
+///
+/// # Safety
+///
+/// `gma` must be an open GmaFile and `out` must point to a writable GmaEntry.
+#[no_mangle]
+pub unsafe extern "C" fn gma_entry(gma: *const GmaFile, index: usize, out: *mut GmaEntry) -> GmaResult {
+	let (Some(gma), Some(out)) = (gma.as_ref(), out.as_mut()) else {
+		return GmaResult::NullPointer;
+	};
+
+	let Some(path) = gma.entry_paths.get(index) else {
+		return GmaResult::EntryNotFound;
+	};
+
+	let entry = &gma.gma.entries.as_ref().unwrap()[path.to_str().unwrap()];
+
+	*out = GmaEntry {
+		path: path.as_ptr(),
+		size: entry.size,
+		crc: entry.crc,
+	};
+
+	GmaResult::Ok
+}
+
+/// Reads the contents of the entry at `entry_path` into a new buffer, which must be freed with gma_free_bytes
+///
+/// # Safety
+///
+/// `gma` must be an open GmaFile, `entry_path` a null terminated string, and `out_data` and `out_len` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn gma_read_entry(gma: *const GmaFile, entry_path: *const c_char, out_data: *mut *mut u8, out_len: *mut usize) -> GmaResult {
+	let (Some(gma), false, false) = (gma.as_ref(), out_data.is_null(), out_len.is_null()) else {
+		return GmaResult::NullPointer;
+	};
+	*out_data = ptr::null_mut();
+	*out_len = 0;
+
+	let entry_path = try_ffi!(str_arg(entry_path));
+
+	let contents = try_ffi!(gma.gma.read_entry(entry_path)).into_boxed_slice();
+
+	*out_len = contents.len();
+	*out_data = Box::into_raw(contents) as *mut u8;
+
+	GmaResult::Ok
+}
+
+/// Frees a buffer returned by gma_read_entry. Does nothing if `data` is null.
+///
+/// # Safety
+///
+/// `data` and `len` must be exactly what gma_read_entry returned, and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn gma_free_bytes(data: *mut u8, len: usize) {
+	if !data.is_null() {
+		drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+	}
+}
+
+/// The message of the last libgma error on this thread, or null if there hasn't been one
+///
+/// The string is owned by libgma and lives until the next error on the same thread.
+#[no_mangle]
+pub extern "C" fn gma_last_error() -> *const c_char {
+	LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+		Some(last_error) => last_error.message.as_ptr(),
+		None => ptr::null(),
+	})
+}
+
+/// The file responsible for the last libgma error on this thread, for GMA_RESULT_PATH_IO_ERROR and GMA_RESULT_SYMLINK, or null
+///
+/// The string is owned by libgma and lives until the next error on the same thread.
+#[no_mangle]
+pub extern "C" fn gma_last_error_path() -> *const c_char {
+	LAST_ERROR.with(|last_error| match &*last_error.borrow() {
+		Some(LastError { path: Some(path), .. }) => path.as_ptr(),
+		_ => ptr::null(),
+	})
+}
+
+/// Packs the folder `src_dir` into a GMA at `out_path`
+///
+/// The addon's metadata comes from `addon_json`, a JSON string in the format of addon.json, or from `src_dir`/addon.json if it is null.
+/// Files ignored by `src_dir`/.gmaignore or not allowed by the whitelist are left out; use gma_verify to find the latter first.
+///
+/// # Safety
+///
+/// `src_dir` and `out_path` must be null terminated strings. `addon_json` must be a null terminated string or null,
+/// and `options` must point to a GmaPackOptions or be null.
+#[no_mangle]
+pub unsafe extern "C" fn gma_create(
+	src_dir: *const c_char,
+	out_path: *const c_char,
+	addon_json: *const c_char,
+	options: *const GmaPackOptions,
+) -> GmaResult {
+	let src_dir = try_ffi!(path_arg(src_dir));
+	let out_path = try_ffi!(path_arg(out_path));
+
+	let metadata = if addon_json.is_null() {
+		try_ffi!(read_addon_json(&src_dir))
+	} else {
+		try_ffi!(parse_addon_json(try_ffi!(str_arg(addon_json))))
+	};
+
+	try_ffi!(GMAFile::new(out_path, metadata).create(&src_dir, &pack_options(options), &()));
+
+	GmaResult::Ok
+}
+
+/// Checks the folder `src_dir` against the addon whitelist, before packing it with gma_create
+///
+/// `callback`, if not null, is called with each file that isn't allowed, and `out_invalid`, if not null, is set to how many there are.
+/// Files ignored by default, by `src_dir`/addon.json or by `src_dir`/.gmaignore aren't checked.
+///
+/// # Safety
+///
+/// `src_dir` must be a null terminated string. `options` must point to a GmaPackOptions or be null, and `out_invalid` must be null or point to a writable size_t.
+#[no_mangle]
+pub unsafe extern "C" fn gma_verify(
+	src_dir: *const c_char,
+	options: *const GmaPackOptions,
+	callback: GmaVerifyCallback,
+	user_data: *mut c_void,
+	out_invalid: *mut usize,
+) -> GmaResult {
+	let src_dir = try_ffi!(path_arg(src_dir));
+	if !src_dir.is_dir() {
+		return GmaResult::IoError;
+	}
+
+	let ignore = read_addon_json(&src_dir)
+		.ok()
+		.and_then(|metadata| metadata.ignore().cloned())
+		.unwrap_or_default();
+
+	let invalid = try_ffi!(whitelist::verify(&src_dir, &ignore, &pack_options(options)));
+
+	if let Some(callback) = callback {
+		for path in &invalid {
+			let path = c_string_lossy(path);
+			callback(path.as_ptr(), user_data);
+		}
+	}
+
+	if let Some(out_invalid) = out_invalid.as_mut() {
+		*out_invalid = invalid.len();
+	}
+
+	GmaResult::Ok
+}
+
+// These call the exported functions the same way a C caller would, through raw pointers and null terminated strings
+
+#[cfg(test)]
+fn test_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("libgma_ffi_{}_{}", name, std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("src/lua/autorun")).unwrap();
+	std::fs::write(dir.join("src/lua/autorun/hello.lua"), "print(\"hello\")").unwrap();
+	std::fs::write(dir.join("src/materials.exe"), "").unwrap();
+	std::fs::write(
+		dir.join("src/addon.json"),
+		r#"{"title": "FFI Test", "type": "tool", "tags": ["fun"], "ignore": ["*.psd"]}"#,
+	)
+	.unwrap();
+	std::fs::write(dir.join("src/ignored.psd"), "").unwrap();
+	std::fs::write(dir.join("src/.gmaignore"), "/notes/\n").unwrap();
+	std::fs::create_dir_all(dir.join("src/notes")).unwrap();
+	std::fs::write(dir.join("src/notes/todo.exe"), "").unwrap();
+	dir
+}
+
+#[cfg(test)]
+fn c_string(path: &Path) -> CString {
+	CString::new(path.to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn test_create_open_read() {
+	let dir = test_dir("create_open_read");
+	let src = c_string(&dir.join("src"));
+	let out = c_string(&dir.join("test.gma"));
+
+	unsafe {
+		assert_eq!(gma_create(src.as_ptr(), out.as_ptr(), ptr::null(), ptr::null()), GmaResult::Ok);
+
+		let mut gma: *mut GmaFile = ptr::null_mut();
+		assert_eq!(gma_open(out.as_ptr(), &mut gma), GmaResult::Ok);
+		assert!(!gma.is_null());
+
+		assert_eq!(CStr::from_ptr(gma_title(gma)).to_str().unwrap(), "FFI Test");
+		assert_eq!(gma_entry_count(gma), 1);
+
+		let mut entry = GmaEntry {
+			path: ptr::null(),
+			size: 0,
+			crc: 0,
+		};
+		assert_eq!(gma_entry(gma, 0, &mut entry), GmaResult::Ok);
+		assert_eq!(CStr::from_ptr(entry.path).to_str().unwrap(), "lua/autorun/hello.lua");
+		assert_eq!(entry.size, 14);
+		assert_eq!(gma_entry(gma, 1, &mut entry), GmaResult::EntryNotFound);
+
+		let mut data: *mut u8 = ptr::null_mut();
+		let mut len: usize = 0;
+		assert_eq!(gma_read_entry(gma, entry.path, &mut data, &mut len), GmaResult::Ok);
+		assert_eq!(std::slice::from_raw_parts(data, len), b"print(\"hello\")");
+		gma_free_bytes(data, len);
+
+		let missing = CString::new("lua/missing.lua").unwrap();
+		assert_eq!(gma_read_entry(gma, missing.as_ptr(), &mut data, &mut len), GmaResult::EntryNotFound);
+		assert!(data.is_null());
+
+		gma_close(gma);
+	}
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_create_with_addon_json() {
+	let dir = test_dir("create_with_addon_json");
+	let src = c_string(&dir.join("src"));
+	let out = c_string(&dir.join("test.gma"));
+
+	unsafe {
+		let no_title = CString::new(r#"{"title": "", "type": "tool", "tags": []}"#).unwrap();
+		assert_eq!(
+			gma_create(src.as_ptr(), out.as_ptr(), no_title.as_ptr(), ptr::null()),
+			GmaResult::InvalidMetadata
+		);
+
+		let addon_json = CString::new(r#"{"title": "Overridden", "type": "tool", "tags": []}"#).unwrap();
+		assert_eq!(gma_create(src.as_ptr(), out.as_ptr(), addon_json.as_ptr(), ptr::null()), GmaResult::Ok);
+
+		let mut gma: *mut GmaFile = ptr::null_mut();
+		assert_eq!(gma_open(out.as_ptr(), &mut gma), GmaResult::Ok);
+		assert_eq!(CStr::from_ptr(gma_title(gma)).to_str().unwrap(), "Overridden");
+		gma_close(gma);
+	}
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_verify() {
+	extern "C" fn collect(path: *const c_char, user_data: *mut c_void) {
+		let invalid = unsafe { &mut *(user_data as *mut Vec<String>) };
+		invalid.push(unsafe { CStr::from_ptr(path) }.to_str().unwrap().to_owned());
+	}
+
+	let dir = test_dir("verify");
+	let src = c_string(&dir.join("src"));
+
+	let mut invalid: Vec<String> = Vec::new();
+	let mut invalid_count: usize = 0;
+	unsafe {
+		assert_eq!(
+			gma_verify(
+				src.as_ptr(),
+				ptr::null(),
+				Some(collect),
+				&mut invalid as *mut _ as *mut c_void,
+				&mut invalid_count
+			),
+			GmaResult::Ok
+		);
+		assert_eq!(
+			gma_verify(src.as_ptr(), ptr::null(), None, ptr::null_mut(), ptr::null_mut()),
+			GmaResult::Ok
+		);
+	}
+
+	assert_eq!(invalid, vec!["materials.exe".to_string()]);
+	assert_eq!(invalid_count, 1);
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_errors() {
+	unsafe {
+		let mut gma: *mut GmaFile = ptr::null_mut();
+		assert_eq!(gma_open(ptr::null(), &mut gma), GmaResult::NullPointer);
+
+		let missing = CString::new("/this/gma/does/not/exist.gma").unwrap();
+		assert_eq!(gma_open(missing.as_ptr(), &mut gma), GmaResult::IoError);
+		assert!(gma.is_null());
+
+		assert_eq!(gma_open(missing.as_ptr(), ptr::null_mut()), GmaResult::NullPointer);
+
+		let invalid_utf8 = [0xFFu8, 0xFE, 0];
+		assert_eq!(gma_open(invalid_utf8.as_ptr() as *const c_char, &mut gma), GmaResult::InvalidUtf8);
+
+		assert!(gma_title(ptr::null()).is_null());
+		assert_eq!(gma_entry_count(ptr::null()), 0);
+		gma_close(ptr::null_mut());
+		gma_free_bytes(ptr::null_mut(), 0);
+	}
+}
+
+#[test]
+#[cfg(unix)]
+fn test_options() {
+	let dir = test_dir("options");
+	let src = c_string(&dir.join("src"));
+	let out = c_string(&dir.join("test.gma"));
+	std::os::unix::fs::symlink(dir.join("src/lua/autorun/hello.lua"), dir.join("src/lua/autorun/link.lua")).unwrap();
+	std::fs::write(dir.join("src/.gitignore"), "materials.exe\n").unwrap();
+
+	unsafe {
+		let error = GmaPackOptions {
+			honor_gitignore: false,
+			symlinks: GmaSymlinkPolicy::Error,
+		};
+		assert_eq!(gma_create(src.as_ptr(), out.as_ptr(), ptr::null(), &error), GmaResult::Symlink);
+		assert_eq!(CStr::from_ptr(gma_last_error_path()).to_str().unwrap(), "lua/autorun/link.lua");
+		assert!(!gma_last_error().is_null());
+
+		let skip = GmaPackOptions {
+			honor_gitignore: true,
+			symlinks: GmaSymlinkPolicy::Skip,
+		};
+		let mut invalid_count: usize = 0;
+		assert_eq!(gma_verify(src.as_ptr(), &skip, None, ptr::null_mut(), &mut invalid_count), GmaResult::Ok);
+		assert_eq!(invalid_count, 0);
+
+		assert_eq!(gma_create(src.as_ptr(), out.as_ptr(), ptr::null(), &skip), GmaResult::Ok);
+		let mut gma: *mut GmaFile = ptr::null_mut();
+		assert_eq!(gma_open(out.as_ptr(), &mut gma), GmaResult::Ok);
+		assert_eq!(gma_entry_count(gma), 1);
+		gma_close(gma);
+	}
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+// include/gma.h is checked in so that C callers don't need cbindgen
+#[test]
+fn test_header() {
+	let crate_dir = env!("CARGO_MANIFEST_DIR");
+	let mut header = Vec::new();
+	cbindgen::generate(crate_dir)
+		.expect("Failed to generate include/gma.h")
+		.write(&mut header);
+
+	let path = Path::new(crate_dir).join("include/gma.h");
+	if std::env::var_os("UPDATE_GMA_H").is_some() {
+		std::fs::write(&path, &header).unwrap();
+	} else {
+		assert!(
+			std::fs::read(&path).unwrap() == header,
+			"include/gma.h is out of date, regenerate it with `UPDATE_GMA_H=1 cargo test -p libgma-ffi`"
+		);
+	}
+}
@@ -0,0 +1,233 @@
+//! Validation of addon.json, the same way gmad and the Workshop check it
+//!
+//! [`GMAMetadata`](crate::GMAMetadata) accepts anything that looks roughly right, so this is where mistakes get caught before Steam rejects the addon.
+
+use std::fmt::Display;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// The addon types the Workshop accepts
+pub const ADDON_TYPES: &[&str] = &[
+	"gamemode",
+	"map",
+	"weapon",
+	"vehicle",
+	"npc",
+	"entity",
+	"tool",
+	"effects",
+	"model",
+	"servercontent",
+];
+
+/// The addon tags the Workshop accepts
+pub const ADDON_TAGS: &[&str] = &["fun", "roleplay", "scenic", "movie", "realism", "cartoon", "water", "comic", "build"];
+
+pub const MAX_TAGS: usize = 2;
+
+/// The keys gmad reads from addon.json
+const KNOWN_KEYS: &[&str] = &["title", "description", "type", "tags", "ignore"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum AddonJsonIssue {
+	/// The file isn't valid JSON, or isn't a JSON object
+	Malformed {
+		message: String,
+		line: usize,
+		column: usize,
+	},
+	MissingTitle,
+	MissingType,
+	InvalidType {
+		addon_type: String,
+	},
+	InvalidTag {
+		tag: String,
+	},
+	DuplicateTag {
+		tag: String,
+	},
+	TooManyTags {
+		count: usize,
+	},
+	/// `key` has the wrong kind of value, for example a string where an array was expected
+	WrongValue {
+		key: String,
+		expected: &'static str,
+	},
+	/// A key gmad doesn't know about, which is ignored. Only a warning.
+	UnknownKey {
+		key: String,
+	},
+}
+impl AddonJsonIssue {
+	/// Whether this issue stops the addon from being packed or published
+	pub fn is_error(&self) -> bool {
+		!matches!(self, AddonJsonIssue::UnknownKey { .. })
+	}
+}
+impl Display for AddonJsonIssue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		use AddonJsonIssue::*;
+		match self {
+			Malformed { message, line, column } => write!(f, "malformed JSON at line {}, column {}: {}", line, column, message),
+			MissingTitle => write!(f, "\"title\" is missing or empty"),
+			MissingType => write!(f, "\"type\" is missing, expected one of: {}", ADDON_TYPES.join(", ")),
+			InvalidType { addon_type } => write!(f, "\"{}\" is not a valid type, expected one of: {}", addon_type, ADDON_TYPES.join(", ")),
+			InvalidTag { tag } => write!(f, "\"{}\" is not a valid tag, expected any of: {}", tag, ADDON_TAGS.join(", ")),
+			DuplicateTag { tag } => write!(f, "the tag \"{}\" is used more than once", tag),
+			TooManyTags { count } => write!(f, "{} tags were given, but only {} are allowed", count, MAX_TAGS),
+			WrongValue { key, expected } => write!(f, "\"{}\" should be {}", key, expected),
+			UnknownKey { key } => write!(f, "unknown key \"{}\" will be ignored", key),
+		}
+	}
+}
+
+/// Checks the contents of an addon.json, returning every problem found
+pub fn validate(json: &str) -> Vec<AddonJsonIssue> {
+	let object = match serde_json::from_str::<Value>(json) {
+		Ok(Value::Object(object)) => object,
+		Ok(_) => {
+			return vec![AddonJsonIssue::Malformed {
+				message: "expected an object".to_string(),
+				line: 1,
+				column: 1,
+			}]
+		}
+		Err(err) => {
+			return vec![AddonJsonIssue::Malformed {
+				message: err.to_string(),
+				line: err.line(),
+				column: err.column(),
+			}]
+		}
+	};
+
+	let mut issues = Vec::new();
+
+	let mut string = |key: &str| match object.get(key) {
+		None | Some(Value::Null) => None,
+		Some(Value::String(value)) => Some(value.trim().to_owned()),
+		Some(_) => {
+			issues.push(AddonJsonIssue::WrongValue {
+				key: key.to_owned(),
+				expected: "a string",
+			});
+			None
+		}
+	};
+
+	let title = string("title");
+	let addon_type = string("type");
+	string("description");
+
+	if title.as_deref().is_none_or(str::is_empty) {
+		issues.push(AddonJsonIssue::MissingTitle);
+	}
+
+	let mut tags = Vec::new();
+	for key in ["tags", "ignore"] {
+		match object.get(key) {
+			None | Some(Value::Null) => {}
+			Some(Value::Array(values)) if values.iter().all(Value::is_string) => {
+				if key == "tags" {
+					tags.extend(values.iter().filter_map(Value::as_str).map(str::to_owned));
+				}
+			}
+			Some(_) => issues.push(AddonJsonIssue::WrongValue {
+				key: key.to_owned(),
+				expected: "an array of strings",
+			}),
+		}
+	}
+
+	issues.extend(validate_metadata(addon_type.as_deref().unwrap_or_default(), &tags));
+
+	let mut unknown: Vec<&String> = object.keys().filter(|key| !KNOWN_KEYS.contains(&key.as_str())).collect();
+	unknown.sort_unstable();
+	issues.extend(unknown.into_iter().map(|key| AddonJsonIssue::UnknownKey { key: key.to_owned() }));
+
+	issues
+}
+
+/// Checks an addon's type and tags, for metadata that doesn't come from an addon.json
+pub fn validate_metadata<S: AsRef<str>>(addon_type: &str, tags: &[S]) -> Vec<AddonJsonIssue> {
+	let mut issues = Vec::new();
+
+	if addon_type.is_empty() {
+		issues.push(AddonJsonIssue::MissingType);
+	} else if !ADDON_TYPES.iter().any(|valid| valid.eq_ignore_ascii_case(addon_type)) {
+		issues.push(AddonJsonIssue::InvalidType {
+			addon_type: addon_type.to_owned(),
+		});
+	}
+
+	let mut seen: Vec<String> = Vec::with_capacity(tags.len());
+	for tag in tags {
+		let tag = tag.as_ref();
+		let lowercase = tag.to_lowercase();
+		if !ADDON_TAGS.contains(&lowercase.as_str()) {
+			issues.push(AddonJsonIssue::InvalidTag { tag: tag.to_owned() });
+		} else if seen.contains(&lowercase) {
+			issues.push(AddonJsonIssue::DuplicateTag { tag: tag.to_owned() });
+		}
+		seen.push(lowercase);
+	}
+
+	if tags.len() > MAX_TAGS {
+		issues.push(AddonJsonIssue::TooManyTags { count: tags.len() });
+	}
+
+	issues
+}
+
+#[test]
+fn test_validate() {
+	assert_eq!(
+		validate(r#"{ "title": "My Addon", "type": "ServerContent", "tags": ["fun", "Build"], "ignore": ["*.psd"], "description": "hi" }"#),
+		[]
+	);
+
+	assert_eq!(
+		validate(r#"{ "title": "", "type": "weapons", "tags": ["fun", "guns", "fun"], "workshopid": 1 }"#),
+		[
+			AddonJsonIssue::MissingTitle,
+			AddonJsonIssue::InvalidType {
+				addon_type: "weapons".to_string()
+			},
+			AddonJsonIssue::InvalidTag { tag: "guns".to_string() },
+			AddonJsonIssue::DuplicateTag { tag: "fun".to_string() },
+			AddonJsonIssue::TooManyTags { count: 3 },
+			AddonJsonIssue::UnknownKey {
+				key: "workshopid".to_string()
+			},
+		]
+	);
+
+	assert_eq!(
+		validate(r#"{ "title": 5, "tags": "fun" }"#),
+		[
+			AddonJsonIssue::WrongValue {
+				key: "title".to_string(),
+				expected: "a string"
+			},
+			AddonJsonIssue::MissingTitle,
+			AddonJsonIssue::WrongValue {
+				key: "tags".to_string(),
+				expected: "an array of strings"
+			},
+			AddonJsonIssue::MissingType,
+		]
+	);
+
+	match validate("{\n\t\"title\": \"My Addon\",\n\t\"type\": \"tool\"\n\t\"tags\": []\n}").as_slice() {
+		[AddonJsonIssue::Malformed { line: 4, column: 2, .. }] => {}
+		issues => panic!("{:?}", issues),
+	}
+	assert!(matches!(validate("[]").as_slice(), [AddonJsonIssue::Malformed { .. }]));
+
+	assert!(!AddonJsonIssue::UnknownKey { key: String::new() }.is_error());
+	assert!(AddonJsonIssue::MissingTitle.is_error());
+}
@@ -0,0 +1,226 @@
+//! Explains why a file is or isn't packed into a GMA, down to the exact rule responsible
+//!
+//! Rules are checked in the same order as when verifying and packing an addon:
+//! the `.gmaignore`/`.gitignore`, [`DEFAULT_IGNORE`](whitelist::DEFAULT_IGNORE), the user's ignore patterns, the addon.json `ignore` list and finally the whitelist.
+
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+	gmaignore::ProjectIgnore,
+	whitelist::{self, IgnoreList},
+	GMAMetadata, PackOptions,
+};
+
+/// The rule that decided a file's fate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum Rule {
+	/// A pattern in the addon whitelist allowed the file
+	Whitelist { glob: String },
+	/// An `!` exclusion in the addon whitelist disallowed the file
+	WhitelistExclusion { glob: String },
+	/// A [`DEFAULT_IGNORE`](whitelist::DEFAULT_IGNORE) pattern
+	DefaultIgnore { glob: String },
+	/// One of the user's own ignore patterns
+	UserIgnore { glob: String },
+	/// A pattern in the addon.json `ignore` list
+	AddonJsonIgnore { glob: String },
+	/// A line of the addon folder's `.gmaignore` or `.gitignore`
+	ProjectIgnore { file: PathBuf, glob: String },
+}
+
+/// What will happen to a file when the addon is packed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum Decision {
+	Included {
+		rule: Rule,
+	},
+	Ignored {
+		rule: Rule,
+	},
+	/// `rule` is the whitelist exclusion responsible, or `None` if no whitelist pattern matches the file at all
+	NotWhitelisted {
+		rule: Option<Rule>,
+	},
+}
+impl Decision {
+	pub fn is_included(&self) -> bool {
+		matches!(self, Decision::Included { .. })
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Explanation {
+	/// The path as it would appear in the GMA
+	pub path: String,
+	#[serde(flatten)]
+	pub decision: Decision,
+}
+
+/// Reads the `ignore` list from the addon.json in `src_path`, if there is one
+pub fn addon_json_ignore<P: AsRef<Path>>(src_path: P) -> Vec<String> {
+	std::fs::read_to_string(src_path.as_ref().join("addon.json"))
+		.ok()
+		.and_then(|json| serde_json::from_str::<GMAMetadata>(&json).ok())
+		.and_then(|metadata| metadata.ignore().cloned())
+		.unwrap_or_default()
+}
+
+/// Explains the fate of files in one addon folder
+pub struct Explainer {
+	user_ignore: IgnoreList,
+	addon_json_ignore: IgnoreList,
+	project_ignore: ProjectIgnore,
+}
+impl Explainer {
+	/// `user_ignore` are the user's own patterns, `addon_json_ignore` is the addon.json `ignore` list (see [`addon_json_ignore`])
+	pub fn new<P: AsRef<Path>>(src_path: P, user_ignore: Vec<String>, addon_json_ignore: Vec<String>, options: &PackOptions) -> Explainer {
+		Explainer {
+			user_ignore: IgnoreList::new(&user_ignore),
+			addon_json_ignore: IgnoreList::new(&addon_json_ignore),
+			project_ignore: ProjectIgnore::new(src_path, options.honor_gitignore),
+		}
+	}
+
+	/// Explains the file at `relative_path`, relative to the addon folder
+	pub fn explain(&self, relative_path: &str) -> Explanation {
+		let path = relative_path.replace('\\', "/").trim_start_matches('/').to_lowercase();
+		let decision = self.decide(&path);
+		Explanation { path, decision }
+	}
+
+	fn decide(&self, path: &str) -> Decision {
+		if let Some((file, glob)) = self.project_ignore.matched(path, false) {
+			return Decision::Ignored {
+				rule: Rule::ProjectIgnore {
+					file: file.to_owned(),
+					glob: glob.to_owned(),
+				},
+			};
+		}
+
+		if let Some(glob) = whitelist::default_ignored_by(path) {
+			return Decision::Ignored {
+				rule: Rule::DefaultIgnore { glob: glob.to_string() },
+			};
+		}
+
+		if let Some(glob) = self.user_ignore.matched(path) {
+			return Decision::Ignored {
+				rule: Rule::UserIgnore { glob: glob.to_owned() },
+			};
+		}
+
+		if let Some(glob) = self.addon_json_ignore.matched(path) {
+			return Decision::Ignored {
+				rule: Rule::AddonJsonIgnore { glob: glob.to_owned() },
+			};
+		}
+
+		// Same logic as whitelist::check, remembering which pattern had the last word
+		let mut rule = None;
+		let mut valid = false;
+		let addon_whitelist = whitelist::current_addon_whitelist();
+		for (pattern, (exclusion, glob)) in addon_whitelist.patterns.iter().zip(&addon_whitelist.globs) {
+			if *exclusion {
+				if glob.matches(path) {
+					valid = false;
+					rule = Some(Rule::WhitelistExclusion { glob: pattern.to_string() });
+				}
+			} else if !valid && glob.matches(path) {
+				valid = true;
+				rule = Some(Rule::Whitelist { glob: pattern.to_string() });
+			}
+		}
+
+		match rule {
+			Some(rule) if valid => Decision::Included { rule },
+			rule => Decision::NotWhitelisted { rule },
+		}
+	}
+}
+
+#[test]
+fn test_explain() {
+	let dir = std::env::temp_dir().join(format!("libgma_test_explain_{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	std::fs::write(dir.join(".gmaignore"), "/notes/\n").unwrap();
+	std::fs::write(
+		dir.join("addon.json"),
+		r#"{"title": "Test", "type": "tool", "tags": [], "ignore": ["*.bak"]}"#,
+	)
+	.unwrap();
+
+	let explainer = Explainer::new(&dir, vec!["lua/dev/*".to_string()], addon_json_ignore(&dir), &PackOptions::default());
+
+	let decision = |path: &str| explainer.explain(path).decision;
+
+	assert_eq!(
+		decision("LUA\\autorun\\init.lua"),
+		Decision::Included {
+			rule: Rule::Whitelist {
+				glob: "lua/*.lua".to_string()
+			}
+		}
+	);
+	assert_eq!(explainer.explain("LUA\\autorun\\init.lua").path, "lua/autorun/init.lua");
+	assert_eq!(
+		decision("models/player.sw.vtx"),
+		Decision::Ignored {
+			rule: Rule::DefaultIgnore {
+				glob: "models/*.sw.vtx".to_string()
+			}
+		}
+	);
+	assert_eq!(
+		decision("models/weapons/gun.sw.vtx"),
+		Decision::Ignored {
+			rule: Rule::DefaultIgnore {
+				glob: "models/*.sw.vtx".to_string()
+			}
+		}
+	);
+	assert_eq!(
+		decision("gamemodes/test/nested/info.txt"),
+		Decision::NotWhitelisted {
+			rule: Some(Rule::WhitelistExclusion {
+				glob: "!gamemodes/*/*/*.txt".to_string()
+			})
+		}
+	);
+	assert_eq!(decision("lua/test.exe"), Decision::NotWhitelisted { rule: None });
+	assert_eq!(
+		decision("lua/dev/test.lua"),
+		Decision::Ignored {
+			rule: Rule::UserIgnore {
+				glob: "lua/dev/*".to_string()
+			}
+		}
+	);
+	assert_eq!(
+		decision("lua/test.bak"),
+		Decision::Ignored {
+			rule: Rule::AddonJsonIgnore { glob: "*.bak".to_string() }
+		}
+	);
+	assert_eq!(
+		decision("notes/todo.exe"),
+		Decision::Ignored {
+			rule: Rule::ProjectIgnore {
+				file: dir.join(".gmaignore"),
+				glob: "/notes/".to_string()
+			}
+		}
+	);
+	assert!(decision("materials/test.vmt").is_included());
+
+	assert_eq!(
+		serde_json::to_value(explainer.explain("lua/test.bak")).unwrap(),
+		serde_json::json!({ "path": "lua/test.bak", "decision": "ignored", "rule": { "source": "addon_json_ignore", "glob": "*.bak" } })
+	);
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,183 @@
+use std::{
+	fs::{self, File},
+	io::{BufWriter, Cursor, Read, SeekFrom, Write},
+	path::Path,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{io, whitelist, GMAEntry, GMAError, GMAFile, GMAMetadata, GMAReader, Progress};
+
+/// Counts the bytes read through it, so decompression progress can be reported
+struct CountingReader<R: Read> {
+	inner: R,
+	bytes_read: u64,
+}
+impl<R: Read> Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		let n = self.inner.read(buf)?;
+		self.bytes_read += n as u64;
+		Ok(n)
+	}
+}
+
+impl GMAFile {
+	/// Decompresses a GMA that has been compressed with LZMA, such as the .bin files that Steam downloads, into memory
+	pub fn decompress<P: AsRef<Path>>(path: P, progress: &dyn Progress) -> Result<GMAFile, GMAError> {
+		let input = File::open(path.as_ref())?;
+
+		let bytes_total = input.metadata().map(|metadata| metadata.len()).ok();
+
+		let lzma_decoder = xz2::stream::Stream::new_lzma_decoder(u64::MAX).map_err(|_| GMAError::LZMA)?;
+
+		let mut xz_decoder = xz2::read::XzDecoder::new_stream(CountingReader { inner: input, bytes_read: 0 }, lzma_decoder);
+
+		let mut output = if let Some(ref bytes_total) = bytes_total {
+			progress.total(*bytes_total);
+			Vec::with_capacity((*bytes_total * 5) as usize)
+		} else {
+			Vec::new()
+		};
+
+		let mut buf = vec![0u8; 65536];
+		let result = loop {
+			progress.pause_point();
+			if progress.cancelled() {
+				return Err(GMAError::Cancelled);
+			}
+
+			match xz_decoder.read(&mut buf) {
+				Ok(0) => break Ok(()),
+				Ok(n) => output.extend_from_slice(&buf[..n]),
+				Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+				Err(err) => break Err(err),
+			}
+
+			if let Some(bytes_total) = bytes_total {
+				progress.progress(xz_decoder.get_ref().bytes_read as f64 / bytes_total as f64);
+
+				let decompressed_bytes = output.len() as u64;
+				if decompressed_bytes > bytes_total {
+					progress.total(decompressed_bytes);
+				}
+			}
+		};
+
+		if result.is_err() {
+			// No idea why, but XZ always errors with "corrupt xz stream" even when the decompression succeeds.
+			// Maybe a difference in the way Gmod encoded the XZ stream?
+			// Let's just check if the file has been fully read, then naively continue.
+			let mut input = xz_decoder.into_inner().inner;
+			if input.read(&mut [0u8]).ok() != Some(0) {
+				return Err(GMAError::LZMA);
+			}
+		}
+
+		output.shrink_to_fit();
+
+		let decompressed_size = output.len() as u64;
+
+		let mut gma = GMAFile::read_header(GMAReader::MemBuffer(Cursor::new(output.into())), path)?;
+		gma.size = decompressed_size;
+
+		Ok(gma)
+	}
+
+	fn stream_entry_bytes(
+		handle: &mut GMAReader,
+		entries_start: u64,
+		entry_path: &Path,
+		entry: &GMAEntry,
+		progress: Option<&dyn Progress>,
+	) -> Result<(), GMAError> {
+		fs::create_dir_all(entry_path.with_file_name(""))?;
+		let f = File::create(entry_path)?;
+
+		handle.seek(SeekFrom::Start(entries_start + entry.index))?;
+
+		let mut w = BufWriter::new(f);
+		match progress {
+			Some(progress) => io::stream_bytes_with_progress(&mut **handle, &mut w, entry.size as usize, progress)?,
+			None => io::stream_bytes(&mut **handle, &mut w, entry.size as usize)?,
+		}
+
+		w.flush()?;
+
+		Ok(())
+	}
+
+	/// Extracts every entry into `dest`, followed by the addon.json if the GMA has one
+	///
+	/// Entries are extracted in parallel on the current rayon thread pool. [`GMAFile::entries`] must have been read first.
+	pub fn extract_to<P: AsRef<Path>>(&self, dest: P, ignore_whitelist: bool, progress: &dyn Progress) -> Result<(), GMAError> {
+		let dest = dest.as_ref();
+		let entries_start = self.pointers.entries;
+
+		let entries = self.entries.as_ref().expect("Expected entries to be read by this point");
+		let entries_len_f = entries.len() as f64;
+
+		self.read()?; // Don't waste time with the threads if the file fails to open
+
+		let i = AtomicUsize::new(0);
+
+		entries.par_iter().try_for_each(|(entry_path, entry)| -> Result<(), GMAError> {
+			progress.pause_point();
+			if progress.cancelled() {
+				return Err(GMAError::Cancelled);
+			}
+
+			if ignore_whitelist || whitelist::check(entry_path) {
+				let mut handle = self.read()?;
+
+				// FIXME count errors, check if errors == number of entries, return an error instead of finished
+				let _ = GMAFile::stream_entry_bytes(&mut handle, entries_start, &dest.join(entry_path), entry, None);
+			} else {
+				progress.skipped(entry_path);
+			}
+
+			let i = i.fetch_add(1, Ordering::AcqRel) + 1;
+			progress.progress((i as f64) / entries_len_f);
+
+			Ok(())
+		})?;
+
+		if progress.cancelled() {
+			return Err(GMAError::Cancelled);
+		}
+
+		if let Some(metadata @ GMAMetadata::Standard { .. }) = &self.metadata {
+			if let Ok(json) = serde_json::ser::to_string_pretty(metadata) {
+				let _ = fs::create_dir_all(dest);
+				let _ = fs::write(dest.join("addon.json"), json.as_bytes());
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Extracts a single entry to the file at `dest`
+	///
+	/// Pass in the handle returned by [`GMAFile::entries`], if any, to avoid opening the GMA again.
+	pub fn extract_entry_to<P: AsRef<Path>>(
+		&self,
+		entry_path: &str,
+		dest: P,
+		handle: Option<GMAReader>,
+		progress: &dyn Progress,
+	) -> Result<(), GMAError> {
+		let mut handle = match handle {
+			Some(handle) => handle,
+			None => self.read()?,
+		};
+
+		let entry = self
+			.entries
+			.as_ref()
+			.expect("Expected entries to be read by this point")
+			.get(entry_path)
+			.ok_or(GMAError::EntryNotFound)?;
+
+		GMAFile::stream_entry_bytes(&mut handle, self.pointers.entries, dest.as_ref(), entry, Some(progress))
+	}
+}
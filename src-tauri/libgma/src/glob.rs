@@ -0,0 +1,165 @@
+//! Glob patterns for the addon whitelist and ignore rules
+//!
+//! Globs match exactly like gmad's matcher (bootil's globber), which is what Garry's Mod uses for the whitelist:
+//! `*` matches any run of characters, including `/`, `?` matches any one character, and everything else is literal.
+//! Richer, `.gitignore` style rules belong in the addon folder's `.gmaignore` instead.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	Literal(char),
+	/// `?`
+	Any,
+	/// `*`
+	Star,
+}
+
+/// A compiled glob pattern, which must match the whole path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glob {
+	tokens: Vec<Token>,
+}
+impl Glob {
+	/// Compiles `pattern`. Every pattern is valid.
+	pub fn new(pattern: &str) -> Glob {
+		let tokens = pattern
+			.chars()
+			.map(|char| match char {
+				'*' => Token::Star,
+				'?' => Token::Any,
+				_ => Token::Literal(char),
+			})
+			.collect();
+
+		Glob { tokens }
+	}
+
+	/// Whether this glob matches the whole of `path`
+	pub fn matches(&self, path: &str) -> bool {
+		let chars: Vec<char> = path.chars().collect();
+		let n = chars.len();
+
+		// next[j] is whether the tokens after the current one match chars[j..]
+		let mut next = vec![false; n + 1];
+		next[n] = true;
+
+		let mut row = vec![false; n + 1];
+		for token in self.tokens.iter().rev() {
+			match token {
+				Token::Literal(literal) => {
+					row[n] = false;
+					for j in (0..n).rev() {
+						row[j] = chars[j] == *literal && next[j + 1];
+					}
+				}
+				Token::Any => {
+					row[n] = false;
+					for j in (0..n).rev() {
+						row[j] = next[j + 1];
+					}
+				}
+				Token::Star => {
+					row[n] = next[n];
+					for j in (0..n).rev() {
+						row[j] = next[j] || row[j + 1];
+					}
+				}
+			}
+			std::mem::swap(&mut row, &mut next);
+		}
+
+		next[0]
+	}
+}
+
+/// Shorthand for compiling `pattern` and matching it against `path` once
+pub fn matches(pattern: &str, path: &str) -> bool {
+	Glob::new(pattern).matches(path)
+}
+
+#[test]
+fn test_gmad() {
+	assert!(matches("lua/*.lua", "lua/test.lua"));
+	assert!(matches("lua/*.lua", "lua/autorun/test.lua"));
+	assert!(matches("lua/*", "lua/"));
+	assert!(!matches("lua/*.lua", "lua/test.luac"));
+	assert!(!matches("lua/*.lua", "test.lua"));
+	assert!(matches("*.psd", "materials/texture.psd"));
+	assert!(matches("models/?.mdl", "models/a.mdl"));
+	assert!(matches("models/?.mdl", "models//.mdl"));
+	assert!(!matches("models/?.mdl", "models/ab.mdl"));
+	assert!(matches("[ab]\\**", "[ab]\\*"));
+	assert!(!matches("[ab]", "a"));
+	assert!(matches("", ""));
+	assert!(!matches("", "a"));
+	assert!(matches("**", ""));
+}
+
+#[cfg(test)]
+mod proptests {
+	use super::Glob;
+	use proptest::prelude::*;
+
+	/// The matcher this module replaced, ported from bootil with raw pointers. It reads past the end of its inputs unless they're null terminated.
+	fn legacy_globber(wild: &str, str: &str) -> bool {
+		const WILD_BYTE: u8 = b'*';
+		const QUESTION_BYTE: u8 = b'?';
+
+		let wild = format!("{wild}\0");
+		let str = format!("{str}\0");
+
+		unsafe {
+			let mut cp: *const u8 = core::ptr::null();
+			let mut mp: *const u8 = core::ptr::null();
+
+			let (mut wild, wild_max) = (wild.as_ptr(), wild.as_ptr().add(wild.len()));
+			let (mut str, str_max) = (str.as_ptr(), str.as_ptr().add(str.len()));
+
+			while wild < wild_max && str < str_max && *wild != WILD_BYTE {
+				if *wild != *str && *wild != QUESTION_BYTE {
+					return false;
+				}
+				wild = wild.add(1);
+				str = str.add(1);
+			}
+
+			while str < str_max {
+				if *wild == WILD_BYTE {
+					wild = wild.add(1);
+					if wild >= wild_max {
+						return true;
+					}
+					mp = wild;
+					cp = str.add(1);
+				} else if *wild == *str || *wild == QUESTION_BYTE {
+					wild = wild.add(1);
+					str = str.add(1);
+				} else {
+					wild = mp;
+					str = cp;
+					cp = cp.add(1);
+				}
+			}
+
+			while wild < wild_max && *wild == WILD_BYTE {
+				wild = wild.add(1);
+			}
+
+			wild >= wild_max
+		}
+	}
+
+	proptest! {
+		#![proptest_config(ProptestConfig::with_cases(4096))]
+
+		#[test]
+		fn gmad_mode_matches_legacy_globber(pattern in "[ab/.*?]{0,10}", path in "[ab/.]{0,14}") {
+			prop_assert_eq!(Glob::new(&pattern).matches(&path), legacy_globber(&pattern, &path));
+		}
+
+		#[test]
+		fn gmad_mode_matches_legacy_globber_on_whitelist(index in 0..crate::whitelist::ADDON_WHITELIST_OFFLINE.len(), path in "(lua|models|materials|gamemodes|sound|maps)/[a-z/.*]{0,16}(\\.lua|\\.vtx|\\.sw\\.vtx|\\.txt|\\.png)?") {
+			let pattern = crate::whitelist::ADDON_WHITELIST_OFFLINE[index].trim_start_matches('!');
+			prop_assert_eq!(Glob::new(pattern).matches(&path), legacy_globber(pattern, &path));
+		}
+	}
+}
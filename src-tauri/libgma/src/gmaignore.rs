@@ -0,0 +1,113 @@
+//! Per-project ignore files
+//!
+//! A `.gmaignore` in the root of an addon's folder lists files that shouldn't be packed, using the same syntax as `.gitignore`:
+//! `**`, directory-only patterns (`build/`), anchoring (`/notes.txt`) and `!` negation all work the way they do in git.
+//! The root `.gitignore` can optionally be honored too. Its rules are applied first, so `.gmaignore` can `!` re-include something git ignores.
+
+use std::path::Path;
+
+use ignore::{
+	gitignore::{Gitignore, GitignoreBuilder},
+	Match,
+};
+
+pub const GMAIGNORE: &str = ".gmaignore";
+pub const GITIGNORE: &str = ".gitignore";
+
+/// The rules from an addon folder's `.gmaignore` and, optionally, `.gitignore`
+#[derive(Debug, Clone)]
+pub struct ProjectIgnore {
+	matcher: Gitignore,
+}
+impl ProjectIgnore {
+	/// Reads the ignore files in the root of `src_path`. Missing files and invalid lines are skipped.
+	pub fn new<P: AsRef<Path>>(src_path: P, honor_gitignore: bool) -> ProjectIgnore {
+		let src_path = src_path.as_ref();
+
+		let mut builder = GitignoreBuilder::new(src_path);
+
+		// GMA paths are lowercase
+		builder.case_insensitive(true).ok();
+
+		if honor_gitignore {
+			builder.add(src_path.join(GITIGNORE));
+		}
+		builder.add(src_path.join(GMAIGNORE));
+
+		ProjectIgnore {
+			matcher: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+		}
+	}
+
+	/// Ignores nothing
+	pub fn empty() -> ProjectIgnore {
+		ProjectIgnore { matcher: Gitignore::empty() }
+	}
+
+	/// Whether the file at `relative_path` (relative to the addon folder, with forward slashes) is ignored, either itself or through one of its parent directories
+	pub fn is_ignored(&self, relative_path: &str) -> bool {
+		self.matched(relative_path, false).is_some()
+	}
+
+	/// Whether the directory at `relative_path` is ignored, so it doesn't need to be walked
+	pub fn is_dir_ignored(&self, relative_path: &str) -> bool {
+		self.matched(relative_path, true).is_some()
+	}
+
+	/// The ignore file and the line in it that ignores `relative_path`, if any
+	pub fn matched(&self, relative_path: &str, is_dir: bool) -> Option<(&Path, &str)> {
+		if self.matcher.is_empty() || relative_path.is_empty() {
+			return None;
+		}
+
+		match self.matcher.matched_path_or_any_parents(relative_path, is_dir) {
+			Match::Ignore(glob) => Some((glob.from().unwrap_or_else(|| Path::new(GMAIGNORE)), glob.original())),
+			Match::None | Match::Whitelist(_) => None,
+		}
+	}
+}
+
+#[test]
+fn test_gmaignore() {
+	let dir = std::env::temp_dir().join(format!("libgma_test_gmaignore_{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+
+	std::fs::write(
+		dir.join(GMAIGNORE),
+		"# comment\n*.psd\n/notes.txt\nbuild/\nsrc/**/*.bak\nlua/*.lua\n!lua/keep.lua\n!generated.lua\n",
+	)
+	.unwrap();
+	std::fs::write(dir.join(GITIGNORE), "*.log\ngenerated.lua\n").unwrap();
+
+	let ignore = ProjectIgnore::new(&dir, false);
+	assert!(ignore.is_ignored("materials/texture.psd"));
+	assert!(ignore.is_ignored("MATERIALS/TEXTURE.PSD"));
+	assert!(ignore.is_ignored("notes.txt"));
+	assert!(!ignore.is_ignored("docs/notes.txt"));
+	assert!(ignore.is_ignored("build/output.lua"));
+	assert!(ignore.is_ignored("lua/build/output.lua"));
+	assert!(ignore.is_dir_ignored("build"));
+	assert!(!ignore.is_ignored("build"));
+	assert!(ignore.is_ignored("src/a/b/c/file.bak"));
+	assert!(ignore.is_ignored("src/file.bak"));
+	assert!(ignore.is_ignored("lua/file.lua"));
+	assert!(!ignore.is_ignored("lua/keep.lua"));
+	assert!(!ignore.is_ignored("lua/autorun/file.lua"));
+	assert!(!ignore.is_ignored("debug.log"));
+	assert!(!ignore.is_ignored("generated.lua"));
+
+	let (from, glob) = ignore.matched("build/output.lua", false).unwrap();
+	assert_eq!(from, dir.join(GMAIGNORE));
+	assert_eq!(glob, "build/");
+
+	let ignore = ProjectIgnore::new(&dir, true);
+	assert!(ignore.is_ignored("debug.log"));
+	assert!(ignore.is_ignored("lua/debug.log"));
+	assert!(!ignore.is_ignored("generated.lua"));
+	assert_eq!(ignore.matched("debug.log", false).unwrap().0, dir.join(GITIGNORE));
+
+	assert!(!ProjectIgnore::new(dir.join("missing"), true).is_ignored("debug.log"));
+	assert!(!ProjectIgnore::empty().is_ignored("debug.log"));
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,3 +1,5 @@
+//! Stream helpers used to read and write GMAs
+
 use std::{
 	io::{BufRead, BufWriter, ErrorKind, Seek, SeekFrom, Write},
 	sync::Arc,
@@ -5,7 +7,7 @@ use std::{
 
 use byteorder::WriteBytesExt;
 
-use crate::Transaction;
+use crate::Progress;
 
 pub fn stream_len<F: Seek + ?Sized>(f: &mut F) -> Result<u64, std::io::Error> {
 	let old_pos = f.stream_position()?;
@@ -18,7 +20,7 @@ pub fn stream_len<F: Seek + ?Sized>(f: &mut F) -> Result<u64, std::io::Error> {
 }
 
 pub fn stream_bytes<R: BufRead + ?Sized, W: Write>(r: &mut R, w: &mut BufWriter<W>, mut bytes: usize) -> Result<(), std::io::Error> {
-	Ok({
+	{
 		let consumed = loop {
 			let consumed = match r.fill_buf() {
 				Ok([]) => break 0,
@@ -37,16 +39,18 @@ pub fn stream_bytes<R: BufRead + ?Sized, W: Write>(r: &mut R, w: &mut BufWriter<
 			r.consume(consumed);
 		};
 		r.consume(consumed);
-	})
+	}
+
+	Ok(())
 }
 
-pub fn stream_bytes_with_transaction<R: BufRead + ?Sized, W: Write>(
+pub fn stream_bytes_with_progress<R: BufRead + ?Sized, W: Write>(
 	r: &mut R,
 	w: &mut BufWriter<W>,
 	mut bytes: usize,
-	transaction: &Transaction,
+	progress: &dyn Progress,
 ) -> Result<(), std::io::Error> {
-	Ok({
+	{
 		let bytes_f = bytes as f64;
 		let mut consumed_total: f64 = 0.;
 
@@ -69,16 +73,18 @@ pub fn stream_bytes_with_transaction<R: BufRead + ?Sized, W: Write>(
 				r.consume(consumed);
 
 				consumed_total += consumed as f64;
-				transaction.progress(consumed_total / bytes_f);
+				progress.progress(consumed_total / bytes_f);
 			}
 		};
 		if consumed > 0 {
 			r.consume(consumed);
 
 			consumed_total += consumed as f64;
-			transaction.progress(consumed_total / bytes_f);
+			progress.progress(consumed_total / bytes_f);
 		}
-	})
+	}
+
+	Ok(())
 }
 
 pub trait NTStringReader: BufRead + Seek {
@@ -0,0 +1,438 @@
+//! Reading, writing and extracting Garry's Mod addons (.gma)
+//!
+//! This is the GMA format code from gmpublisher, usable without the rest of the app.
+//! Long running operations report to a [`Progress`] and can be cancelled through it.
+
+use std::{
+	collections::HashMap,
+	fmt::Display,
+	fs::File,
+	io::{BufReader, SeekFrom},
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use byteorder::ReadBytesExt;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub mod io;
+use io::ArcBytes;
+
+pub mod glob;
+pub mod whitelist;
+
+mod extract;
+
+mod read;
+pub use read::*;
+
+mod write;
+pub use write::*;
+
+mod walk;
+pub use walk::*;
+
+pub mod gmaignore;
+
+pub mod explain;
+
+pub mod report;
+
+pub mod addon_json;
+
+pub mod plan;
+
+#[cfg(feature = "steamworks")]
+pub use steamworks::PublishedFileId;
+
+/// A Steam Workshop item ID
+#[cfg(not(feature = "steamworks"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PublishedFileId(pub u64);
+
+/// Receives updates from long running operations, such as packing or extracting a GMA
+pub trait Progress: Sync {
+	/// How much of the operation is complete, from 0.0 to 1.0
+	fn progress(&self, _progress: f64) {}
+
+	/// The total number of bytes the operation is working through, when known
+	fn total(&self, _bytes: u64) {}
+
+	/// A file was skipped because it is not allowed by the addon whitelist
+	fn skipped(&self, _path: &str) {}
+
+	/// Return true to stop the operation as soon as possible, which then fails with [`GMAError::Cancelled`]
+	fn cancelled(&self) -> bool {
+		false
+	}
+
+	/// Called between units of work, so the operation can be paused by blocking until it should carry on
+	fn pause_point(&self) {}
+}
+impl Progress for () {}
+
+pub(crate) const GMA_HEADER: &[u8; 4] = b"GMAD";
+
+#[derive(Debug, Clone, Serialize, Error)]
+pub enum GMAError {
+	IOError,
+	/// A file couldn't be read while packing
+	PathIO(PathBuf),
+	FormatError,
+	InvalidHeader,
+	EntryNotFound,
+	LZMA,
+	Cancelled,
+	/// Files that would be packed into the same entry, see [`find_case_collisions`]
+	CaseCollision(Vec<CaseCollision>),
+	/// A symbolic link was found in the addon folder and [`SymlinkPolicy::Error`] is in effect
+	Symlink(String),
+}
+impl Display for GMAError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		use GMAError::*;
+		match self {
+			IOError => write!(f, "an I/O error occurred"),
+			PathIO(path) => write!(f, "couldn't read \"{}\"", path.display()),
+			FormatError => write!(f, "the file isn't a valid GMA"),
+			InvalidHeader => write!(f, "the GMA's header is invalid"),
+			EntryNotFound => write!(f, "the file wasn't found in the GMA"),
+			LZMA => write!(f, "the GMA couldn't be decompressed"),
+			Cancelled => write!(f, "cancelled"),
+			Symlink(path) => write!(f, "\"{}\" is a symbolic link, which isn't allowed", path),
+			CaseCollision(collisions) => write!(
+				f,
+				"files would be packed into the same entry ({})",
+				collisions.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+			),
+		}
+	}
+}
+impl From<std::io::Error> for GMAError {
+	fn from(_: std::io::Error) -> Self {
+		Self::IOError
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GMAFilePointers {
+	metadata: u64,
+	entries: u64,
+	entries_list: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GMAMetadata {
+	Standard {
+		#[serde(default)]
+		title: String,
+		#[serde(default)]
+		#[serde(rename = "type")]
+		addon_type: String,
+		#[serde(default)]
+		tags: Vec<String>,
+		#[serde(default)]
+		ignore: Vec<String>,
+	},
+	Legacy {
+		title: String,
+		description: String,
+	},
+}
+impl GMAMetadata {
+	pub fn title(&self) -> &str {
+		match &self {
+			GMAMetadata::Standard { title, .. } => title,
+			GMAMetadata::Legacy { title, .. } => title,
+		}
+		.as_str()
+	}
+
+	pub fn addon_type(&self) -> Option<&str> {
+		match &self {
+			GMAMetadata::Standard { addon_type, .. } => Some(addon_type.as_str()),
+			_ => None,
+		}
+	}
+
+	pub fn tags(&self) -> Option<&Vec<String>> {
+		match &self {
+			GMAMetadata::Standard { tags, .. } => Some(tags),
+			_ => None,
+		}
+	}
+
+	pub fn ignore(&self) -> Option<&Vec<String>> {
+		match &self {
+			GMAMetadata::Standard { ignore, .. } => Some(ignore),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GMAEntry {
+	pub path: String,
+	pub size: u64,
+	pub crc: u32,
+
+	#[serde(skip)]
+	pub index: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GMAFile {
+	#[serde(serialize_with = "serde_canonicalize")]
+	pub path: PathBuf,
+	pub size: u64,
+
+	pub id: Option<PublishedFileId>,
+
+	#[serde(flatten)]
+	pub metadata: Option<GMAMetadata>,
+
+	pub entries: Option<HashMap<String, GMAEntry>>,
+
+	#[serde(skip)]
+	pub pointers: GMAFilePointers,
+
+	#[serde(skip)]
+	pub version: u8,
+
+	pub extracted_name: String,
+
+	#[serde(skip)]
+	pub modified: Option<u64>,
+
+	#[serde(skip)]
+	pub membuffer: Option<ArcBytes>,
+}
+impl std::fmt::Debug for GMAFile {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("GMAFile")
+			.field("path", &self.path)
+			.field("size", &self.size)
+			.field("id", &self.id)
+			.field("metadata", &self.metadata)
+			.field("entries", &self.entries)
+			.field("pointers", &self.pointers)
+			.field("version", &self.version)
+			.field("extracted_name", &self.extracted_name)
+			.field("modified", &self.modified)
+			.finish()
+	}
+}
+impl PartialEq for GMAFile {
+	fn eq(&self, other: &Self) -> bool {
+		self.path == other.path
+	}
+}
+impl Eq for GMAFile {}
+impl PartialOrd for GMAFile {
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+impl Ord for GMAFile {
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		self.modified.cmp(&other.modified).reverse()
+	}
+}
+
+impl GMAFile {
+	/// A GMA that will be written to `path` by [`GMAFile::create`]
+	pub fn new<P: AsRef<Path>>(path: P, metadata: GMAMetadata) -> GMAFile {
+		GMAFile {
+			path: path.as_ref().to_owned(),
+			size: 0,
+			id: None,
+			metadata: Some(metadata),
+			entries: None,
+			pointers: GMAFilePointers::default(),
+			version: 3,
+			extracted_name: String::new(),
+			modified: None,
+			membuffer: None,
+		}
+	}
+
+	pub(crate) fn read_header<P: AsRef<Path>>(mut f: GMAReader, path: P) -> Result<GMAFile, GMAError> {
+		let mut gma = GMAFile {
+			size: path.as_ref().metadata().map(|metadata| metadata.len()).unwrap_or(0),
+			path: path.as_ref().to_owned(),
+			id: None,
+			metadata: None,
+			entries: None,
+			pointers: GMAFilePointers::default(),
+			version: 0,
+			extracted_name: String::new(),
+			modified: None,
+			membuffer: None,
+		};
+
+		if gma.size == 0 {
+			if let Ok(size) = io::stream_len(&mut *f) {
+				gma.size = size;
+			}
+		}
+
+		let mut header_buf = [0; 4];
+		f.read_exact(&mut header_buf).map_err(|_| GMAError::InvalidHeader)?;
+		if &header_buf != GMA_HEADER {
+			return Err(GMAError::InvalidHeader);
+		}
+
+		gma.version = f.read_u8()?;
+
+		gma.pointers.metadata = f.seek(SeekFrom::Current(0))?;
+
+		gma.compute_extracted_name();
+
+		if let GMAReader::MemBuffer(buf) = f {
+			gma.membuffer = Some(buf.into_inner());
+		}
+
+		Ok(gma)
+	}
+
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<GMAFile, GMAError> {
+		GMAFile::read_header(GMAReader::Disk(BufReader::new(File::open(path.as_ref())?)), path)
+	}
+
+	pub fn set_ws_id(&mut self, id: PublishedFileId) {
+		let compute = self.id.is_some() || self.metadata.is_some();
+
+		self.id = Some(id);
+
+		if compute {
+			self.compute_extracted_name();
+		} else {
+			self.extracted_name.push('_');
+			self.extracted_name.push_str(&id.0.to_string());
+		}
+	}
+
+	fn compute_extracted_name(&mut self) {
+		let mut extracted_name = String::new();
+		let mut underscored = false;
+
+		{
+			let name = match self.metadata {
+				Some(ref metadata) => match metadata {
+					GMAMetadata::Legacy { title, .. } | GMAMetadata::Standard { title, .. } => title.to_lowercase(),
+				},
+				None => match self.path.file_name() {
+					Some(file_name) => file_name.to_string_lossy().to_lowercase(),
+					None => match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+						Ok(unix) => format!("gmpublisher_extracted_{}", unix.as_secs()),
+						Err(_) => "gmpublisher_extracted".into(),
+					},
+				},
+			};
+
+			extracted_name.reserve(name.len());
+
+			let mut first = true;
+			for char in name.chars() {
+				if char.is_alphanumeric() {
+					underscored = false;
+					extracted_name.push(char);
+				} else if !underscored && !first {
+					underscored = true;
+					extracted_name.push('_');
+				}
+				first = false;
+			}
+		}
+
+		if self.id.is_none() {
+			if let Some(file_stem) = self.path.file_stem() {
+				let found_id = workshop_id(&file_stem.to_string_lossy());
+				if found_id.is_some() {
+					self.id = found_id;
+				}
+			}
+		}
+
+		if let Some(id) = self.id {
+			let id_str = id.0.to_string();
+			if !underscored {
+				extracted_name.reserve(id_str.len() + 1);
+				extracted_name.push('_');
+				extracted_name.push_str(&id_str);
+			} else {
+				extracted_name.reserve(id_str.len());
+				extracted_name.push_str(&id_str);
+			}
+		} else if underscored {
+			extracted_name.pop();
+		}
+
+		self.extracted_name = extracted_name;
+	}
+}
+
+fn serde_canonicalize<S>(path: &PathBuf, serializer: S) -> Result<S::Ok, S::Error>
+where
+	S: serde::Serializer,
+{
+	match dunce::canonicalize(path) {
+		Ok(path) => path.serialize(serializer),
+		Err(_) => path.serialize(serializer),
+	}
+}
+
+/// Guesses the Workshop ID of an addon from its file name (without the extension)
+///
+/// Garry's Mod names downloaded addons after their ID (`123456789`, `ds_123456789`) and extracted addons end with `_123456789`
+pub fn workshop_id(file_stem: &str) -> Option<PublishedFileId> {
+	let digits = &file_stem[file_stem.trim_end_matches(|c: char| c.is_ascii_digit()).len()..];
+	match digits.parse::<u64>() {
+		Ok(0) | Err(_) => None,
+		Ok(id) => Some(PublishedFileId(id)),
+	}
+}
+
+#[test]
+fn test_create_extract() {
+	let dir = std::env::temp_dir().join(format!("libgma_test_create_extract_{}", std::process::id()));
+	let src = dir.join("src");
+	std::fs::create_dir_all(src.join("lua/autorun")).unwrap();
+	std::fs::write(src.join("lua/autorun/hello.lua"), "print(\"hello\")").unwrap();
+	std::fs::write(src.join("lua/autorun/not_allowed.exe"), "").unwrap();
+	std::fs::write(src.join("readme.txt"), "").unwrap();
+	std::fs::write(src.join("lua/autorun/scratch.lua"), "").unwrap();
+	std::fs::write(src.join(".gmaignore"), "scratch.lua\n").unwrap();
+
+	let metadata = GMAMetadata::Standard {
+		title: "Test Addon".to_string(),
+		addon_type: "tool".to_string(),
+		tags: vec!["fun".to_string()],
+		ignore: vec!["readme.txt".to_string()],
+	};
+
+	let gma_path = dir.join("test_123456789.gma");
+	GMAFile::new(&gma_path, metadata).create(&src, &PackOptions::default(), &()).unwrap();
+
+	let mut gma = GMAFile::open(&gma_path).unwrap();
+	gma.entries().unwrap();
+
+	assert_eq!(gma.metadata.as_ref().unwrap().title(), "Test Addon");
+	assert_eq!(gma.id, Some(PublishedFileId(123456789)));
+	assert_eq!(gma.extracted_name, "test_addon_123456789");
+
+	let entries = gma.entries.as_ref().unwrap();
+	assert_eq!(entries.len(), 1);
+	assert_eq!(entries["lua/autorun/hello.lua"].size, 14);
+
+	let dest = dir.join("extracted");
+	gma.extract_to(&dest, false, &()).unwrap();
+	assert_eq!(std::fs::read_to_string(dest.join("lua/autorun/hello.lua")).unwrap(), "print(\"hello\")");
+	assert!(dest.join("addon.json").is_file());
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
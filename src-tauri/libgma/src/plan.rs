@@ -0,0 +1,268 @@
+//! A dry run of [`GMAFile::create`], saying what will happen to every file without writing anything
+//!
+//! [`GMAFile::create`] packs exactly what [`GMAFile::plan`] says it will, so a plan can be kept as a build artifact and compared between releases.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+	find_case_collisions, walk::walk_addon_cancellable, whitelist, CaseCollision, GMAError, GMAFile, GMAMetadata, PackOptions, Progress, Symlink,
+	SymlinkPolicy,
+};
+
+/// What will happen to a file when the addon is packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fate {
+	Included,
+	/// Matched by [`DEFAULT_IGNORE`](whitelist::DEFAULT_IGNORE)
+	IgnoredByDefault,
+	/// Matched by one of [`PackOptions::ignore`]
+	IgnoredByUser,
+	/// Matched by the addon's own `ignore` list
+	IgnoredByAddonJson,
+	NotWhitelisted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedFile {
+	/// The file's path relative to the addon folder, with forward slashes and in its original case
+	pub path: String,
+	/// The file's path inside the GMA
+	pub archive_path: String,
+	pub size: u64,
+	pub fate: Fate,
+}
+
+/// The header of the GMA that would be written, minus its timestamp
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlannedHeader {
+	pub version: u8,
+	pub title: String,
+	pub description: String,
+	pub author: String,
+	pub addon_version: i32,
+}
+
+/// The result of [`GMAFile::plan`]
+#[derive(Debug, Clone, Serialize)]
+pub struct PackPlan {
+	pub header: PlannedHeader,
+	/// Every file in the addon folder that isn't ignored by its `.gmaignore`, sorted by path
+	pub files: Vec<PlannedFile>,
+	/// The size the GMA will be, in bytes
+	pub size: u64,
+	/// Included files that only differ by case. Packing fails if there are any.
+	pub collisions: Vec<CaseCollision>,
+	pub symlink_policy: SymlinkPolicy,
+	/// Every symbolic link that was found, and what will be done with it
+	pub symlinks: Vec<Symlink>,
+}
+impl PackPlan {
+	/// The files that will be packed
+	pub fn included(&self) -> impl Iterator<Item = &PlannedFile> {
+		self.files.iter().filter(|file| file.fate == Fate::Included)
+	}
+}
+
+pub(crate) const GMA_VERSION: u8 = 3;
+pub(crate) const GMA_AUTHOR: &str = "Author Name";
+pub(crate) const GMA_ADDON_VERSION: i32 = 1;
+
+/// The title and description written to a GMA's header
+pub(crate) fn header_strings(metadata: &GMAMetadata) -> (&str, String) {
+	match metadata {
+		GMAMetadata::Legacy { title, .. } => (title.as_str(), "Description".to_string()),
+		GMAMetadata::Standard { title, .. } => (title.as_str(), serde_json::ser::to_string(metadata).unwrap()),
+	}
+}
+
+impl GMAFile {
+	/// Walks `src_path` exactly like [`GMAFile::create`] would, without writing anything
+	///
+	/// Fails if [`GMAFile::create`] would fail before writing anything, i.e. because of [`SymlinkPolicy::Error`].
+	pub fn plan<P: AsRef<Path>>(&self, src_path: P, options: &PackOptions) -> Result<PackPlan, GMAError> {
+		self.plan_cancellable(src_path.as_ref(), options, &())
+	}
+
+	/// [`GMAFile::plan`], failing with [`GMAError::Cancelled`] as soon as `progress` is cancelled
+	pub(crate) fn plan_cancellable(&self, src_path: &Path, options: &PackOptions, progress: &dyn Progress) -> Result<PackPlan, GMAError> {
+		let metadata = self.metadata.as_ref().expect("Expected metadata to be set");
+		let user_ignore = whitelist::IgnoreList::new(&options.ignore);
+		let addon_ignore = whitelist::IgnoreList::new(metadata.ignore().map(Vec::as_slice).unwrap_or_default());
+
+		let walk = walk_addon_cancellable(src_path, options, progress)?;
+
+		let mut files: Vec<PlannedFile> = walk
+			.files
+			.into_iter()
+			.map(|(path, relative_path)| {
+				let archive_path = relative_path.to_lowercase();
+
+				let fate = if !whitelist::filter_default_ignored(&archive_path) {
+					Fate::IgnoredByDefault
+				} else if user_ignore.is_ignored(&archive_path) {
+					Fate::IgnoredByUser
+				} else if addon_ignore.is_ignored(&archive_path) {
+					Fate::IgnoredByAddonJson
+				} else if !whitelist::check(&archive_path) {
+					Fate::NotWhitelisted
+				} else {
+					Fate::Included
+				};
+
+				PlannedFile {
+					size: path.metadata().map(|metadata| metadata.len()).unwrap_or(0),
+					path: relative_path,
+					archive_path,
+					fate,
+				}
+			})
+			.collect();
+
+		files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+		let collisions = find_case_collisions(files.iter().filter(|file| file.fate == Fate::Included).map(|file| &file.path));
+
+		let (title, description) = header_strings(metadata);
+		let header = PlannedHeader {
+			version: GMA_VERSION,
+			title: title.to_owned(),
+			description,
+			author: GMA_AUTHOR.to_string(),
+			addon_version: GMA_ADDON_VERSION,
+		};
+
+		let mut plan = PackPlan {
+			header,
+			files,
+			size: 0,
+			collisions,
+			symlink_policy: options.symlinks,
+			symlinks: walk.symlinks,
+		};
+
+		plan.size = {
+			let header = &plan.header;
+
+			// magic + version + steamid + timestamp + required content + title + description + author + addon version
+			let header_size = 4 + 1 + 8 + 8 + 1 + (header.title.len() + 1) + (header.description.len() + 1) + (header.author.len() + 1) + 4;
+
+			// index + path + null + size + crc32, then the file contents
+			let entries_size: u64 = plan
+				.included()
+				.map(|file| 4 + file.archive_path.len() as u64 + 1 + 8 + 4 + file.size)
+				.sum();
+
+			// end of the entry list + crc32
+			header_size as u64 + entries_size + 4 + 4
+		};
+
+		Ok(plan)
+	}
+}
+
+#[test]
+fn test_plan() {
+	let dir = std::env::temp_dir().join(format!("libgma_test_plan_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	let src = dir.join("src");
+	std::fs::create_dir_all(src.join("lua/autorun")).unwrap();
+	std::fs::create_dir_all(src.join("models")).unwrap();
+	std::fs::create_dir_all(src.join("notes")).unwrap();
+	std::fs::write(src.join("lua/autorun/Init.lua"), "print(\"hello\")").unwrap();
+	std::fs::write(src.join("lua/autorun/dev.lua"), "").unwrap();
+	std::fs::write(src.join("models/crate.sw.vtx"), "").unwrap();
+	std::fs::write(src.join("texture.psd"), "").unwrap();
+	std::fs::write(src.join("tool.exe"), "").unwrap();
+	std::fs::write(src.join("lua/autorun/notes.md"), "").unwrap();
+	std::fs::write(src.join("notes/todo.txt"), "").unwrap();
+	std::fs::write(src.join(".gmaignore"), "/notes/\n").unwrap();
+
+	let gma = GMAFile::new(
+		dir.join("out.gma"),
+		GMAMetadata::Standard {
+			title: "Plan".to_string(),
+			addon_type: "tool".to_string(),
+			tags: vec!["fun".to_string()],
+			ignore: vec!["*.exe".to_string()],
+		},
+	);
+	let options = PackOptions {
+		ignore: vec!["*/dev.lua".to_string()],
+		..Default::default()
+	};
+
+	let plan = gma.plan(&src, &options).unwrap();
+
+	let fates: Vec<(&str, Fate)> = plan.files.iter().map(|file| (file.path.as_str(), file.fate)).collect();
+	assert_eq!(
+		fates,
+		[
+			(".gmaignore", Fate::IgnoredByDefault),
+			("lua/autorun/Init.lua", Fate::Included),
+			("lua/autorun/dev.lua", Fate::IgnoredByUser),
+			("lua/autorun/notes.md", Fate::NotWhitelisted),
+			("models/crate.sw.vtx", Fate::IgnoredByDefault),
+			("texture.psd", Fate::IgnoredByDefault),
+			("tool.exe", Fate::IgnoredByAddonJson),
+		]
+	);
+	assert_eq!(plan.files[1].archive_path, "lua/autorun/init.lua");
+	assert_eq!(plan.files[1].size, 14);
+	assert_eq!(plan.header.title, "Plan");
+	assert!(plan.collisions.is_empty());
+
+	// The projected size is exact
+	gma.create(&src, &options, &()).unwrap();
+	assert_eq!(plan.size, dir.join("out.gma").metadata().unwrap().len());
+
+	let json = serde_json::to_value(&plan).unwrap();
+	assert_eq!(json["files"][2]["fate"], "ignored_by_user");
+	assert_eq!(json["header"]["author"], "Author Name");
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_plan_addon_json_ignore() {
+	let dir = std::env::temp_dir().join(format!("libgma_test_plan_addon_json_ignore_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("lua/autorun/client")).unwrap();
+	std::fs::create_dir_all(dir.join("docs")).unwrap();
+	std::fs::write(dir.join("lua/autorun/client/old.bak"), "").unwrap();
+	std::fs::write(dir.join("lua/autorun/Thumbs.db"), "").unwrap();
+	std::fs::write(dir.join("docs/old.bak"), "").unwrap();
+	std::fs::write(
+		dir.join("addon.json"),
+		r#"{"title": "Test", "type": "tool", "tags": [], "ignore": ["lua/*.bak", "*.db"]}"#,
+	)
+	.unwrap();
+
+	let ignore = crate::explain::addon_json_ignore(&dir);
+	let gma = GMAFile::new(
+		dir.join("out.gma"),
+		GMAMetadata::Standard {
+			title: "Test".to_string(),
+			addon_type: "tool".to_string(),
+			tags: vec![],
+			ignore,
+		},
+	);
+	let plan = gma.plan(&dir, &PackOptions::default()).unwrap();
+
+	// Like in gmad, `*` matches across folders and the pattern has to match from the start of the path
+	let fates: Vec<(&str, Fate)> = plan.files.iter().map(|file| (file.path.as_str(), file.fate)).collect();
+	assert_eq!(
+		fates,
+		[
+			("addon.json", Fate::IgnoredByDefault),
+			("docs/old.bak", Fate::NotWhitelisted),
+			("lua/autorun/Thumbs.db", Fate::IgnoredByAddonJson),
+			("lua/autorun/client/old.bak", Fate::IgnoredByAddonJson),
+		]
+	);
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,14 +1,15 @@
 use std::{
 	collections::HashMap,
 	fs::File,
-	io::{BufReader, Cursor, SeekFrom},
+	io::{BufReader, Cursor, Read, SeekFrom},
 };
 
 use byteorder::{LittleEndian, ReadBytesExt};
 
-use crate::{ArcBytes, NTStringReader};
-
-use super::{GMAEntry, GMAError, GMAFile, GMAMetadata};
+use crate::{
+	io::{ArcBytes, NTStringReader},
+	GMAEntry, GMAError, GMAFile, GMAMetadata,
+};
 
 macro_rules! safe_read {
 	( $x:expr ) => {
@@ -16,6 +17,7 @@ macro_rules! safe_read {
 	};
 }
 
+/// A handle to the contents of a GMA, either on disk or decompressed in memory
 pub enum GMAReader {
 	MemBuffer(Cursor<ArcBytes>),
 	Disk(BufReader<File>),
@@ -51,8 +53,6 @@ impl GMAFile {
 	}
 
 	pub fn metadata(&mut self) -> Result<Option<GMAReader>, GMAError> {
-		main_thread_forbidden!();
-
 		if self.metadata.is_some() {
 			Ok(None)
 		} else {
@@ -98,11 +98,31 @@ impl GMAFile {
 		}
 	}
 
+	/// Reads the contents of an entry into memory. [`GMAFile::entries`] must have been read first.
+	pub fn read_entry(&self, entry_path: &str) -> Result<Vec<u8>, GMAError> {
+		let entry = self
+			.entries
+			.as_ref()
+			.expect("Expected entries to be read by this point")
+			.get(entry_path)
+			.ok_or(GMAError::EntryNotFound)?;
+
+		let mut handle = self.read()?;
+		handle.seek(SeekFrom::Start(self.pointers.entries + entry.index))?;
+
+		let mut contents = Vec::new();
+		Read::take(&mut *handle, entry.size).read_to_end(&mut contents)?;
+
+		if contents.len() as u64 != entry.size {
+			return Err(GMAError::FormatError);
+		}
+
+		Ok(contents)
+	}
+
 	// https://steamcommunity.com/sharedfiles/filedetails/?id=1727993520
 
 	pub fn entries(&mut self) -> Result<Option<GMAReader>, GMAError> {
-		main_thread_forbidden!();
-
 		if self.entries.is_some() {
 			Ok(None)
 		} else {
@@ -138,7 +158,6 @@ impl GMAFile {
 							}
 							FORWARDS_SLASH | BACKWARDS_SLASH => {
 								if dots == 2 {
-									eprintln!("Illegal GMA entry: {}", path);
 									continue 'read_entries;
 								} else {
 									dots = 0;
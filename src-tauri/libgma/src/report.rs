@@ -0,0 +1,227 @@
+//! Groups the files rejected by the whitelist by why they were rejected, with suggested fixes
+//!
+//! Files that are only in the wrong folder come with where to move them, anything else comes with ignore patterns that leave it out of the addon.
+//! The ignore patterns are matched like gmad matches the addon.json `ignore` list, against the lowercase path (see [`whitelist::IgnoreList`]).
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+
+use crate::{
+	plan::{Fate, PackPlan, PlannedFile},
+	whitelist,
+};
+
+/// Why a group of files was rejected, which also decides the suggested fix
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum Reason {
+	/// Images that would be allowed inside `materials/`
+	JpegOutsideMaterials,
+	/// Lua files that would be allowed inside `lua/`
+	LuaOutsideLua,
+	/// Anything else, grouped by file extension
+	NotWhitelisted { extension: Option<String> },
+}
+
+/// Moving the file at `from` to `to`, both relative to the addon folder, gets it past the whitelist
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Move {
+	pub from: String,
+	pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportGroup {
+	#[serde(flatten)]
+	pub reason: Reason,
+	/// The rejected files, relative to the addon folder
+	pub paths: Vec<String>,
+	/// Where each of `paths` should be moved to, if the files are just in the wrong folder
+	pub moves: Vec<Move>,
+	/// Ignore patterns that would leave every one of `paths` out of the addon, if they can't be packed anywhere
+	pub ignore: Vec<String>,
+}
+
+/// Every file rejected by the whitelist, grouped by [`Reason`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WhitelistReport {
+	pub groups: Vec<ReportGroup>,
+}
+impl WhitelistReport {
+	/// Groups the [`Fate::NotWhitelisted`] files of `plan`
+	///
+	/// An extension is only suggested for ignoring when no included files have it too.
+	pub fn new(plan: &PackPlan) -> WhitelistReport {
+		let accepted_extensions: HashSet<String> = plan.included().filter_map(|file| extension(&file.archive_path)).collect();
+
+		let mut groups: BTreeMap<Reason, Vec<&PlannedFile>> = BTreeMap::new();
+		for file in plan.files.iter().filter(|file| file.fate == Fate::NotWhitelisted) {
+			groups.entry(Self::reason(&file.archive_path)).or_default().push(file);
+		}
+
+		WhitelistReport {
+			groups: groups
+				.into_iter()
+				.map(|(reason, files)| {
+					let paths = files.iter().map(|file| file.path.clone()).collect();
+					let moves = match reason {
+						Reason::JpegOutsideMaterials => Self::moves(&files, "materials/"),
+						Reason::LuaOutsideLua => Self::moves(&files, "lua/"),
+						Reason::NotWhitelisted { .. } => Vec::new(),
+					};
+					let ignore = match &reason {
+						Reason::NotWhitelisted { extension: Some(extension) } if !accepted_extensions.contains(extension) => {
+							vec![format!("*.{}", extension)]
+						}
+						Reason::NotWhitelisted { .. } => files.iter().map(|file| file.archive_path.clone()).collect(),
+						_ => Vec::new(),
+					};
+					ReportGroup {
+						reason,
+						paths,
+						moves,
+						ignore,
+					}
+				})
+				.collect(),
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.groups.is_empty()
+	}
+
+	/// All of the rejected paths
+	pub fn paths(&self) -> impl Iterator<Item = &str> {
+		self.groups.iter().flat_map(|group| group.paths.iter().map(String::as_str))
+	}
+
+	fn reason(archive_path: &str) -> Reason {
+		if (archive_path.ends_with(".jpeg") || archive_path.ends_with(".jpg")) && whitelist::check(&format!("materials/{}", archive_path)) {
+			Reason::JpegOutsideMaterials
+		} else if archive_path.ends_with(".lua") && whitelist::check(&format!("lua/{}", archive_path)) {
+			Reason::LuaOutsideLua
+		} else {
+			Reason::NotWhitelisted {
+				extension: extension(archive_path),
+			}
+		}
+	}
+
+	fn moves(files: &[&PlannedFile], folder: &str) -> Vec<Move> {
+		files
+			.iter()
+			.map(|file| Move {
+				from: file.path.clone(),
+				to: format!("{}{}", folder, file.path),
+			})
+			.collect()
+	}
+}
+
+/// The extension of the file name in `path`, if it has one
+fn extension(path: &str) -> Option<String> {
+	let file_name = path.rsplit('/').next()?;
+	match file_name.rsplit_once('.') {
+		Some((name, extension)) if !name.is_empty() && !extension.is_empty() => Some(extension.to_owned()),
+		_ => None,
+	}
+}
+
+#[test]
+fn test_report() {
+	use crate::{GMAFile, GMAMetadata, PackOptions};
+
+	let dir = std::env::temp_dir().join(format!("libgma_test_report_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	let src = dir.join("src");
+
+	let files = [
+		// Packed as they are
+		"lua/autorun/init.lua",
+		"data_static/info.txt",
+		"materials/Logo.png",
+		"Materials/Texture.VTF",
+		// Ignored by default
+		"models/props/crate.sw.vtx",
+		// In the wrong folder
+		"Screenshots/Loading.jpg",
+		"autorun/server.lua",
+		// Not allowed anywhere
+		"docs/notes.txt",
+		"tools/build.exe",
+		"tools/Run.EXE",
+		"Makefile",
+	];
+	for file in files {
+		let path = src.join(file);
+		std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+		std::fs::write(path, "").unwrap();
+	}
+
+	let gma = GMAFile::new(
+		dir.join("out.gma"),
+		GMAMetadata::Standard {
+			title: "Report".to_string(),
+			addon_type: "tool".to_string(),
+			tags: vec![],
+			ignore: vec![],
+		},
+	);
+	let report = WhitelistReport::new(&gma.plan(&src, &PackOptions::default()).unwrap());
+
+	let group = |reason: Reason| report.groups.iter().find(|group| group.reason == reason).unwrap();
+
+	assert_eq!(report.groups.len(), 5);
+
+	let jpeg = group(Reason::JpegOutsideMaterials);
+	assert_eq!(jpeg.paths, ["Screenshots/Loading.jpg"]);
+	assert_eq!(
+		jpeg.moves,
+		[Move {
+			from: "Screenshots/Loading.jpg".to_string(),
+			to: "materials/Screenshots/Loading.jpg".to_string()
+		}]
+	);
+	assert!(jpeg.ignore.is_empty());
+
+	let lua = group(Reason::LuaOutsideLua);
+	assert_eq!(lua.moves[0].to, "lua/autorun/server.lua");
+	assert!(lua.ignore.is_empty());
+
+	let exe = group(Reason::NotWhitelisted {
+		extension: Some("exe".to_string()),
+	});
+	assert_eq!(exe.paths, ["tools/Run.EXE", "tools/build.exe"]);
+	assert_eq!(exe.ignore, ["*.exe"]);
+	assert!(exe.moves.is_empty());
+
+	// Other .txt files are packed, so only this one should be ignored
+	let txt = group(Reason::NotWhitelisted {
+		extension: Some("txt".to_string()),
+	});
+	assert_eq!(txt.ignore, ["docs/notes.txt"]);
+
+	assert_eq!(group(Reason::NotWhitelisted { extension: None }).ignore, ["makefile"]);
+
+	assert_eq!(report.paths().count(), 6);
+
+	// Making the suggested moves and ignoring the rest leaves nothing behind
+	for group in &report.groups {
+		for Move { from, to } in &group.moves {
+			std::fs::create_dir_all(src.join(to).parent().unwrap()).unwrap();
+			std::fs::rename(src.join(from), src.join(to)).unwrap();
+		}
+	}
+	let options = PackOptions {
+		ignore: report.groups.iter().flat_map(|group| group.ignore.iter().cloned()).collect(),
+		..Default::default()
+	};
+	let plan = gma.plan(&src, &options).unwrap();
+	assert!(WhitelistReport::new(&plan).is_empty());
+	assert_eq!(plan.included().count(), 6);
+	assert!(plan.collisions.is_empty());
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+
+use path_slash::PathExt;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{gmaignore::ProjectIgnore, GMAError, PackOptions, Progress};
+
+/// What to do with symbolic links found in an addon folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SymlinkPolicy {
+	/// Pack whatever links point to, as long as it's inside the addon folder
+	#[default]
+	Follow,
+	/// Leave links out of the addon
+	Skip,
+	/// Refuse to pack an addon that contains links
+	Error,
+}
+
+/// What happened to a symbolic link found in an addon folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum SymlinkOutcome {
+	Followed,
+	/// Left out because of [`SymlinkPolicy::Skip`]
+	Skipped,
+	/// Left out because it points outside of the addon folder
+	OutsideRoot {
+		target: PathBuf,
+	},
+	/// Left out because it points to one of its own parent folders
+	Loop,
+	/// Left out because what it points to doesn't exist
+	Broken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Symlink {
+	/// The link's path relative to the addon folder, with forward slashes and in its original case
+	pub path: String,
+	#[serde(flatten)]
+	pub outcome: SymlinkOutcome,
+}
+
+/// The files in an addon folder, see [`walk_addon`]
+#[derive(Debug, Clone, Default)]
+pub struct AddonWalk {
+	/// Each file's path and its path relative to the addon folder, with forward slashes and in its original case
+	pub files: Vec<(PathBuf, String)>,
+	/// Every symbolic link that was found, and what was done with it
+	pub symlinks: Vec<Symlink>,
+}
+
+/// Walks the files in an addon folder that aren't ignored by its `.gmaignore` (or `.gitignore`, see [`PackOptions`])
+///
+/// Symbolic links are handled according to [`PackOptions::symlinks`]. Links are never followed outside of `src_path` or in a loop.
+/// Fails with [`GMAError::Symlink`] if a link is found and the policy is [`SymlinkPolicy::Error`].
+pub fn walk_addon<P: AsRef<Path>>(src_path: P, options: &PackOptions) -> Result<AddonWalk, GMAError> {
+	walk_addon_cancellable(src_path.as_ref(), options, &())
+}
+
+/// [`walk_addon`], failing with [`GMAError::Cancelled`] as soon as `progress` is cancelled
+pub(crate) fn walk_addon_cancellable(src_path: &Path, options: &PackOptions, progress: &dyn Progress) -> Result<AddonWalk, GMAError> {
+	let ignore = ProjectIgnore::new(src_path, options.honor_gitignore);
+
+	// Links are resolved to where they really point, so the root has to be too
+	let root = std::fs::canonicalize(src_path).unwrap_or_else(|_| src_path.to_owned());
+
+	let relative_path = |path: &Path| -> Option<String> { Some(path.strip_prefix(src_path).ok()?.to_slash_lossy()) };
+
+	let mut walk = AddonWalk::default();
+
+	let mut entries = WalkDir::new(src_path).follow_links(options.symlinks == SymlinkPolicy::Follow).into_iter();
+	while let Some(entry) = entries.next() {
+		if progress.cancelled() {
+			return Err(GMAError::Cancelled);
+		}
+
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(err) => {
+				// Following a link is the only way to end up in a loop or at a path that doesn't exist
+				if let Some(path) = err
+					.path()
+					.filter(|path| path.is_symlink())
+					.and_then(relative_path)
+					.filter(|path| !ignore.is_ignored(path) && !ignore.is_dir_ignored(path))
+				{
+					walk.symlinks.push(Symlink {
+						path,
+						outcome: if err.loop_ancestor().is_some() {
+							SymlinkOutcome::Loop
+						} else {
+							SymlinkOutcome::Broken
+						},
+					});
+				}
+				continue;
+			}
+		};
+
+		if entry.depth() == 0 {
+			continue;
+		}
+
+		// Links aren't descended into unless they're being followed, but they still count as directories for ignoring
+		let descends = entry.file_type().is_dir();
+		let is_dir = descends || (entry.path_is_symlink() && entry.path().is_dir());
+
+		let relative_path = match relative_path(entry.path()) {
+			Some(relative_path) => relative_path,
+			None => continue,
+		};
+
+		if is_dir && ignore.is_dir_ignored(&relative_path) {
+			if descends {
+				entries.skip_current_dir();
+			}
+			continue;
+		} else if !is_dir && ignore.is_ignored(&relative_path) {
+			continue;
+		}
+
+		if entry.path_is_symlink() {
+			let outcome = match options.symlinks {
+				SymlinkPolicy::Error => return Err(GMAError::Symlink(relative_path)),
+				SymlinkPolicy::Skip => SymlinkOutcome::Skipped,
+				SymlinkPolicy::Follow => match std::fs::canonicalize(entry.path()) {
+					Ok(target) if target.starts_with(&root) => SymlinkOutcome::Followed,
+					Ok(target) => SymlinkOutcome::OutsideRoot { target },
+					Err(_) => SymlinkOutcome::Broken,
+				},
+			};
+
+			let followed = outcome == SymlinkOutcome::Followed;
+
+			walk.symlinks.push(Symlink {
+				path: relative_path.clone(),
+				outcome,
+			});
+
+			if !followed {
+				if descends {
+					entries.skip_current_dir();
+				}
+				continue;
+			}
+		}
+
+		if entry.file_type().is_file() {
+			walk.files.push((entry.into_path(), relative_path));
+		}
+	}
+
+	Ok(walk)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks() {
+	use std::os::unix::fs::symlink;
+
+	let dir = std::env::temp_dir().join(format!("libgma_test_symlinks_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	let src = dir.join("src");
+	std::fs::create_dir_all(src.join("lua/autorun")).unwrap();
+	std::fs::create_dir_all(dir.join("outside")).unwrap();
+	std::fs::write(src.join("lua/autorun/init.lua"), "").unwrap();
+	std::fs::write(dir.join("outside/secret.lua"), "").unwrap();
+
+	symlink(src.join("lua/autorun"), src.join("lua/linked")).unwrap();
+	symlink(dir.join("outside"), src.join("lua/outside")).unwrap();
+	symlink(&src, src.join("lua/loop")).unwrap();
+	symlink(src.join("missing.lua"), src.join("lua/broken.lua")).unwrap();
+
+	let walk = |symlinks: SymlinkPolicy| {
+		walk_addon(
+			&src,
+			&PackOptions {
+				symlinks,
+				..Default::default()
+			},
+		)
+	};
+
+	let outcome = |walk: &AddonWalk, path: &str| {
+		walk.symlinks
+			.iter()
+			.find(|symlink| symlink.path == path)
+			.map(|symlink| symlink.outcome.clone())
+	};
+
+	let followed = walk(SymlinkPolicy::Follow).unwrap();
+	let mut files: Vec<&str> = followed.files.iter().map(|(_, relative_path)| relative_path.as_str()).collect();
+	files.sort_unstable();
+	assert_eq!(files, ["lua/autorun/init.lua", "lua/linked/init.lua"]);
+	assert_eq!(outcome(&followed, "lua/linked"), Some(SymlinkOutcome::Followed));
+	assert_eq!(
+		outcome(&followed, "lua/outside"),
+		Some(SymlinkOutcome::OutsideRoot {
+			target: std::fs::canonicalize(dir.join("outside")).unwrap()
+		})
+	);
+	assert_eq!(outcome(&followed, "lua/loop"), Some(SymlinkOutcome::Loop));
+	assert_eq!(outcome(&followed, "lua/broken.lua"), Some(SymlinkOutcome::Broken));
+
+	let skipped = walk(SymlinkPolicy::Skip).unwrap();
+	assert_eq!(skipped.files.len(), 1);
+	assert_eq!(skipped.symlinks.len(), 4);
+	assert!(skipped.symlinks.iter().all(|symlink| symlink.outcome == SymlinkOutcome::Skipped));
+
+	assert!(matches!(walk(SymlinkPolicy::Error), Err(GMAError::Symlink(_))));
+
+	// Ignored links don't count
+	std::fs::write(src.join(".gmaignore"), "/lua/linked/\n/lua/outside/\n/lua/loop/\n/lua/broken.lua\n").unwrap();
+	let ignored = walk(SymlinkPolicy::Error).unwrap();
+	let mut files: Vec<&str> = ignored.files.iter().map(|(_, relative_path)| relative_path.as_str()).collect();
+	files.sort_unstable();
+	assert_eq!(files, [".gmaignore", "lua/autorun/init.lua"]);
+	assert!(ignored.symlinks.is_empty());
+	assert!(walk(SymlinkPolicy::Follow).unwrap().symlinks.is_empty());
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
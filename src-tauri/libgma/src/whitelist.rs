@@ -0,0 +1,483 @@
+// https://github.com/Facepunch/gmad/blob/master/include/AddonWhiteList.h
+
+use std::{
+	path::Path,
+	sync::{Arc, RwLock},
+};
+
+use lazy_static::lazy_static;
+use thiserror::Error;
+
+use crate::{glob::Glob, walk_addon, GMAError, PackOptions};
+
+/// A copy of gmad's whitelist, used when an up to date one hasn't been provided
+pub const ADDON_WHITELIST_OFFLINE: &[&str] = &[
+	"lua/*.lua",
+	"scenes/*.vcd",
+	"particles/*.pcf",
+	"resource/fonts/*.ttf",
+	"scripts/vehicles/*.txt",
+	"resource/localization/*/*.properties",
+	"maps/*.bsp",
+	"maps/*.lmp",
+	"maps/*.nav",
+	"maps/*.ain",
+	"maps/thumb/*.png",
+	"sound/*.wav",
+	"sound/*.mp3",
+	"sound/*.ogg",
+	"materials/*.vmt",
+	"materials/*.vtf",
+	"materials/*.png",
+	"materials/*.jpg",
+	"materials/*.jpeg",
+	"materials/colorcorrection/*.raw",
+	"models/*.mdl",
+	"models/*.phy",
+	"models/*.ani",
+	"models/*.vvd",
+	"models/*.vtx",
+	"!models/*.sw.vtx",
+	"!models/*.360.vtx",
+	"!models/*.xbox.vtx",
+	"gamemodes/*/*.txt",
+	"!gamemodes/*/*/*.txt",
+	"gamemodes/*/*.fgd",
+	"!gamemodes/*/*/*.fgd",
+	"gamemodes/*/logo.png",
+	"gamemodes/*/icon24.png",
+	"gamemodes/*/gamemode/*.lua",
+	"gamemodes/*/entities/effects/*.lua",
+	"gamemodes/*/entities/weapons/*.lua",
+	"gamemodes/*/entities/entities/*.lua",
+	"gamemodes/*/backgrounds/*.png",
+	"gamemodes/*/backgrounds/*.jpg",
+	"gamemodes/*/backgrounds/*.jpeg",
+	"gamemodes/*/content/models/*.mdl",
+	"gamemodes/*/content/models/*.phy",
+	"gamemodes/*/content/models/*.ani",
+	"gamemodes/*/content/models/*.vvd",
+	"gamemodes/*/content/models/*.vtx",
+	"!gamemodes/*/content/models/*.sw.vtx",
+	"!gamemodes/*/content/models/*.360.vtx",
+	"!gamemodes/*/content/models/*.xbox.vtx",
+	"gamemodes/*/content/materials/*.vmt",
+	"gamemodes/*/content/materials/*.vtf",
+	"gamemodes/*/content/materials/*.png",
+	"gamemodes/*/content/materials/*.jpg",
+	"gamemodes/*/content/materials/*.jpeg",
+	"gamemodes/*/content/materials/colorcorrection/*.raw",
+	"gamemodes/*/content/scenes/*.vcd",
+	"gamemodes/*/content/particles/*.pcf",
+	"gamemodes/*/content/resource/fonts/*.ttf",
+	"gamemodes/*/content/scripts/vehicles/*.txt",
+	"gamemodes/*/content/resource/localization/*/*.properties",
+	"gamemodes/*/content/maps/*.bsp",
+	"gamemodes/*/content/maps/*.nav",
+	"gamemodes/*/content/maps/*.ain",
+	"gamemodes/*/content/maps/thumb/*.png",
+	"gamemodes/*/content/sound/*.wav",
+	"gamemodes/*/content/sound/*.mp3",
+	"gamemodes/*/content/sound/*.ogg",
+	"data_static/*.txt",
+	"data_static/*.dat",
+	"data_static/*.json",
+	"data_static/*.xml",
+	"data_static/*.csv",
+	"shaders/*.vcs",
+];
+
+pub const DEFAULT_IGNORE: &[&str] = &[
+	".git/*",
+	"*.psd",
+	"*.pdn",
+	"*.xcf",
+	"*.kra",
+	"*.svn",
+	"*.ini",
+	"*.rtf",
+	"*.pdf",
+	"*.log",
+	"*.prt",
+	"*.vmf",
+	"*.vmx",
+	".DS_Store",
+	".gitignore",
+	".gmaignore",
+	".gitmodules",
+	".gitattributes",
+	".vscode/*",
+	".github/*",
+	".vs/*",
+	".editorconfig",
+	"LICENSE",
+	"LICENSE.*",
+	"license",
+	"license.*",
+	"README",
+	"README.*",
+	"readme",
+	"readme.*",
+	"addon.json",
+	"addon.txt",
+	"addon.jpg",
+	"thumbs.db",
+	"desktop.ini",
+	"models/*.sw.vtx",
+	"models/*.360.vtx",
+	"models/*.xbox.vtx",
+	"gamemodes/*/content/models/*.sw.vtx",
+	"gamemodes/*/content/models/*.360.vtx",
+	"gamemodes/*/content/models/*.xbox.vtx",
+];
+
+/// The addon whitelist, and its patterns compiled with whether each is an `!` exclusion
+pub(crate) struct AddonWhitelist {
+	pub(crate) patterns: Arc<[String]>,
+	pub(crate) globs: Vec<(bool, Glob)>,
+}
+impl AddonWhitelist {
+	fn new(patterns: Vec<String>) -> AddonWhitelist {
+		let globs = patterns
+			.iter()
+			.map(|glob| match glob.strip_prefix('!') {
+				Some(glob) => (true, Glob::new(glob)),
+				None => (false, Glob::new(glob)),
+			})
+			.collect();
+
+		AddonWhitelist {
+			patterns: patterns.into(),
+			globs,
+		}
+	}
+}
+
+lazy_static! {
+	static ref ADDON_WHITELIST: RwLock<Arc<AddonWhitelist>> = RwLock::new(Arc::new(AddonWhitelist::new(
+		ADDON_WHITELIST_OFFLINE.iter().map(|glob| glob.to_string()).collect()
+	)));
+}
+
+/// Replaces the addon whitelist, for example with an up to date copy downloaded from gmad's repository (see [`parse_header`])
+///
+/// Until then, [`ADDON_WHITELIST_OFFLINE`] is used. Files that are already being checked keep using the whitelist they started with.
+pub fn set_addon_whitelist(whitelist: Vec<String>) {
+	let whitelist = Arc::new(AddonWhitelist::new(whitelist));
+	*ADDON_WHITELIST.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = whitelist;
+}
+
+pub(crate) fn current_addon_whitelist() -> Arc<AddonWhitelist> {
+	ADDON_WHITELIST.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// The addon whitelist in use
+pub fn addon_whitelist() -> Arc<[String]> {
+	current_addon_whitelist().patterns.clone()
+}
+
+#[derive(Debug, Error)]
+pub enum WhitelistParseError {
+	#[error("couldn't find the Wildcard array")]
+	MissingWildcard,
+	#[error("the whitelist is empty")]
+	Empty,
+	#[error("the whitelist doesn't allow lua/*.lua")]
+	MissingLua,
+	#[error("{0}")]
+	Json(#[from] serde_json::Error),
+}
+
+/// Parses the `Wildcard` array out of gmad's `AddonWhiteList.h`
+pub fn parse_header(header: &str) -> Result<Vec<String>, WhitelistParseError> {
+	let array = header
+		.find("Wildcard")
+		.map(|pos| &header[pos..])
+		.ok_or(WhitelistParseError::MissingWildcard)?;
+	let array = array.find('{').map(|pos| &array[pos + 1..]).ok_or(WhitelistParseError::MissingWildcard)?;
+
+	let mut whitelist = Vec::new();
+
+	let mut chars = array.chars().peekable();
+	while let Some(char) = chars.next() {
+		match char {
+			'"' => {
+				let mut glob = String::new();
+				while let Some(char) = chars.next() {
+					match char {
+						'"' => break,
+						'\\' => glob.extend(chars.next()),
+						_ => glob.push(char),
+					}
+				}
+				whitelist.push(glob);
+			}
+
+			// Comments
+			'/' if chars.peek() == Some(&'/') => {
+				for char in chars.by_ref() {
+					if char == '\n' {
+						break;
+					}
+				}
+			}
+			'/' if chars.peek() == Some(&'*') => {
+				chars.next();
+				let mut prev = '\0';
+				for char in chars.by_ref() {
+					if prev == '*' && char == '/' {
+						break;
+					}
+					prev = char;
+				}
+			}
+
+			// The array is terminated by NULL and closed by a brace
+			'N' if chars.clone().take(3).eq("ULL".chars()) => break,
+			'}' => break,
+
+			_ => {}
+		}
+	}
+
+	validate(whitelist)
+}
+
+/// Parses a whitelist from a JSON array of patterns
+pub fn parse_json(json: &str) -> Result<Vec<String>, WhitelistParseError> {
+	validate(serde_json::from_str(json)?)
+}
+
+fn validate(whitelist: Vec<String>) -> Result<Vec<String>, WhitelistParseError> {
+	if whitelist.is_empty() {
+		Err(WhitelistParseError::Empty)
+	} else if !whitelist.iter().any(|glob| glob == "lua/*.lua") {
+		// This should definitely be in there, so if it isn't, something has gone wrong. Probably.
+		Err(WhitelistParseError::MissingLua)
+	} else {
+		Ok(whitelist)
+	}
+}
+
+lazy_static! {
+	static ref DEFAULT_IGNORE_GLOBS: Vec<Glob> = DEFAULT_IGNORE.iter().map(|glob| Glob::new(glob)).collect();
+}
+
+/// Check if a path is allowed in a GMA file
+pub fn check(str: &str) -> bool {
+	let mut valid = false;
+
+	for (exclusion, glob) in &current_addon_whitelist().globs {
+		if *exclusion {
+			if glob.matches(str) {
+				valid = false;
+			}
+		} else if !valid && glob.matches(str) {
+			valid = true;
+		}
+	}
+
+	valid
+}
+
+pub fn filter_default_ignored(str: &str) -> bool {
+	default_ignored_by(str).is_none()
+}
+
+/// The [`DEFAULT_IGNORE`] pattern that ignores a path, if any
+pub(crate) fn default_ignored_by(str: &str) -> Option<&'static str> {
+	DEFAULT_IGNORE_GLOBS
+		.iter()
+		.position(|glob| glob.matches(str))
+		.map(|index| DEFAULT_IGNORE[index])
+}
+
+/// A list of custom ignore globs, compiled once so that a whole addon can be checked against them
+///
+/// They match like gmad matches the addon.json `ignore` list: `*` also matches across `/`
+/// and a glob has to match the whole path, which must already be lowercase like it would be in a GMA.
+/// Richer, `.gitignore` style rules belong in the addon folder's `.gmaignore` instead.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreList {
+	globs: Vec<(String, Glob)>,
+}
+impl IgnoreList {
+	pub fn new(ignore: &[String]) -> IgnoreList {
+		IgnoreList {
+			globs: ignore.iter().map(|glob| (glob.to_owned(), Glob::new(glob))).collect(),
+		}
+	}
+
+	/// The first glob that ignores a path, if any
+	pub fn matched(&self, str: &str) -> Option<&str> {
+		self.globs.iter().find(|(_, glob)| glob.matches(str)).map(|(pattern, _)| pattern.as_str())
+	}
+
+	/// Check if a path is ignored by any of the globs
+	pub fn is_ignored(&self, str: &str) -> bool {
+		self.matched(str).is_some()
+	}
+}
+
+/// Walks `src_path` the same way [`GMAFile::create`](crate::GMAFile::create) does, returning the paths (relative to `src_path`) that aren't allowed by the whitelist
+///
+/// Files matched by [`DEFAULT_IGNORE`], `ignore`, [`PackOptions::ignore`] or the folder's `.gmaignore` don't count, since they wouldn't be packed anyway.
+pub fn verify<P: AsRef<Path>>(src_path: P, ignore: &[String], options: &PackOptions) -> Result<Vec<String>, GMAError> {
+	let ignore = IgnoreList::new(ignore);
+	let user_ignore = IgnoreList::new(&options.ignore);
+
+	let mut invalid: Vec<String> = walk_addon(src_path, options)?
+		.files
+		.into_iter()
+		.map(|(_, relative_path)| relative_path.to_lowercase())
+		.filter(|relative_path| {
+			filter_default_ignored(relative_path)
+				&& !ignore.is_ignored(relative_path)
+				&& !user_ignore.is_ignored(relative_path)
+				&& !check(relative_path)
+		})
+		.collect();
+
+	invalid.sort_unstable();
+	Ok(invalid)
+}
+
+#[test]
+fn test_whitelist() {
+	let good: &'static [&'static str] = &[
+		"lua/test.lua",
+		"lua/lol/test.lua",
+		"lua/lua/testing.lua",
+		"gamemodes/test/something.txt",
+		"gamemodes/test/content/sound/lol.wav",
+		"materials/lol.jpeg",
+		"gamemodes/the_gamemode_name/backgrounds/file_name.jpg",
+		"gamemodes/my_base_defence/backgrounds/1.jpg",
+	];
+
+	let bad: &'static [&'static str] = &[
+		"test.lua",
+		"lua/test.exe",
+		"lua/lol/test.exe",
+		"gamemodes/test",
+		"gamemodes/test/something",
+		"gamemodes/test/something/something.exe",
+		"gamemodes/test/content/sound/lol.vvv",
+		"materials/lol.vvv",
+	];
+
+	for good in good {
+		assert!(check(good), "{}", good);
+	}
+
+	for good in addon_whitelist().iter().filter(|glob| !glob.starts_with('!')) {
+		assert!(check(&good.replace('*', "test")), "{}", good);
+		assert!(check(&good.replace('*', "a")), "{}", good);
+	}
+
+	for bad in bad {
+		assert!(!check(bad));
+	}
+}
+
+#[test]
+fn test_ignore() {
+	let ignored: &'static [&'static str] = &[
+		".git/index",
+		".git/info/exclude",
+		".git/logs/head",
+		".git/logs/refs/heads/4.0.0",
+		".git/logs/refs/heads/master",
+		".git/logs/refs/remotes/origin/4.0.0",
+		".git/logs/refs/remotes/origin/cracker",
+		".git/logs/refs/remotes/origin/cracker-no-minigames",
+		".git/logs/refs/remotes/origin/master",
+		".git/objects/00/007c75922055623f4177467fd50a7d573c2c86",
+		"blah.psd",
+		"some/location/blah.psd",
+		"some/blah/blah.pdn",
+		"hi.xcf",
+		"addon.jpg",
+		"addon.json",
+	];
+
+	for ignored in ignored {
+		assert!(!filter_default_ignored(ignored));
+	}
+
+	let default_ignore = IgnoreList::new(&DEFAULT_IGNORE.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+	for ignored in ignored {
+		assert!(default_ignore.is_ignored(ignored));
+	}
+
+	assert!(IgnoreList::new(&["lol.txt".to_string()]).is_ignored("lol.txt"));
+	assert!(IgnoreList::new(&["lua/*.lua".to_string()]).is_ignored("lua/hello.lua"));
+	assert!(IgnoreList::new(&["lua/*".to_string()]).is_ignored("lua/hello.lua"));
+	assert!(!IgnoreList::new(&[]).is_ignored("lol.txt"));
+
+	// Like gmad, `*` matches across `/` and nothing else is special
+	assert!(IgnoreList::new(&["lua/*".to_string()]).is_ignored("lua/autorun/hello.lua"));
+	assert!(IgnoreList::new(&["lua/*.lua".to_string()]).is_ignored("lua/autorun/hello.lua"));
+	assert!(IgnoreList::new(&["*.psd".to_string()]).is_ignored("materials/src/texture.psd"));
+	assert!(IgnoreList::new(&["sound/take?.wav".to_string()]).is_ignored("sound/take1.wav"));
+	assert!(IgnoreList::new(&["notes[1].txt".to_string()]).is_ignored("notes[1].txt"));
+	assert!(!IgnoreList::new(&["src/".to_string()]).is_ignored("materials/src/texture.vtf"));
+	assert!(!IgnoreList::new(&["src/*".to_string()]).is_ignored("materials/src/texture.vtf"));
+	assert!(!IgnoreList::new(&["sound/take[0-9].wav".to_string()]).is_ignored("sound/take1.wav"));
+	assert!(!IgnoreList::new(&["/".to_string(), "".to_string()]).is_ignored("lol.txt"));
+
+	let ignore = IgnoreList::new(&["*.md".to_string(), "*.txt".to_string(), "lol.*".to_string()]);
+	assert_eq!(ignore.matched("lol.txt"), Some("*.txt"));
+	assert_eq!(ignore.matched("lua/lol.lua"), None);
+}
+
+#[test]
+fn test_parse_header() {
+	let current = parse_header(include_str!("../fixtures/AddonWhiteList.h")).unwrap();
+	assert_eq!(current, ADDON_WHITELIST_OFFLINE);
+
+	// An older copy, saved with Windows line endings, from before data_static/ and shaders/ were allowed
+	let old = parse_header(include_str!("../fixtures/AddonWhiteList-old.h")).unwrap();
+	assert_eq!(old.len(), ADDON_WHITELIST_OFFLINE.len() - 6);
+	assert_eq!(old.first().map(String::as_str), Some("lua/*.lua"));
+	assert_eq!(old.last().map(String::as_str), Some("gamemodes/*/content/sound/*.ogg"));
+	assert!(!old.iter().any(|glob| glob.contains("commented")));
+
+	assert!(matches!(parse_header(""), Err(WhitelistParseError::MissingWildcard)));
+	assert!(matches!(
+		parse_header("static const char* Wildcard[] = { NULL };"),
+		Err(WhitelistParseError::Empty)
+	));
+	assert!(matches!(
+		parse_header("static const char* Wildcard[] = { \"maps/*.bsp\", NULL };"),
+		Err(WhitelistParseError::MissingLua)
+	));
+}
+
+#[test]
+fn test_parse_json() {
+	assert_eq!(parse_json(r#"["lua/*.lua", "!lua/*.txt"]"#).unwrap(), ["lua/*.lua", "!lua/*.txt"]);
+	assert!(matches!(parse_json("{}"), Err(WhitelistParseError::Json(_))));
+	assert!(matches!(parse_json("[]"), Err(WhitelistParseError::Empty)));
+}
+
+#[test]
+fn test_exclusions() {
+	assert!(check("models/player.vtx"));
+	assert!(check("models/weapons/gun.vtx"));
+
+	assert!(!check("models/player.sw.vtx"));
+	assert!(!check("models/player.360.vtx"));
+	assert!(!check("models/player.xbox.vtx"));
+	assert!(!check("models/weapons/gun.sw.vtx"));
+
+	assert!(check("gamemodes/test/content/models/player.vtx"));
+	assert!(!check("gamemodes/test/content/models/player.sw.vtx"));
+	assert!(!check("gamemodes/test/content/models/player.360.vtx"));
+	assert!(!check("gamemodes/test/content/models/player.xbox.vtx"));
+
+	assert!(check("gamemodes/sandbox/info.txt"));
+	assert!(check("gamemodes/sandbox/sandbox.fgd"));
+	assert!(!check("gamemodes/sandbox/nested/info.txt"));
+	assert!(!check("gamemodes/sandbox/entities/weapons/info.txt"));
+}
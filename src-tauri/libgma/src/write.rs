@@ -0,0 +1,407 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use lazy_static::lazy_static;
+use rayon::ThreadPool;
+use serde::Serialize;
+use std::{
+	collections::BTreeMap,
+	fs::{self, File},
+	io::{BufWriter, Seek, Write},
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc, OnceLock,
+	},
+	time::SystemTime,
+};
+
+use crate::{
+	io::NTStringWriter,
+	plan::{header_strings, Fate, PackPlan, GMA_ADDON_VERSION, GMA_AUTHOR, GMA_VERSION},
+	GMAError, GMAFile, Progress, Symlink, SymlinkPolicy, GMA_HEADER,
+};
+
+lazy_static! {
+	static ref THREAD_POOL: ThreadPool = rayon::ThreadPoolBuilder::new().build().unwrap();
+}
+
+impl NTStringWriter for BufWriter<File> {}
+
+/// Options for packing a folder into a GMA
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+	/// Honor the `.gitignore` in the root of the folder as well as its `.gmaignore`
+	pub honor_gitignore: bool,
+	/// What to do with symbolic links in the folder
+	pub symlinks: SymlinkPolicy,
+	/// The user's own ignore patterns, on top of the addon's `ignore` list, matched the same way (see [`whitelist::IgnoreList`](crate::whitelist::IgnoreList))
+	pub ignore: Vec<String>,
+}
+
+/// Files whose paths only differ by case, which would be packed into the same (lowercase) GMA entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CaseCollision {
+	/// The entry they would all be packed into
+	pub path: String,
+	/// The colliding files, relative to the addon folder and in their original case
+	pub files: Vec<String>,
+}
+impl std::fmt::Display for CaseCollision {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.path, self.files.join(", "))
+	}
+}
+
+/// Finds the paths in `relative_paths` that collide with each other once lowercased
+pub fn find_case_collisions<S: AsRef<str>, I: IntoIterator<Item = S>>(relative_paths: I) -> Vec<CaseCollision> {
+	let mut entries: BTreeMap<String, Vec<String>> = BTreeMap::new();
+	for relative_path in relative_paths {
+		let relative_path = relative_path.as_ref();
+		entries.entry(relative_path.to_lowercase()).or_default().push(relative_path.to_owned());
+	}
+
+	entries
+		.into_iter()
+		.filter(|(_, files)| files.len() > 1)
+		.map(|(path, mut files)| {
+			files.sort_unstable();
+			CaseCollision { path, files }
+		})
+		.collect()
+}
+
+/// What [`GMAFile::create`] packed
+#[derive(Debug, Clone, Serialize)]
+pub struct PackSummary {
+	/// How many files were packed
+	pub files: usize,
+	/// The total size of the packed files, in bytes
+	pub size: u64,
+	/// The [`SymlinkPolicy`] that was in effect
+	pub symlink_policy: SymlinkPolicy,
+	/// Every symbolic link that was found, and what was done with it
+	pub symlinks: Vec<Symlink>,
+}
+
+impl GMAFile {
+	pub fn write(&self) -> Result<BufWriter<File>, GMAError> {
+		Ok(BufWriter::new(File::create(&self.path)?))
+	}
+
+	/// Packs the whitelisted files in `src_path` into a GMA at `self.path`
+	///
+	/// Files ignored by default, by [`PackOptions::ignore`], by the addon's metadata or by the folder's `.gmaignore` are left out. See [`GMAFile::plan`].
+	///
+	/// Stops walking, reading and writing as soon as `progress` is cancelled. If packing fails or is cancelled, the partially written GMA is deleted.
+	pub fn create<P: AsRef<Path>>(&self, src_path: P, options: &PackOptions, progress: &dyn Progress) -> Result<PackSummary, GMAError> {
+		let src_path = src_path.as_ref();
+
+		let plan = self.plan_cancellable(src_path, options, progress)?;
+
+		for file in plan.files.iter().filter(|file| file.fate == Fate::NotWhitelisted) {
+			progress.skipped(&file.archive_path);
+		}
+
+		// Paths are lowercased in the GMA, so files that only differ by case would overwrite each other
+		if !plan.collisions.is_empty() {
+			return Err(GMAError::CaseCollision(plan.collisions));
+		}
+
+		if progress.cancelled() {
+			return Err(GMAError::Cancelled);
+		}
+
+		let result = self.pack(src_path, &plan, progress);
+		if result.is_err() {
+			let _ = fs::remove_file(&self.path);
+		}
+		result
+	}
+
+	fn pack(&self, src_path: &Path, plan: &PackPlan, progress: &dyn Progress) -> Result<PackSummary, GMAError> {
+		let metadata = self.metadata.as_ref().expect("Expected metadata to be set");
+
+		let mut f = self.write()?;
+
+		let (title, description) = header_strings(metadata);
+
+		f.write_all(GMA_HEADER)?;
+
+		f.write_u8(GMA_VERSION)?;
+
+		// steamid [unused]
+		f.write_u64::<LittleEndian>(0)?;
+
+		// timestamp [unused]
+		f.write_u64::<LittleEndian>(match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+			Ok(unix) => unix.as_secs(),
+			Err(_) => 0,
+		})?;
+
+		// required content [unused]
+		f.write_u8(0)?;
+
+		// addon name
+		f.write_nt_string(title)?;
+
+		// addon description
+		f.write_nt_string(&description)?;
+
+		// addon author [unused]
+		f.write_nt_string(GMA_AUTHOR)?;
+
+		// addon version [unused]
+		f.write_i32::<LittleEndian>(GMA_ADDON_VERSION)?;
+
+		// file list
+		let mut file_list: BTreeMap<String, (usize, u64, Box<[u8]>)> = BTreeMap::new();
+		let (failed, cancelled, rx, total) = {
+			// The first file that couldn't be read
+			let failed: Arc<OnceLock<PathBuf>> = Arc::new(OnceLock::new());
+			let cancelled = Arc::new(AtomicBool::new(false));
+
+			let (tx, rx) = crossbeam::channel::unbounded();
+
+			let mut total = 0.;
+			for file in plan.included() {
+				let path = src_path.join(&file.path);
+				let relative_path = file.archive_path.clone();
+
+				if failed.get().is_some() {
+					break;
+				}
+				if progress.cancelled() {
+					cancelled.store(true, Ordering::Release);
+					break;
+				}
+
+				file_list.insert(relative_path.clone(), (0, 0, Vec::new().into_boxed_slice()));
+
+				let tx = tx.clone();
+				let failed = failed.clone();
+				let cancelled = cancelled.clone();
+				THREAD_POOL.spawn(move || {
+					if cancelled.load(Ordering::Acquire) {
+						return;
+					}
+
+					let contents = match fs::read(&path) {
+						Ok(contents) => contents,
+						Err(_) => {
+							let _ = failed.set(path);
+							return;
+						}
+					};
+
+					let mut crc32 = crc32fast::Hasher::new();
+					crc32.reset();
+					crc32.update(&contents);
+					let crc32 = crc32.finalize();
+
+					// The receiver is gone if packing was cancelled
+					let _ = tx.send((relative_path.into_boxed_str(), contents.into_boxed_slice(), crc32));
+				});
+
+				total += 1.;
+			}
+
+			(failed, cancelled, rx, total)
+		};
+
+		let mut cursor = f.stream_position()?;
+		file_list.iter_mut().enumerate().for_each(|(i, (path, (idx, pos, _)))| {
+			*pos = cursor;
+			*idx = i + 1;
+			cursor += 4 + path.len() as u64 + 1 + 8 + 4; // index + path + null + size + crc32
+		});
+
+		let mut size = 0;
+		let mut i_f: f64 = 0.;
+		while let Ok((path, contents, crc32)) = rx.recv() {
+			if cancelled.load(Ordering::Acquire) || progress.cancelled() {
+				// Tell the workers that haven't started reading yet not to bother
+				cancelled.store(true, Ordering::Release);
+				return Err(GMAError::Cancelled);
+			}
+
+			let (i, cursor, read_contents) = file_list.get_mut(&*path).unwrap();
+
+			*read_contents = contents;
+
+			let contents = &**read_contents;
+
+			f.seek(std::io::SeekFrom::Start(*cursor))?;
+			f.write_u32::<LittleEndian>(*i as u32)?;
+			f.write_all(path.as_bytes())?;
+			f.write_u8(0)?;
+			f.write_i64::<LittleEndian>(contents.len() as i64)?;
+			f.write_u32::<LittleEndian>(crc32)?;
+
+			size += contents.len() as u64;
+			i_f += 1.;
+			progress.progress(i_f / total);
+		}
+
+		f.seek(std::io::SeekFrom::Start(cursor))?;
+		f.write_u32::<LittleEndian>(0)?;
+
+		let files = file_list.len();
+		for (_, (_, _, contents)) in file_list {
+			if progress.cancelled() {
+				return Err(GMAError::Cancelled);
+			}
+			f.write_all(&contents)?;
+		}
+
+		let written = f.buffer();
+
+		let mut crc32 = crc32fast::Hasher::new();
+		crc32.reset();
+		crc32.update(written);
+		let crc32 = crc32.finalize();
+
+		f.write_u32::<LittleEndian>(crc32)?;
+
+		if let Some(path) = failed.get() {
+			return Err(GMAError::PathIO(path.clone()));
+		}
+
+		Ok(PackSummary {
+			files,
+			size,
+			symlink_policy: plan.symlink_policy,
+			symlinks: plan.symlinks.clone(),
+		})
+	}
+}
+
+#[test]
+fn test_case_collisions() {
+	assert_eq!(
+		find_case_collisions(["Materials/a.vtf", "lua/init.lua", "materials/a.vtf", "materials/A.vtf", "materials/b.vtf"]),
+		[CaseCollision {
+			path: "materials/a.vtf".to_string(),
+			files: vec![
+				"Materials/a.vtf".to_string(),
+				"materials/A.vtf".to_string(),
+				"materials/a.vtf".to_string()
+			],
+		}]
+	);
+	assert!(find_case_collisions(["lua/a.lua", "lua/b.lua"]).is_empty());
+
+	// Only possible on case sensitive file systems
+	let dir = std::env::temp_dir().join(format!("libgma_test_case_collisions_{}", std::process::id()));
+	std::fs::create_dir_all(dir.join("src/Materials")).unwrap();
+	std::fs::create_dir_all(dir.join("src/materials")).unwrap();
+	std::fs::write(dir.join("src/Materials/a.vtf"), "upper").unwrap();
+	std::fs::write(dir.join("src/materials/a.vtf"), "lower").unwrap();
+
+	if std::fs::read_to_string(dir.join("src/Materials/a.vtf")).unwrap() == "upper" {
+		let gma = GMAFile::new(
+			dir.join("out.gma"),
+			crate::GMAMetadata::Standard {
+				title: "Test".to_string(),
+				addon_type: "tool".to_string(),
+				tags: Vec::new(),
+				ignore: Vec::new(),
+			},
+		);
+
+		match gma.create(dir.join("src"), &PackOptions::default(), &()) {
+			Err(GMAError::CaseCollision(collisions)) => assert_eq!(collisions[0].files, ["Materials/a.vtf", "materials/a.vtf"]),
+			result => panic!("{:?}", result),
+		}
+		assert!(!dir.join("out.gma").exists());
+	}
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cancel() {
+	use std::sync::atomic::AtomicUsize;
+
+	/// Cancels once `progress` has been called `after` times
+	struct CancelAfter {
+		after: usize,
+		calls: AtomicUsize,
+	}
+	impl Progress for CancelAfter {
+		fn progress(&self, _progress: f64) {
+			self.calls.fetch_add(1, Ordering::AcqRel);
+		}
+
+		fn cancelled(&self) -> bool {
+			self.calls.load(Ordering::Acquire) >= self.after
+		}
+	}
+
+	let dir = std::env::temp_dir().join(format!("libgma_test_cancel_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("src/lua/autorun")).unwrap();
+	for i in 0..8 {
+		std::fs::write(dir.join(format!("src/lua/autorun/{}.lua", i)), vec![b'-'; 1024]).unwrap();
+	}
+
+	let gma = GMAFile::new(
+		dir.join("out.gma"),
+		crate::GMAMetadata::Standard {
+			title: "Test".to_string(),
+			addon_type: "tool".to_string(),
+			tags: Vec::new(),
+			ignore: Vec::new(),
+		},
+	);
+
+	for after in [0, 1] {
+		let progress = CancelAfter {
+			after,
+			calls: AtomicUsize::new(0),
+		};
+		assert!(matches!(
+			gma.create(dir.join("src"), &PackOptions::default(), &progress),
+			Err(GMAError::Cancelled)
+		));
+		assert!(!dir.join("out.gma").exists());
+	}
+
+	gma.create(dir.join("src"), &PackOptions::default(), &()).unwrap();
+	assert!(dir.join("out.gma").exists());
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_unreadable_file() {
+	let dir = std::env::temp_dir().join(format!("libgma_test_unreadable_file_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("src/lua/autorun")).unwrap();
+	std::fs::write(dir.join("src/lua/autorun/a.lua"), "print('a')").unwrap();
+	std::fs::write(dir.join("src/lua/autorun/b.lua"), "print('b')").unwrap();
+
+	let gma = GMAFile::new(
+		dir.join("out.gma"),
+		crate::GMAMetadata::Standard {
+			title: "Test".to_string(),
+			addon_type: "tool".to_string(),
+			tags: Vec::new(),
+			ignore: Vec::new(),
+		},
+	);
+
+	// Deleted after it was planned, so it can't be read when it's packed
+	let plan = gma.plan(dir.join("src"), &PackOptions::default()).unwrap();
+	std::fs::remove_file(dir.join("src/lua/autorun/b.lua")).unwrap();
+
+	match gma.pack(&dir.join("src"), &plan, &()) {
+		Err(error @ GMAError::PathIO(_)) => {
+			assert_eq!(
+				error.to_string(),
+				format!("couldn't read \"{}\"", dir.join("src").join("lua/autorun/b.lua").display())
+			)
+		}
+		result => panic!("{:?}", result),
+	}
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
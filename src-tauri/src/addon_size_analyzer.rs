@@ -7,7 +7,7 @@ use steamworks::PublishedFileId;
 
 use serde::Serialize;
 
-use crate::{game_addons, transaction, transactions::Transaction, webview::Addon};
+use crate::{game_addons, transaction, transactions::Transaction, webview::Addon, ErrorCode};
 
 lazy_static! {
 	static ref THREAD_POOL: ThreadPool = thread_pool!(4);
@@ -174,28 +174,33 @@ impl AddonSizeAnalyzer {
 
 			let addons = game_addons!().get_addons().clone();
 			if addons.is_empty() {
-				transaction.error("ERR_NO_ADDONS_FOUND", turbonone!());
+				transaction.error(ErrorCode::NoAddonsFound, turbonone!());
 				return;
 			}
 
+			let counting = transaction.child(1.);
+			let tagging = transaction.child(1.);
+			let laying_out = transaction.child(1.);
+
 			transaction.status("FS_ANALYZER_COMPUTING");
 
-			let (addons, total_size) = self.count(addons, &transaction);
+			let (addons, total_size) = self.count(addons, &counting);
 
 			if transaction.aborted() {
 				return;
 			}
+			counting.finished(turbonone!());
 
 			transaction.status("FS_ANALYZER_TAGGIFYING");
 
-			let treemap = self.taggify(addons, w, h, total_size, &transaction);
+			let treemap = self.taggify(addons, w, h, total_size, &tagging, &laying_out);
 
 			if transaction.aborted() {
 				return;
 			}
+			laying_out.finished(turbonone!());
 
 			transaction.status("FS_ANALYZER_SERIALIZING");
-			transaction.progress(1.);
 
 			transaction.finished(Some(treemap.squares));
 		});
@@ -210,6 +215,8 @@ impl AddonSizeAnalyzer {
 		let mut total_size = 0;
 		let total = addons.len() as f64;
 		for (i, gma) in addons.into_iter().enumerate() {
+			transaction.pause_point();
+
 			total_size += gma.installed().size;
 
 			if let Some(ref id) = gma.installed().id {
@@ -217,7 +224,7 @@ impl AddonSizeAnalyzer {
 			}
 
 			sorted_addons.push(AnalyzedAddon(gma));
-			transaction.progress(((i as f64) / total) / 3.);
+			transaction.progress((i as f64) / total);
 		}
 
 		steam!().fetch_workshop_items(ids);
@@ -225,7 +232,7 @@ impl AddonSizeAnalyzer {
 		(sorted_addons.into_sorted_vec(), total_size)
 	}
 
-	fn taggify(&self, gma_files: Vec<AnalyzedAddon>, w: f64, h: f64, total_size: u64, transaction: &Transaction) -> TreeMap {
+	fn taggify(&self, gma_files: Vec<AnalyzedAddon>, w: f64, h: f64, total_size: u64, tagging: &Transaction, laying_out: &Transaction) -> TreeMap {
 		use indexmap::map::Entry::{Occupied, Vacant};
 
 		let mut master_treemap = TreeMap::new(w, h);
@@ -236,6 +243,8 @@ impl AddonSizeAnalyzer {
 		let mut tag_sizes: IndexMap<String, Vec<f64>> = IndexMap::new();
 		let mut tag_data: IndexMap<String, Vec<TaggedTreeMapData>> = IndexMap::new();
 		for (i, gma) in gma_files.into_iter().enumerate() {
+			tagging.pause_point();
+
 			let metadata = gma.installed().metadata.as_ref().unwrap();
 			let tag = metadata
 				.addon_type()
@@ -269,7 +278,11 @@ impl AddonSizeAnalyzer {
 				}
 			}
 
-			transaction.progress(0.33 + (((i as f64) / total_files) / 3.));
+			tagging.progress((i as f64) / total_files);
+		}
+
+		if !tagging.aborted() {
+			tagging.finished(turbonone!());
 		}
 
 		for tag in tag_total_sizes.keys() {
@@ -283,7 +296,8 @@ impl AddonSizeAnalyzer {
 			let total_squares_f = total_squares_i as f64; // TODO is this = to something?
 
 			for (i, square) in master_treemap.squares.chunks_exact_mut(1).enumerate() {
-				if transaction.aborted() {
+				laying_out.pause_point();
+				if laying_out.aborted() {
 					break;
 				}
 
@@ -296,9 +310,9 @@ impl AddonSizeAnalyzer {
 				let tag_sizes = tag_sizes.remove(&tag).unwrap();
 				let tag_data = tag_data.remove(&tag).unwrap();
 
-				let transaction = transaction.clone();
+				let laying_out = laying_out.clone();
 				scope.spawn(move |_| {
-					if transaction.aborted() {
+					if laying_out.aborted() {
 						return;
 					}
 
@@ -310,7 +324,7 @@ impl AddonSizeAnalyzer {
 
 					square.data = Some((Some(Box::new(treemap.squares)), Some(tag.clone())));
 
-					transaction.progress(0.66 + (((i as f64) / total_squares_f) / 3.));
+					laying_out.progress((i as f64) / total_squares_f);
 				});
 			}
 		});
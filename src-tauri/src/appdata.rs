@@ -8,15 +8,17 @@ use std::{
 };
 
 use crate::{
-	gma::{ExtractDestination, ExtractionOverwriteMode},
-	webview_emit, RwLockCow,
+	gma::{ExtractDestination, ExtractionOverwriteMode, PackOptions, SymlinkPolicy},
+	PublishedFileId, RwLockCow,
 };
 
+#[cfg(feature = "gui")]
+use crate::webview_emit;
+#[cfg(feature = "steam")]
 use crate::GMOD_APP_ID;
 use lazy_static::lazy_static;
 use parking_lot::{RwLock, RwLockReadGuard};
 use serde::{Deserialize, Serialize};
-use steamworks::PublishedFileId;
 
 lazy_static! {
 	static ref USER_DATA_DIR: PathBuf = dirs_next::data_dir()
@@ -29,6 +31,9 @@ lazy_static! {
 	static ref DOWNLOADS_DIR: Option<PathBuf> = dirs::download_dir();
 }
 
+/// The most workshop items that can be downloaded at once
+pub const MAX_DOWNLOAD_CONCURRENCY: usize = 8;
+
 #[derive(Debug)]
 pub struct OpenCount(Cell<u32>);
 unsafe impl Send for OpenCount {}
@@ -38,6 +43,8 @@ impl OpenCount {
 		OpenCount(Cell::new(0))
 	}
 
+	// The webview counts how many times gmpublisher has been opened, which headless builds don't have
+	#[cfg_attr(not(feature = "gui"), allow(dead_code))]
 	fn increment(&self) {
 		self.0.set(
 			(|| -> Result<u32, std::io::Error> {
@@ -84,8 +91,13 @@ pub struct Settings {
 	pub extract_destination: ExtractDestination,
 	pub destinations: Vec<PathBuf>,
 	pub create_folder_on_extract: bool,
+	/// How many workshop items are downloaded at once
+	pub download_concurrency: usize,
 
 	pub ignore_globs: Vec<String>,
+	pub honor_gitignore: bool,
+	pub symlink_policy: SymlinkPolicy,
+	pub addon_whitelist: Option<PathBuf>,
 
 	pub my_workshop_local_paths: HashMap<PublishedFileId, PathBuf>,
 	pub upscale_addon_icon: bool,
@@ -114,8 +126,12 @@ impl Default for Settings {
 
 			destinations: Vec::new(),
 			create_folder_on_extract: true,
+			download_concurrency: 3,
 
 			ignore_globs: Vec::new(),
+			honor_gitignore: false,
+			symlink_policy: SymlinkPolicy::default(),
+			addon_whitelist: None,
 			my_workshop_local_paths: HashMap::new(),
 			upscale_addon_icon: true,
 
@@ -184,6 +200,15 @@ impl Settings {
 		}
 
 		self.destinations.truncate(20);
+		self.download_concurrency = self.download_concurrency.clamp(1, MAX_DOWNLOAD_CONCURRENCY);
+	}
+
+	pub fn pack_options(&self) -> PackOptions {
+		PackOptions {
+			honor_gitignore: self.honor_gitignore,
+			symlinks: self.symlink_policy,
+			ignore: self.ignore_globs.clone(),
+		}
 	}
 }
 
@@ -218,10 +243,12 @@ impl AppData {
 		}
 	}
 
+	#[cfg(feature = "gui")]
 	pub fn send(&'static self) {
 		webview_emit!("UpdateAppData", self);
 	}
 
+	#[cfg(feature = "gui")]
 	pub fn gmod_dir(&self) -> Option<PathBuf> {
 		println!("Locating Garry's Mod...");
 
@@ -266,6 +293,24 @@ impl AppData {
 		}
 	}
 
+	#[cfg(not(feature = "gui"))]
+	pub fn gmod_dir(&self) -> Option<PathBuf> {
+		if let Some(ref gmod) = self.settings.read().gmod {
+			if gmod.is_dir() {
+				return Some(gmod.to_owned());
+			}
+		}
+
+		#[cfg(feature = "steam")]
+		{
+			steamlocate::SteamDir::locate().and_then(|mut steam_dir| steam_dir.app(&GMOD_APP_ID.0).map(|steam_app| steam_app.path.to_owned()))
+		}
+		#[cfg(not(feature = "steam"))]
+		{
+			None
+		}
+	}
+
 	pub fn temp_dir(&self) -> RwLockCow<'_, PathBuf> {
 		let lock = self.settings.read();
 		if let Some(ref temp) = lock.temp {
@@ -300,20 +345,21 @@ impl AppData {
 	}
 }
 
-#[cfg(target_os = "windows")]
+#[cfg(all(feature = "gui", target_os = "windows"))]
 const PATH_SEPARATOR: char = '\\';
-#[cfg(not(target_os = "windows"))]
+#[cfg(all(feature = "gui", not(target_os = "windows")))]
 const PATH_SEPARATOR: char = '/';
 
+#[cfg(feature = "gui")]
 pub struct Plugin;
+#[cfg(feature = "gui")]
 impl<R: tauri::Runtime> tauri::plugin::Plugin<R> for Plugin {
 	fn initialization_script(&self) -> Option<String> {
 		let mut sanitized = app_data!().settings.read().clone();
 		sanitized.sanitize();
 		*app_data!().settings.write() = sanitized;
 
-		// Trim off nul byte
-		let mut default_ignore: Vec<String> = crate::gma::DEFAULT_IGNORE.iter().map(|x| x[0..x.len() - 1].to_string()).collect();
+		let mut default_ignore: Vec<String> = crate::gma::DEFAULT_IGNORE.iter().map(|x| x.to_string()).collect();
 		default_ignore.sort();
 
 		app_data!().open_count.increment();
@@ -346,6 +392,7 @@ impl<R: tauri::Runtime> tauri::plugin::Plugin<R> for Plugin {
 	}
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn update_settings(mut settings: Settings) -> bool {
 	settings.sanitize();
@@ -366,6 +413,7 @@ pub fn update_settings(mut settings: Settings) -> bool {
 	true
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn validate_gmod(mut path: PathBuf) -> bool {
 	path.push("GarrysMod");
@@ -373,6 +421,7 @@ pub fn validate_gmod(mut path: PathBuf) -> bool {
 	path.is_absolute() && path.is_dir()
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn window_resized(window: tauri::Window, width: f64, height: f64) {
 	{
@@ -417,6 +466,7 @@ where
 	app_data!().downloads_dir().serialize(serializer)
 }
 
+#[cfg(feature = "gui")]
 pub fn write_tauri_settings() -> Option<()> {
 	use serde_json::Value as JsonValue;
 	use std::io::{BufReader, BufWriter};
@@ -0,0 +1,120 @@
+// `gmpublisher explain <PATH>...`, which says why each file will or won't be packed into the addon
+
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
+
+use path_slash::PathExt;
+
+use crate::{
+	app_data,
+	appdata::Settings,
+	gma::{
+		explain::{self, Decision, Explainer, Rule},
+		whitelist,
+	},
+};
+
+/// Explains each path, returning whether all of them are inside an addon folder
+pub fn run(paths: Vec<PathBuf>, folder: Option<&Path>) -> bool {
+	whitelist::init();
+
+	let settings = app_data!().settings.read().clone();
+	let mut explainers: HashMap<PathBuf, Explainer> = HashMap::new();
+
+	let mut ok = true;
+	for path in paths {
+		let path = dunce::canonicalize(&path).unwrap_or(path);
+
+		let folder = match folder.map(|folder| dunce::canonicalize(folder).unwrap_or_else(|_| folder.to_owned())) {
+			Some(folder) => folder,
+			None => match find_addon_folder(&path) {
+				Some(folder) => folder,
+				None => {
+					std::eprintln!("\"{}\" isn't inside an addon folder (no addon.json found), pass --folder", path.display());
+					ok = false;
+					continue;
+				}
+			},
+		};
+
+		let relative_path = match path.strip_prefix(&folder) {
+			Ok(relative_path) => relative_path.to_slash_lossy(),
+			Err(_) => {
+				std::eprintln!("\"{}\" isn't inside \"{}\"", path.display(), folder.display());
+				ok = false;
+				continue;
+			}
+		};
+
+		let explainer = explainers.entry(folder).or_insert_with_key(|folder| explainer(folder, &settings));
+		let explanation = explainer.explain(&relative_path);
+
+		std::println!("{}: {}", explanation.path, describe(&explanation.decision));
+	}
+
+	ok
+}
+
+/// Explains files in `folder` the same way they'd be packed, with the user's ignore patterns and pack options
+fn explainer(folder: &Path, settings: &Settings) -> Explainer {
+	Explainer::new(
+		folder,
+		settings.ignore_globs.clone(),
+		explain::addon_json_ignore(folder),
+		&settings.pack_options(),
+	)
+}
+
+fn find_addon_folder(path: &Path) -> Option<PathBuf> {
+	path.ancestors()
+		.skip(1)
+		.find(|dir| dir.join("addon.json").is_file())
+		.map(Path::to_path_buf)
+}
+
+fn describe(decision: &Decision) -> String {
+	match decision {
+		Decision::Included { rule } => format!("included ({})", describe_rule(rule)),
+		Decision::Ignored { rule } => format!("ignored ({})", describe_rule(rule)),
+		Decision::NotWhitelisted { rule: Some(rule) } => format!("not allowed ({})", describe_rule(rule)),
+		Decision::NotWhitelisted { rule: None } => "not allowed (no whitelist pattern matches)".to_string(),
+	}
+}
+
+fn describe_rule(rule: &Rule) -> String {
+	match rule {
+		Rule::Whitelist { glob } => format!("whitelisted by \"{}\"", glob),
+		Rule::WhitelistExclusion { glob } => format!("excluded by the whitelist's \"{}\"", glob),
+		Rule::DefaultIgnore { glob } => format!("default pattern \"{}\"", glob),
+		Rule::UserIgnore { glob } => format!("ignore pattern \"{}\"", glob),
+		Rule::AddonJsonIgnore { glob } => format!("addon.json ignore \"{}\"", glob),
+		Rule::ProjectIgnore { file, glob } => format!("\"{}\" in {}", glob, file.display()),
+	}
+}
+
+#[test]
+fn test_user_ignore() {
+	let dir = std::env::temp_dir().join(format!("gmpublisher_test_cli_explain_{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	std::fs::write(dir.join("addon.json"), r#"{"title": "Test", "type": "tool", "tags": []}"#).unwrap();
+
+	let settings = Settings {
+		ignore_globs: vec!["lua/*.txt".to_string()],
+		..Default::default()
+	};
+	let explainer = explainer(&dir, &settings);
+
+	assert!(explainer.explain("lua/autorun/test.lua").decision.is_included());
+	assert_eq!(
+		explainer.explain("lua/notes.txt").decision,
+		Decision::Ignored {
+			rule: Rule::UserIgnore {
+				glob: "lua/*.txt".to_string()
+			}
+		}
+	);
+
+	std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,267 @@
+// gmad compatible command line syntax, so gmpublisher can be dropped into build scripts that call gmad.exe
+// https://github.com/Facepunch/gmad/blob/master/src/create_gmad.cpp
+
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	gma::{
+		addon_json::{self, AddonJsonIssue},
+		whitelist, ExtractDestination, ExtractGMAMut, PackOptions, SymlinkOutcome, SymlinkPolicy,
+	},
+	GMAError, GMAFile, GMAMetadata,
+};
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+
+/// Returns the gmad exit code if the arguments are in gmad's syntax, otherwise None
+pub fn run(args: &[String]) -> Option<i32> {
+	let command = args.first()?;
+
+	match command.as_str() {
+		"create" => Some(match (arg_value(args, "-folder"), arg_value(args, "-out")) {
+			(None, _) => {
+				std::eprintln!("Missing -folder (the folder to turn into an addon)");
+				EXIT_FAILURE
+			}
+			(_, None) => {
+				std::eprintln!("Missing -out (the filename of the target gma)");
+				EXIT_FAILURE
+			}
+			(Some(folder), Some(out)) => match symlink_policy(args) {
+				Some(symlinks) => create(Path::new(folder), &gma_out(Path::new(out)), has_switch(args, "-warninvalid"), symlinks),
+				None => {
+					std::eprintln!("-symlinks must be one of: follow, skip, error");
+					EXIT_FAILURE
+				}
+			},
+		}),
+
+		"extract" => Some(match arg_value(args, "-file") {
+			None => {
+				std::eprintln!("Missing -file (the filename of the gma to extract)");
+				EXIT_FAILURE
+			}
+			Some(file) => extract(Path::new(file), arg_value(args, "-out").map(Path::new)),
+		}),
+
+		// Drag and drop onto the executable
+		path if args.len() == 1 => {
+			let path = Path::new(path);
+			if path.is_dir() {
+				Some(create(
+					path,
+					&default_out(path),
+					has_switch(args, "-warninvalid"),
+					SymlinkPolicy::default(),
+				))
+			} else if path.is_file() {
+				Some(extract(path, None))
+			} else {
+				None
+			}
+		}
+
+		_ => None,
+	}
+}
+
+fn arg_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+	args.iter()
+		.position(|arg| arg.eq_ignore_ascii_case(name))
+		.and_then(|pos| args.get(pos + 1))
+		.map(|value| value.as_str())
+		.filter(|value| !value.is_empty())
+}
+
+fn has_switch(args: &[String], name: &str) -> bool {
+	args.iter().any(|arg| arg.eq_ignore_ascii_case(name))
+}
+
+/// `-symlinks follow|skip|error`, which isn't part of gmad's syntax
+fn symlink_policy(args: &[String]) -> Option<SymlinkPolicy> {
+	match arg_value(args, "-symlinks").map(str::to_ascii_lowercase).as_deref() {
+		None | Some("follow") => Some(SymlinkPolicy::Follow),
+		Some("skip") => Some(SymlinkPolicy::Skip),
+		Some("error") => Some(SymlinkPolicy::Error),
+		Some(_) => None,
+	}
+}
+
+fn create(folder: &Path, out: &Path, warn_invalid: bool, symlinks: SymlinkPolicy) -> i32 {
+	std::println!("Looking in folder \"{}\"", folder.display());
+
+	if !folder.is_dir() {
+		std::eprintln!("Folder does not exist: \"{}\"", folder.display());
+		return EXIT_FAILURE;
+	}
+
+	let json = match fs::read_to_string(folder.join("addon.json")) {
+		Ok(json) => json,
+		Err(err) => {
+			std::eprintln!("addon.json error: {}", err);
+			return EXIT_FAILURE;
+		}
+	};
+
+	let issues = addon_json::validate(&json);
+	for issue in &issues {
+		if issue.is_error() {
+			std::eprintln!("addon.json error: {}", issue);
+		} else {
+			std::eprintln!("addon.json warning: {}", issue);
+		}
+	}
+	if issues.iter().any(AddonJsonIssue::is_error) {
+		return EXIT_FAILURE;
+	}
+
+	let metadata = match serde_json::from_str::<GMAMetadata>(&json) {
+		Ok(metadata) => metadata,
+		Err(err) => {
+			std::eprintln!("addon.json error: {}", err);
+			return EXIT_FAILURE;
+		}
+	};
+
+	let ignore = metadata.ignore().cloned().unwrap_or_default();
+
+	whitelist::init();
+
+	let options = PackOptions {
+		symlinks,
+		..Default::default()
+	};
+
+	let invalid = match whitelist::verify(folder, &ignore, &options) {
+		Ok(invalid) => invalid,
+		Err(err) => {
+			std::eprintln!("Failed to read the folder: {}", describe_error(&err));
+			return EXIT_FAILURE;
+		}
+	};
+	for relative_path in &invalid {
+		if warn_invalid {
+			std::eprintln!("Warning: Not allowed by whitelist: {}", relative_path);
+		} else {
+			std::eprintln!("Not allowed by whitelist: {}", relative_path);
+		}
+	}
+
+	if !invalid.is_empty() && !warn_invalid {
+		std::eprintln!("File list verification failed");
+		return EXIT_FAILURE;
+	}
+
+	let gma = GMAFile::new(out, metadata);
+
+	let transaction = transaction!();
+	match gma.create(folder, &options, &*transaction) {
+		Ok(summary) => {
+			transaction.finished(turbonone!());
+
+			std::println!(
+				"Packed {} files [{} bytes], symbolic links: {:?}",
+				summary.files,
+				summary.size,
+				summary.symlink_policy
+			);
+			for symlink in &summary.symlinks {
+				std::println!("  {}: {}", symlink.path, describe_symlink(&symlink.outcome));
+			}
+
+			let size = out.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+			std::println!("Successfully saved to \"{}\" [{} bytes]", out.display(), size);
+
+			EXIT_SUCCESS
+		}
+		Err(GMAError::CaseCollision(collisions)) => {
+			transaction.cancel();
+
+			for collision in collisions {
+				std::eprintln!("Paths only differ by case: {}", collision.files.join(", "));
+			}
+			std::eprintln!("Failed to create the addon: rename or ignore all but one of each");
+			EXIT_FAILURE
+		}
+		Err(err) => {
+			transaction.cancel();
+
+			std::eprintln!("Failed to create the addon: {}", describe_error(&err));
+			EXIT_FAILURE
+		}
+	}
+}
+
+fn describe_error(err: &GMAError) -> String {
+	match err {
+		GMAError::Symlink(path) => format!("\"{}\" is a symbolic link, which -symlinks error doesn't allow", path),
+		err => err.to_string(),
+	}
+}
+
+fn describe_symlink(outcome: &SymlinkOutcome) -> String {
+	match outcome {
+		SymlinkOutcome::Followed => "followed".to_string(),
+		SymlinkOutcome::Skipped => "skipped".to_string(),
+		SymlinkOutcome::OutsideRoot { target } => format!("skipped, points outside the folder to \"{}\"", target.display()),
+		SymlinkOutcome::Loop => "skipped, points to one of its own parent folders".to_string(),
+		SymlinkOutcome::Broken => "skipped, points to something that doesn't exist".to_string(),
+	}
+}
+
+fn extract(file: &Path, out: Option<&Path>) -> i32 {
+	std::println!("Opening \"{}\"", file.display());
+
+	let mut gma = match GMAFile::open(file) {
+		Ok(gma) => gma,
+		Err(err) => {
+			std::eprintln!("Couldn't open \"{}\": {}", file.display(), err);
+			return EXIT_FAILURE;
+		}
+	};
+
+	// gmad extracts next to the GMA, in a folder with the same name, if -out isn't given
+	let out = out.map(Path::to_path_buf).unwrap_or_else(|| file.with_extension(""));
+
+	std::println!("Extracting to \"{}\"", out.display());
+
+	match gma.extract(ExtractDestination::Directory(out), &transaction!(), false, true) {
+		Ok(_) => {
+			std::println!("Done!");
+			EXIT_SUCCESS
+		}
+		Err(err) => {
+			std::eprintln!("Failed to extract the addon: {}", err);
+			EXIT_FAILURE
+		}
+	}
+}
+
+fn default_out(folder: &Path) -> PathBuf {
+	let mut out = folder.components().as_path().as_os_str().to_owned();
+	out.push(".gma");
+	PathBuf::from(out)
+}
+
+/// gmad adds the .gma extension to -out if it's missing, comparing it case sensitively
+fn gma_out(out: &Path) -> PathBuf {
+	if out.extension().is_some_and(|extension| extension == "gma") {
+		out.to_path_buf()
+	} else {
+		let mut out = out.as_os_str().to_owned();
+		out.push(".gma");
+		PathBuf::from(out)
+	}
+}
+
+#[test]
+fn test_gma_out() {
+	assert_eq!(gma_out(Path::new("addon.gma")), Path::new("addon.gma"));
+	assert_eq!(gma_out(Path::new("build/addon.GMA")), Path::new("build/addon.GMA.gma"));
+	assert_eq!(gma_out(Path::new("build/addon")), Path::new("build/addon.gma"));
+	assert_eq!(gma_out(Path::new("build/addon.v2")), Path::new("build/addon.v2.gma"));
+}
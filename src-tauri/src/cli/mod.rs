@@ -0,0 +1,268 @@
+#[cfg(feature = "cli")]
+use std::{
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "cli")]
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+#[cfg(feature = "cli")]
+mod gmad;
+
+#[cfg(feature = "cli")]
+mod explain;
+
+#[cfg(feature = "cli")]
+mod plan;
+
+#[cfg(feature = "cli")]
+use crate::{
+	gma::{ExtractDestination, ExtractGMAMut, SymlinkPolicy},
+	GMAError, GMAFile,
+};
+
+lazy_static! {
+	// Headless builds are always in CLI mode
+	pub static ref CLI_MODE: bool = cfg!(feature = "cli") && (!cfg!(feature = "gui") || std::env::args_os().len() > 1);
+}
+
+#[cfg(not(feature = "cli"))]
+pub(super) fn stdin() -> bool {
+	false
+}
+
+#[cfg(feature = "cli")]
+pub(super) fn stdin() -> bool {
+	use clap::{Arg, ArgAction, Command};
+
+	if !*CLI_MODE {
+		return false;
+	}
+
+	// Remove the logging::panic() hook
+	let _ = std::panic::take_hook();
+
+	let args: Vec<String> = std::env::args_os().skip(1).map(|arg| arg.to_string_lossy().into_owned()).collect();
+	if let Some(exit_code) = gmad::run(&args) {
+		std::process::exit(exit_code);
+	}
+
+	let command = Command::new("gmpublisher");
+
+	let matches = command
+	.version(env!("CARGO_PKG_VERSION"))
+	.arg_required_else_help(!cfg!(feature = "gui"))
+	.author("William Venner <william@venner.io>")
+	.about("Publish, extract and work with GMA files")
+	.after_help("gmad syntax is also accepted:\n  gmpublisher create -folder <PATH> -out <FILE> [-warninvalid]\n  gmpublisher extract -file <FILE> [-out <PATH>]")
+	.args(&[
+		Arg::new("extract")
+		.short('e')
+		.long("extract")
+		.value_name("FILE")
+		.num_args(1..)
+		.action(ArgAction::Append)
+		.help("Extracts one or more .GMA files, or compressed .bin files from the Workshop cache. Globs are accepted."),
+		//.conflicts_with_all(&["update", "in", "changes", "icon"]),
+
+		Arg::new("out")
+		.short('o')
+		.long("out")
+		.value_name("PATH")
+		.help("Sets the output path for extracting GMAs. Each GMA is extracted into its own named folder inside it. Defaults to the temp directory.")
+		.requires("extract"),
+		//.conflicts_with_all(&["update", "in", "changes", "icon"])
+
+		Arg::new("open")
+		.long("open")
+		.action(ArgAction::SetTrue)
+		.help("Opens the extracted folder in the file browser once done")
+		.requires("extract")
+	])
+	.subcommand(
+		Command::new("explain")
+		.about("Explains why files will or won't be packed into their addon, down to the rule responsible")
+		.args(&[
+			Arg::new("paths")
+			.value_name("PATH")
+			.num_args(1..)
+			.required(true)
+			.help("The files to explain"),
+
+			Arg::new("folder")
+			.long("folder")
+			.value_name("FOLDER")
+			.help("The addon folder the files are in. Defaults to the closest folder with an addon.json in it.")
+		])
+	)
+	.subcommand(
+		Command::new("plan")
+		.about("Lists what packing an addon would do with every file, its path in the GMA and the GMA's size, as JSON, without packing it")
+		.args(&[
+			Arg::new("folder")
+			.value_name("FOLDER")
+			.required(true)
+			.help("The addon folder, with an addon.json in it"),
+
+			Arg::new("out")
+			.short('o')
+			.long("out")
+			.value_name("FILE")
+			.help("Writes the plan to a file instead of stdout"),
+
+			Arg::new("symlinks")
+			.long("symlinks")
+			.value_name("POLICY")
+			.value_parser(["follow", "skip", "error"])
+			.default_value("follow")
+			.help("What to do with symbolic links in the folder")
+		])
+	)
+	/*.args(&[
+		Arg::with_name("update")
+		.short('u')
+		.long("update")
+		.value_name("PublishedFileId")
+		.takes_value(true)
+		.help("Publishes an update.")
+		.requires("in")
+		.conflicts_with_all(&["out", "extract"]),
+
+		Arg::with_name("in")
+		.long("in")
+		.value_name("PATH")
+		.takes_value(true)
+		.help("Sets the directory the GMA for updating will be built from.")
+		.requires("update")
+		.conflicts_with_all(&["out", "extract"]),
+
+		Arg::with_name("changes")
+		.long("changes")
+		.value_name("CHANGES")
+		.takes_value(true)
+		.help("Sets the changelog for an update.")
+		.requires("update")
+		.conflicts_with_all(&["out", "extract"]),
+
+		Arg::with_name("icon")
+		.long("icon")
+		.value_name("PATH")
+		.takes_value(true)
+		.help("Path to a (max 1 MB) JPG/PNG/GIF file for Workshop preview image updating.")
+		.requires("update")
+		.conflicts_with_all(&["out", "extract"])
+	])*/
+	.get_matches();
+
+	dprintln!("{:#?}", matches);
+
+	if let Some(matches) = matches.subcommand_matches("explain") {
+		let paths = matches.get_many::<String>("paths").into_iter().flatten().map(PathBuf::from).collect();
+		if !explain::run(paths, matches.get_one::<String>("folder").map(Path::new)) {
+			std::process::exit(1);
+		}
+		return true;
+	}
+
+	if let Some(matches) = matches.subcommand_matches("plan") {
+		let symlinks = match matches.get_one::<String>("symlinks").map(String::as_str) {
+			Some("skip") => SymlinkPolicy::Skip,
+			Some("error") => SymlinkPolicy::Error,
+			_ => SymlinkPolicy::Follow,
+		};
+		let folder = Path::new(matches.get_one::<String>("folder").unwrap());
+		if !plan::run(folder, matches.get_one::<String>("out").map(Path::new), symlinks) {
+			std::process::exit(1);
+		}
+		return true;
+	}
+
+	if let Some(extract_paths) = matches.get_many::<String>("extract") {
+		let dest = match matches.get_one::<String>("out") {
+			Some(out) => ExtractDestination::NamedDirectory(PathBuf::from(out)),
+			None => ExtractDestination::Temp,
+		};
+
+		if !extract(expand_globs(extract_paths), dest, matches.get_flag("open")) {
+			std::process::exit(1);
+		}
+	}
+
+	true
+}
+
+#[cfg(feature = "cli")]
+fn expand_globs<'a, I: Iterator<Item = &'a String>>(args: I) -> Vec<PathBuf> {
+	let mut paths = Vec::new();
+	for arg in args {
+		// Windows shells don't expand globs for us
+		if !Path::new(arg).exists() && arg.contains(['*', '?', '[']) {
+			match glob::glob(arg) {
+				Ok(matches) => {
+					let len = paths.len();
+					paths.extend(matches.filter_map(Result::ok).filter(|path| path.is_file()));
+					if paths.len() == len {
+						std::eprintln!("No files matched \"{}\"", arg);
+					}
+					continue;
+				}
+				Err(err) => {
+					std::eprintln!("Invalid glob \"{}\": {}", arg, err);
+					continue;
+				}
+			}
+		}
+		paths.push(PathBuf::from(arg));
+	}
+	paths.sort();
+	paths.dedup();
+	paths
+}
+
+#[cfg(feature = "cli")]
+fn open_gma(path: &Path) -> Result<GMAFile, GMAError> {
+	match GMAFile::open(path) {
+		Err(GMAError::InvalidHeader) if crate::path::has_extension(path, "bin") => {
+			let transaction = transaction!();
+			let result = GMAFile::decompress(path, &*transaction);
+			transaction.finished(turbonone!());
+			result
+		}
+		result => result,
+	}
+}
+
+/// Extracts every path in parallel, returning whether all of them succeeded
+#[cfg(feature = "cli")]
+fn extract(paths: Vec<PathBuf>, dest: ExtractDestination, open_after_extract: bool) -> bool {
+	if paths.is_empty() {
+		std::eprintln!("No GMA files to extract.");
+		return false;
+	}
+
+	let failed = AtomicUsize::new(0);
+
+	paths.par_iter().for_each(|path| {
+		if !path.is_file() {
+			std::eprintln!("Invalid GMA file path provided: \"{}\"", path.display());
+			failed.fetch_add(1, Ordering::Relaxed);
+			return;
+		}
+
+		match open_gma(path).and_then(|mut gma| gma.extract(dest.clone(), &transaction!(), open_after_extract, true)) {
+			Ok(extracted) => std::println!("\"{}\" -> \"{}\"", path.display(), extracted.display()),
+			Err(err) => {
+				std::eprintln!("Failed to extract \"{}\": {}", path.display(), err);
+				failed.fetch_add(1, Ordering::Relaxed);
+			}
+		}
+	});
+
+	let failed = failed.into_inner();
+	if paths.len() > 1 {
+		std::println!("Extracted {}/{} GMAs", paths.len() - failed, paths.len());
+	}
+
+	failed == 0
+}
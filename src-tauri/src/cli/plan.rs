@@ -0,0 +1,52 @@
+// `gmpublisher plan <FOLDER>`, a dry run of packing an addon that prints what will happen to every file as JSON
+
+use std::{fs, path::Path};
+
+use crate::{
+	gma::{whitelist, PackOptions, SymlinkPolicy},
+	GMAFile, GMAMetadata,
+};
+
+/// Plans packing `folder`, writing the plan to `out` or stdout, and returns whether it succeeded
+pub fn run(folder: &Path, out: Option<&Path>, symlinks: SymlinkPolicy) -> bool {
+	let metadata = match fs::read_to_string(folder.join("addon.json"))
+		.map_err(|err| err.to_string())
+		.and_then(|json| serde_json::from_str::<GMAMetadata>(&json).map_err(|err| err.to_string()))
+	{
+		Ok(metadata) => metadata,
+		Err(err) => {
+			std::eprintln!("addon.json error: {}", err);
+			return false;
+		}
+	};
+
+	whitelist::init();
+
+	let options = PackOptions {
+		symlinks,
+		..Default::default()
+	};
+
+	let plan = match GMAFile::new(folder.with_extension("gma"), metadata).plan(folder, &options) {
+		Ok(plan) => plan,
+		Err(err) => {
+			std::eprintln!("Failed to plan the addon: {}", err);
+			return false;
+		}
+	};
+
+	let json = serde_json::to_string_pretty(&plan).unwrap();
+	match out {
+		Some(out) => match fs::write(out, json) {
+			Ok(_) => true,
+			Err(err) => {
+				std::eprintln!("Failed to write \"{}\": {}", out.display(), err);
+				false
+			}
+		},
+		None => {
+			std::println!("{}", json);
+			true
+		}
+	}
+}
@@ -14,6 +14,10 @@ pub fn invoke_handler() -> impl Fn(tauri::Invoke<tauri::Wry>) + Send + Sync + 's
 		crate::webview::warn,
 		crate::transactions::websocket,
 		crate::transactions::cancel_transaction,
+		crate::transactions::pause_transaction,
+		crate::transactions::resume_transaction,
+		crate::transactions::active_transactions,
+		crate::transactions::transaction_history,
 		crate::appdata::update_settings,
 		crate::appdata::validate_gmod,
 		crate::appdata::window_resized,
@@ -28,7 +32,14 @@ pub fn invoke_handler() -> impl Fn(tauri::Invoke<tauri::Wry>) + Send + Sync + 's
 		crate::steam::workshop::browse_my_workshop,
 		crate::steam::workshop::workshop_item_channel,
 		crate::steam::downloads::workshop_download,
+		crate::steam::downloads::download_queue,
+		crate::steam::downloads::retry_downloads,
+		crate::steam::downloads::move_download_to_front,
+		crate::steam::downloads::set_download_priority,
 		crate::steam::publishing::verify_whitelist,
+		crate::steam::publishing::explain_paths,
+		crate::steam::publishing::whitelist_report,
+		crate::gma::whitelist::addon_whitelist_info,
 		crate::steam::publishing::publish,
 		crate::steam::publishing::verify_icon,
 		crate::steam::publishing::publish_icon,
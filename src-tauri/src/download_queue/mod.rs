@@ -0,0 +1,7 @@
+// The bookkeeping behind Steam Workshop downloads, which doesn't need Steam itself
+
+mod queue;
+pub use queue::{DownloadQueue, DownloadState, QueuedDownload};
+
+mod scheduler;
+pub use scheduler::DownloadScheduler;
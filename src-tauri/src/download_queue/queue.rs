@@ -0,0 +1,237 @@
+use std::{
+	fs,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{gma::ExtractDestination, ErrorCode, PublishedFileId};
+
+/// Where an item in the [`DownloadQueue`] is up to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+	Queued,
+	Downloading,
+	Extracting,
+	Done,
+	Failed,
+}
+impl DownloadState {
+	/// Whether the item still needs work, so it's picked up again if gmpublisher is closed before it's done
+	pub fn unfinished(self) -> bool {
+		matches!(self, DownloadState::Queued | DownloadState::Downloading | DownloadState::Extracting)
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedDownload {
+	pub item: PublishedFileId,
+	pub extract_destination: ExtractDestination,
+	/// The collection it's being downloaded as part of
+	#[serde(default)]
+	pub group: Option<PublishedFileId>,
+	/// Higher priorities are downloaded first
+	#[serde(default)]
+	pub priority: i32,
+	pub state: DownloadState,
+	/// The [`ErrorCode`] it failed with
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+impl QueuedDownload {
+	pub fn new(item: PublishedFileId, extract_destination: ExtractDestination, group: Option<PublishedFileId>) -> QueuedDownload {
+		QueuedDownload {
+			item,
+			extract_destination,
+			group,
+			priority: 0,
+			state: DownloadState::Queued,
+			error: None,
+		}
+	}
+
+	/// Whether this is `item`, or part of the collection `item`
+	fn is(&self, item: PublishedFileId) -> bool {
+		self.item == item || self.group == Some(item)
+	}
+}
+
+/// Every item queued for download and where it's up to, which is saved whenever it changes so the queue survives restarts
+#[derive(Debug, Default)]
+pub struct DownloadQueue {
+	/// Where the queue is saved, if anywhere
+	path: Option<PathBuf>,
+	downloads: Vec<QueuedDownload>,
+}
+impl DownloadQueue {
+	/// Loads the queue saved at `path`, forgetting the items that were done
+	pub fn load(path: PathBuf) -> DownloadQueue {
+		let mut downloads = match fs::read(&path) {
+			Ok(json) => serde_json::from_slice::<Vec<QueuedDownload>>(&json).unwrap_or_else(|err| {
+				eprintln!("Failed to read the download queue from {:?}: {}", path, err);
+				Vec::new()
+			}),
+			Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+			Err(err) => {
+				eprintln!("Failed to read the download queue from {:?}: {}", path, err);
+				Vec::new()
+			}
+		};
+
+		downloads.retain(|download| download.state != DownloadState::Done);
+
+		DownloadQueue { path: Some(path), downloads }
+	}
+
+	fn save(&self) {
+		if let Some(path) = &self.path {
+			if let Err(err) = DownloadQueue::write(path, &self.downloads) {
+				eprintln!("Failed to save the download queue to {:?}: {}", path, err);
+			}
+		}
+	}
+
+	fn write(path: &Path, downloads: &[QueuedDownload]) -> Result<(), anyhow::Error> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		// Moved over the queue once it's written, so closing gmpublisher while saving can't leave it half written
+		let tmp_path = path.with_extension("json.tmp");
+		fs::write(&tmp_path, serde_json::to_vec(downloads)?)?;
+		fs::rename(tmp_path, path)?;
+
+		Ok(())
+	}
+
+	fn find_mut(&mut self, item: PublishedFileId) -> Option<&mut QueuedDownload> {
+		self.downloads.iter_mut().find(|download| download.item == item)
+	}
+
+	/// Queues `download`, replacing its item if it was already in the queue
+	pub fn queue(&mut self, mut download: QueuedDownload) {
+		download.state = DownloadState::Queued;
+		download.error = None;
+
+		match self.find_mut(download.item) {
+			Some(queued) => *queued = download,
+			None => self.downloads.push(download),
+		}
+
+		self.save();
+	}
+
+	pub fn set_state(&mut self, item: PublishedFileId, state: DownloadState) {
+		if let Some(download) = self.find_mut(item) {
+			if download.state != state {
+				download.state = state;
+				download.error = None;
+				self.save();
+			}
+		}
+	}
+
+	pub fn failed(&mut self, item: PublishedFileId, error: ErrorCode) {
+		if let Some(download) = self.find_mut(item) {
+			download.state = DownloadState::Failed;
+			download.error = Some(error.to_string());
+			self.save();
+		}
+	}
+
+	/// Sets the priority of an item, or of every item in a collection
+	pub fn set_priority(&mut self, item: PublishedFileId, priority: i32) {
+		for download in self.downloads.iter_mut().filter(|download| download.is(item)) {
+			download.priority = priority;
+		}
+		self.save();
+	}
+
+	/// Moves an item, or every item in a collection, to the front of the queue, so it's picked up first if gmpublisher is restarted
+	pub fn move_to_front(&mut self, item: PublishedFileId) {
+		// Nothing to do if none of it is behind something else
+		if !self
+			.downloads
+			.iter()
+			.skip_while(|download| download.is(item))
+			.any(|download| download.is(item))
+		{
+			return;
+		}
+
+		let (mut front, rest): (Vec<QueuedDownload>, Vec<QueuedDownload>) =
+			std::mem::take(&mut self.downloads).into_iter().partition(|download| download.is(item));
+		front.extend(rest);
+		self.downloads = front;
+		self.save();
+	}
+
+	/// The items that weren't done when gmpublisher was last closed, or that are in progress now
+	pub fn unfinished(&self) -> Vec<QueuedDownload> {
+		self.downloads.iter().filter(|download| download.state.unfinished()).cloned().collect()
+	}
+
+	pub fn failures(&self) -> Vec<QueuedDownload> {
+		self.downloads
+			.iter()
+			.filter(|download| download.state == DownloadState::Failed)
+			.cloned()
+			.collect()
+	}
+
+	pub fn to_vec(&self) -> Vec<QueuedDownload> {
+		self.downloads.clone()
+	}
+}
+
+#[test]
+fn test_download_queue() {
+	let dir = std::env::temp_dir().join(format!("gmpublisher_test_download_queue_{}", std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	let path = dir.join("download_queue.json");
+
+	let mut queue = DownloadQueue::load(path.clone());
+	assert!(queue.to_vec().is_empty());
+
+	for item in 1..=4 {
+		queue.queue(QueuedDownload::new(PublishedFileId(item), ExtractDestination::Temp, None));
+	}
+	queue.set_state(PublishedFileId(1), DownloadState::Done);
+	queue.set_state(PublishedFileId(2), DownloadState::Downloading);
+	queue.set_state(PublishedFileId(3), DownloadState::Extracting);
+	queue.failed(PublishedFileId(4), ErrorCode::DownloadFailed);
+
+	// Done items are forgotten, and everything else is picked up where it was left
+	let mut queue = DownloadQueue::load(path.clone());
+	let items = |downloads: Vec<QueuedDownload>| downloads.into_iter().map(|download| download.item.0).collect::<Vec<_>>();
+	assert_eq!(items(queue.to_vec()), [2, 3, 4]);
+	assert_eq!(items(queue.unfinished()), [2, 3]);
+	assert_eq!(items(queue.failures()), [4]);
+	assert_eq!(queue.failures()[0].error.as_deref(), Some("ERR_DOWNLOAD_FAILED"));
+
+	// Retrying queues it again
+	queue.queue(QueuedDownload::new(
+		PublishedFileId(4),
+		ExtractDestination::Addons,
+		Some(PublishedFileId(100)),
+	));
+	queue.move_to_front(PublishedFileId(100));
+	queue.set_priority(PublishedFileId(100), 1);
+
+	let mut queue = DownloadQueue::load(path.clone());
+	assert!(queue.failures().is_empty());
+	assert_eq!(items(queue.unfinished()), [4, 2, 3]);
+
+	let retried = &queue.to_vec()[0];
+	assert!(matches!(retried.extract_destination, ExtractDestination::Addons));
+	assert_eq!(retried.priority, 1);
+
+	// Nothing is saved if nothing moves
+	fs::remove_file(&path).unwrap();
+	queue.move_to_front(PublishedFileId(4));
+	queue.move_to_front(PublishedFileId(5));
+	assert!(!path.exists());
+
+	let _ = fs::remove_dir_all(&dir);
+}
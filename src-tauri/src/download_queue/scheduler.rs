@@ -0,0 +1,190 @@
+use crate::PublishedFileId;
+
+#[derive(Debug)]
+struct Scheduled<T> {
+	item: PublishedFileId,
+	/// The collection it's being downloaded as part of
+	group: Option<PublishedFileId>,
+	priority: i32,
+	download: T,
+}
+
+/// Decides which queued items are downloaded, and when
+///
+/// At most `concurrency` items are downloaded at once. The next one to start is the queued item with the highest priority, or the first of
+/// those queued. Items from the same collection are queued next to each other, so collections are downloaded one after another rather than
+/// all at once.
+#[derive(Debug)]
+pub struct DownloadScheduler<T> {
+	concurrency: usize,
+	queued: Vec<Scheduled<T>>,
+	active: Vec<PublishedFileId>,
+}
+impl<T> DownloadScheduler<T> {
+	pub fn new(concurrency: usize) -> DownloadScheduler<T> {
+		DownloadScheduler {
+			concurrency: concurrency.max(1),
+			queued: Vec::new(),
+			active: Vec::new(),
+		}
+	}
+
+	pub fn set_concurrency(&mut self, concurrency: usize) {
+		self.concurrency = concurrency.max(1);
+	}
+
+	/// Whether there's nothing queued or downloading
+	pub fn is_empty(&self) -> bool {
+		self.queued.is_empty() && self.active.is_empty()
+	}
+
+	pub fn contains(&self, item: PublishedFileId) -> bool {
+		self.active.contains(&item) || self.queued.iter().any(|scheduled| scheduled.item == item)
+	}
+
+	/// The queued items, in the order they'd be started in if none of them were held back
+	pub fn queue(&self) -> Vec<PublishedFileId> {
+		let mut order: Vec<&Scheduled<T>> = self.queued.iter().collect();
+		order.sort_by_key(|scheduled| std::cmp::Reverse(scheduled.priority));
+		order.into_iter().map(|scheduled| scheduled.item).collect()
+	}
+
+	/// Queues `item` after the rest of its collection, or at the back if none of it is queued
+	///
+	/// Returns false if the item is already queued or downloading.
+	pub fn push(&mut self, item: PublishedFileId, group: Option<PublishedFileId>, priority: i32, download: T) -> bool {
+		if self.contains(item) {
+			return false;
+		}
+
+		let scheduled = Scheduled {
+			item,
+			group,
+			priority,
+			download,
+		};
+
+		match group.and_then(|group| self.queued.iter().rposition(|scheduled| scheduled.group == Some(group))) {
+			Some(pos) => self.queued.insert(pos + 1, scheduled),
+			None => self.queued.push(scheduled),
+		}
+
+		true
+	}
+
+	/// Sets the priority of a queued item, or of every queued item in a collection
+	pub fn set_priority(&mut self, item: PublishedFileId, priority: i32) -> bool {
+		let mut found = false;
+		for scheduled in self
+			.queued
+			.iter_mut()
+			.filter(|scheduled| scheduled.item == item || scheduled.group == Some(item))
+		{
+			scheduled.priority = priority;
+			found = true;
+		}
+		found
+	}
+
+	/// Moves a queued item, or every queued item in a collection, to the front of the queue so it's started next
+	///
+	/// Its priority is raised to the highest queued priority if it's lower, as the front of the queue only goes first among equals.
+	pub fn move_to_front(&mut self, item: PublishedFileId) -> bool {
+		let (mut front, rest): (Vec<Scheduled<T>>, Vec<Scheduled<T>>) = std::mem::take(&mut self.queued)
+			.into_iter()
+			.partition(|scheduled| scheduled.item == item || scheduled.group == Some(item));
+
+		if front.is_empty() {
+			self.queued = rest;
+			return false;
+		}
+
+		if let Some(highest) = rest.iter().map(|scheduled| scheduled.priority).max() {
+			for scheduled in front.iter_mut() {
+				scheduled.priority = scheduled.priority.max(highest);
+			}
+		}
+
+		front.extend(rest);
+		self.queued = front;
+
+		true
+	}
+
+	/// Takes the next download to start, if fewer than `concurrency` are downloading
+	///
+	/// Queued downloads that aren't `ready` are skipped over, and stay queued.
+	pub fn next<F: Fn(&T) -> bool>(&mut self, ready: F) -> Option<T> {
+		if self.active.len() >= self.concurrency {
+			return None;
+		}
+
+		let mut next: Option<(usize, i32)> = None;
+		for (i, scheduled) in self.queued.iter().enumerate() {
+			if next.is_none_or(|(_, priority)| scheduled.priority > priority) && ready(&scheduled.download) {
+				next = Some((i, scheduled.priority));
+			}
+		}
+
+		let scheduled = self.queued.remove(next?.0);
+		self.active.push(scheduled.item);
+		Some(scheduled.download)
+	}
+
+	/// A download stopped, whether it finished, failed or was cancelled, so another can take its place
+	pub fn finished(&mut self, item: PublishedFileId) {
+		self.active.retain(|active| *active != item);
+	}
+
+	/// Takes the queued downloads that `remove` returns true for
+	pub fn remove<F: Fn(&T) -> bool>(&mut self, remove: F) -> Vec<T> {
+		let (removed, queued) = std::mem::take(&mut self.queued)
+			.into_iter()
+			.partition(|scheduled| remove(&scheduled.download));
+		self.queued = queued;
+		removed.into_iter().map(|scheduled: Scheduled<T>| scheduled.download).collect()
+	}
+}
+
+#[test]
+fn test_scheduler() {
+	let id = PublishedFileId;
+	let collection = Some(id(100));
+
+	let mut scheduler = DownloadScheduler::new(2);
+	assert!(scheduler.push(id(1), None, 0, 1));
+	assert!(scheduler.push(id(2), collection, 0, 2));
+	assert!(scheduler.push(id(3), None, 0, 3));
+	assert!(!scheduler.push(id(1), None, 0, 1));
+
+	// Collections are kept together
+	assert!(scheduler.push(id(4), collection, 0, 4));
+	assert_eq!(scheduler.queue(), [id(1), id(2), id(4), id(3)]);
+
+	// Higher priorities go first
+	assert!(scheduler.set_priority(id(3), 1));
+	assert_eq!(scheduler.queue(), [id(3), id(1), id(2), id(4)]);
+
+	// Moving to the front goes ahead of higher priorities, and moves the whole collection
+	assert!(scheduler.move_to_front(id(100)));
+	assert_eq!(scheduler.queue(), [id(2), id(4), id(3), id(1)]);
+	assert!(!scheduler.move_to_front(id(5)));
+
+	// Only two at once, and items that aren't ready are skipped
+	assert_eq!(scheduler.next(|download| *download != 2), Some(4));
+	assert_eq!(scheduler.next(|_| true), Some(2));
+	assert_eq!(scheduler.next(|_| true), None);
+	assert!(!scheduler.push(id(2), None, 0, 2));
+
+	scheduler.finished(id(4));
+	assert_eq!(scheduler.next(|_| true), Some(3));
+
+	scheduler.set_concurrency(4);
+	assert_eq!(scheduler.remove(|download| *download == 1), [1]);
+	assert_eq!(scheduler.next(|_| true), None);
+	assert!(!scheduler.is_empty());
+
+	scheduler.finished(id(2));
+	scheduler.finished(id(3));
+	assert!(scheduler.is_empty());
+}
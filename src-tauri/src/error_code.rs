@@ -0,0 +1,198 @@
+//! Every error the backend reports to the frontend
+//!
+//! Errors are sent as their code, such as `ERR_IO_ERROR`, which the frontend translates through the i18n files. Errors with data append
+//! it after a colon, with lists joined by newlines, and the translation receives it as `{data}`.
+
+// Most errors come from Steam and the webview, which don't exist in headless builds
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
+
+use std::fmt::{Display, Write};
+
+use crate::GMAError;
+
+#[cfg(feature = "gui")]
+use crate::steam::publishing::PublishError;
+
+/// The data an [`ErrorCode`] carries, as it is appended to the code
+trait ErrorData {
+	fn write_data(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+}
+impl ErrorData for String {
+	fn write_data(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(self)
+	}
+}
+impl ErrorData for Vec<String> {
+	fn write_data(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		for (i, line) in self.iter().enumerate() {
+			if i > 0 {
+				f.write_char('\n')?;
+			}
+			f.write_str(line)?;
+		}
+		Ok(())
+	}
+}
+
+macro_rules! error_codes {
+	($($(#[$meta:meta])* $variant:ident $(($data:ident: $ty:ty))? => $code:literal,)*) => {
+		#[derive(Debug, Clone, PartialEq, Eq)]
+		pub enum ErrorCode {
+			$($(#[$meta])* $variant $(($ty))?,)*
+		}
+		impl ErrorCode {
+			/// Every code, each of which needs a translation
+			pub const CODES: &'static [&'static str] = &[$($code),*];
+
+			pub fn code(&self) -> &'static str {
+				match self {
+					$(ErrorCode::$variant { .. } => $code,)*
+				}
+			}
+		}
+		impl Display for ErrorCode {
+			fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+				match self {
+					$(ErrorCode::$variant $(($data))? => {
+						f.write_str($code)?;
+						$(
+							f.write_char(':')?;
+							$data.write_data(f)?;
+						)?
+						Ok(())
+					})*
+				}
+			}
+		}
+	};
+}
+
+error_codes! {
+	Unknown => "ERR_UNKNOWN",
+	Cancelled => "ERR_CANCELLED",
+	IOError => "ERR_IO_ERROR",
+	/// A file that couldn't be read
+	PathIOError(path: String) => "ERR_PATH_IO_ERROR",
+
+	GMAFormatError => "ERR_GMA_FORMAT_ERROR",
+	GMAInvalidHeader => "ERR_GMA_INVALID_HEADER",
+	GMAEntryNotFound => "ERR_GMA_ENTRY_NOT_FOUND",
+	Lzma => "ERR_LZMA",
+	NoAddonsFound => "ERR_NO_ADDONS_FOUND",
+
+	/// Paths that don't pass the whitelist
+	Whitelist(paths: Vec<String>) => "ERR_WHITELIST",
+	/// Problems with an addon.json, its type or its tags
+	AddonJson(issues: Vec<String>) => "ERR_ADDON_JSON",
+	/// Problems with an addon.json that gmad ignores, such as unknown keys
+	AddonJsonWarning(issues: Vec<String>) => "WARN_ADDON_JSON",
+	/// Groups of paths that only differ by case
+	DuplicateEntries(collisions: Vec<String>) => "ERR_DUPLICATE_ENTRIES",
+	Symlink(path: String) => "ERR_SYMLINK",
+	NoEntries => "ERR_NO_ENTRIES",
+	InvalidContentPath => "ERR_INVALID_CONTENT_PATH",
+	MultipleGMAs => "ERR_MULTIPLE_GMAS",
+	IconTooLarge => "ERR_ICON_TOO_LARGE",
+	IconTooSmall => "ERR_ICON_TOO_SMALL",
+	IconInvalidFormat => "ERR_ICON_INVALID_FORMAT",
+	ImageError(error: String) => "ERR_IMAGE_ERROR",
+
+	SteamError(error: String) => "ERR_STEAM_ERROR",
+	ItemNotFound => "ERR_ITEM_NOT_FOUND",
+	#[allow(dead_code)]
+	ItemInaccessible => "ERR_ITEM_INACCESSIBLE",
+	#[allow(dead_code)]
+	WorkshopLegalAgreement => "ERR_WORKSHOP_LEGAL_AGREEMENT",
+	DownloadFailed => "ERR_DOWNLOAD_FAILED",
+	/// Steam says the item was downloaded, but it isn't there
+	DownloadMissing => "ERR_DOWNLOAD_MISSING",
+
+	// Why the transaction websocket rejected a request
+	TransactionNotFound => "ERR_TRANSACTION_NOT_FOUND",
+	Handshake => "ERR_HANDSHAKE",
+	ProtocolVersion => "ERR_PROTOCOL_VERSION",
+	Unauthorized => "ERR_UNAUTHORIZED",
+}
+
+impl serde::Serialize for ErrorCode {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		serializer.serialize_str(&self.to_string())
+	}
+}
+
+impl From<&GMAError> for ErrorCode {
+	fn from(error: &GMAError) -> ErrorCode {
+		match error {
+			GMAError::IOError => ErrorCode::IOError,
+			GMAError::PathIO(path) => ErrorCode::PathIOError(path.display().to_string()),
+			GMAError::FormatError => ErrorCode::GMAFormatError,
+			GMAError::InvalidHeader => ErrorCode::GMAInvalidHeader,
+			GMAError::EntryNotFound => ErrorCode::GMAEntryNotFound,
+			GMAError::LZMA => ErrorCode::Lzma,
+			GMAError::Cancelled => ErrorCode::Cancelled,
+			GMAError::CaseCollision(collisions) => ErrorCode::DuplicateEntries(collisions.iter().map(ToString::to_string).collect()),
+			GMAError::Symlink(path) => ErrorCode::Symlink(path.clone()),
+		}
+	}
+}
+impl From<GMAError> for ErrorCode {
+	fn from(error: GMAError) -> ErrorCode {
+		ErrorCode::from(&error)
+	}
+}
+
+#[cfg(feature = "gui")]
+impl From<&PublishError> for ErrorCode {
+	fn from(error: &PublishError) -> ErrorCode {
+		match error {
+			PublishError::NotWhitelisted(report) => ErrorCode::Whitelist(report.paths().map(str::to_owned).collect()),
+			PublishError::InvalidAddonJson(issues) => ErrorCode::AddonJson(issues.iter().map(ToString::to_string).collect()),
+			PublishError::NoEntries => ErrorCode::NoEntries,
+			PublishError::DuplicateEntry(collisions) => ErrorCode::DuplicateEntries(collisions.iter().map(ToString::to_string).collect()),
+			PublishError::Symlink(path) => ErrorCode::Symlink(path.clone()),
+			PublishError::InvalidContentPath => ErrorCode::InvalidContentPath,
+			PublishError::MultipleGMAs => ErrorCode::MultipleGMAs,
+			PublishError::IconTooLarge => ErrorCode::IconTooLarge,
+			PublishError::IconTooSmall => ErrorCode::IconTooSmall,
+			PublishError::IconInvalidFormat => ErrorCode::IconInvalidFormat,
+			PublishError::IOError => ErrorCode::IOError,
+			PublishError::Cancelled => ErrorCode::Cancelled,
+			PublishError::SteamError(error) => ErrorCode::SteamError(error.to_string()),
+			PublishError::ImageError(error) => ErrorCode::ImageError(error.to_string()),
+		}
+	}
+}
+#[cfg(feature = "gui")]
+impl From<PublishError> for ErrorCode {
+	fn from(error: PublishError) -> ErrorCode {
+		ErrorCode::from(&error)
+	}
+}
+
+#[test]
+fn test_error_codes() {
+	let en: serde_json::Map<String, serde_json::Value> = serde_json::from_str(include_str!("../../i18n/en.json")).unwrap();
+	for code in ErrorCode::CODES {
+		assert!(en.contains_key(*code), "{} is missing from i18n/en.json", code);
+	}
+
+	assert_eq!(ErrorCode::IOError.to_string(), "ERR_IO_ERROR");
+	assert_eq!(ErrorCode::Symlink("lua/a.lua".to_string()).to_string(), "ERR_SYMLINK:lua/a.lua");
+	assert_eq!(
+		ErrorCode::Whitelist(vec!["a.exe".to_string(), "b.dll".to_string()]).to_string(),
+		"ERR_WHITELIST:a.exe\nb.dll"
+	);
+	assert_eq!(
+		ErrorCode::AddonJsonWarning(vec!["unknown key \"author\" will be ignored".to_string()]).to_string(),
+		"WARN_ADDON_JSON:unknown key \"author\" will be ignored"
+	);
+	assert_eq!(ErrorCode::from(GMAError::Cancelled).code(), "ERR_CANCELLED");
+	assert_eq!(
+		ErrorCode::from(GMAError::PathIO("addon/lua/a.lua".into())).to_string(),
+		"ERR_PATH_IO_ERROR:addon/lua/a.lua"
+	);
+	assert_eq!(json!(ErrorCode::SteamError("Timeout".to_string())), "ERR_STEAM_ERROR:Timeout");
+}
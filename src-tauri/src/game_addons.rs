@@ -74,43 +74,6 @@ impl GameAddons {
 		Some((path, file_name[..(file_name.len() - 4)].to_owned()))
 	}
 
-	pub fn get_ws_id<S: AsRef<str>>(_file_name: S) -> Option<PublishedFileId> {
-		let _file_name = _file_name.as_ref();
-		let file_name = _file_name.strip_prefix("ds_").unwrap_or(_file_name);
-
-		if let Ok(id) = str::parse::<u64>(file_name) {
-			return Some(PublishedFileId(id));
-		}
-
-		let id = GameAddons::extract_suffix_ws_id(file_name);
-		if id == 0 {
-			None
-		} else {
-			Some(PublishedFileId(id))
-		}
-	}
-
-	fn extract_suffix_ws_id<S: AsRef<str>>(file_name: S) -> u64 {
-		let mut id = 0u64;
-		for char in file_name.as_ref()
-			.chars()
-			.rev() // Reverse iterator so we're looking at the suffix (the PublishedFileId)
-			.take_while(|c| c.is_ascii_digit())
-			.collect::<Vec<char>>()
-			.into_iter()
-			.rev()
-		{
-			match id.checked_add(char::to_digit(char, 10).unwrap() as u64) {
-				None => return 0,
-				Some(id_op) => match 10_u64.checked_mul(id_op) {
-					None => return 0,
-					Some(id_op) => id = id_op,
-				},
-			}
-		}
-		id
-	}
-
 	fn get_workshop_content_dir<P: AsRef<Path>>(gmod: P) -> Option<PathBuf> {
 		Some(gmod.as_ref().parent()?.parent()?.join("workshop/content/4000"))
 	}
@@ -183,13 +146,8 @@ impl GameAddons {
 				Err(_) => return,
 			};
 
-			for (path, _file_name) in addons.filter_map(GameAddons::gma_check) {
-				let file_name = _file_name.strip_prefix("ds_").unwrap_or(&_file_name);
-				let id = GameAddons::extract_suffix_ws_id(file_name);
-
-				tx_addons_metadata
-					.send((path, if id == 0 { None } else { Some(PublishedFileId(id / 10)) }))
-					.unwrap();
+			for (path, file_name) in addons.filter_map(GameAddons::gma_check) {
+				tx_addons_metadata.send((path, libgma::workshop_id(&file_name))).unwrap();
 			}
 		});
 
@@ -345,10 +303,6 @@ pub fn get_installed_addon(path: PathBuf) -> Option<Arc<Addon>> {
 	if path.is_absolute() && path.is_file() && crate::path::has_extension(&path, "gma") {
 		match GMAFile::open(&path) {
 			Ok(mut gma) => {
-				if let Some(id) = GameAddons::get_ws_id(path.file_name().unwrap().to_string_lossy()) {
-					gma.set_ws_id(id);
-				}
-
 				ignore! { gma.metadata() };
 
 				let gma = Arc::new(Addon::Installed(gma));
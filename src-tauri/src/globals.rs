@@ -1,10 +1,19 @@
+#[cfg(feature = "steam")]
 pub const GMOD_APP_ID: steamworks::AppId = steamworks::AppId(4000);
 
+lazy_static! {
+	pub static ref APP_DATA: crate::appdata::AppData = crate::appdata::AppData::init();
+}
+
+#[cfg(feature = "steam")]
 lazy_static! {
 	pub static ref STEAMWORKS: crate::steam::Steam = crate::steam::Steam::init();
+}
+
+#[cfg(feature = "gui")]
+lazy_static! {
 	pub static ref GAME_ADDONS: crate::game_addons::GameAddons = crate::game_addons::GameAddons::init();
 	pub static ref ADDON_SIZE_ANALYZER: crate::addon_size_analyzer::AddonSizeAnalyzer = crate::addon_size_analyzer::AddonSizeAnalyzer::init();
-	pub static ref APP_DATA: crate::appdata::AppData = crate::appdata::AppData::init();
 	pub static ref WEBVIEW: crate::webview::WrappedWebview = crate::webview::WrappedWebview::pending();
 	pub static ref SEARCH: crate::search::Search = crate::search::Search::init();
 }
@@ -60,6 +69,7 @@ macro_rules! webview_emit {
 	};
 }
 
+#[cfg(feature = "gui")]
 pub(super) fn init_globals() {
 	println!("Initializing Steamworks...");
 	lazy_static::initialize(&STEAMWORKS);
@@ -76,7 +86,5 @@ pub(super) fn init_globals() {
 		GAME_ADDONS.discover_addons();
 	});
 
-	rayon::spawn(|| {
-		&*crate::gma::whitelist::ADDON_WHITELIST;
-	});
+	rayon::spawn(crate::gma::whitelist::init);
 }
@@ -1,22 +1,11 @@
-use std::{
-	fs::{self, File},
-	io::{BufWriter, Cursor, Read, SeekFrom},
-	path::{Path, PathBuf},
-	sync::{
-		atomic::{AtomicBool, AtomicUsize, Ordering},
-		Arc,
-	},
-};
+use std::{fs, path::PathBuf};
 
 use crate::{app_data, transactions::Transaction};
 
-use super::{whitelist, GMAEntry, GMAError, GMAFile, GMAMetadata, GMAReader};
+use super::{GMAError, GMAFile, GMAReader};
 
 use lazy_static::lazy_static;
-use rayon::{
-	iter::{IntoParallelRefIterator, ParallelIterator},
-	ThreadPool,
-};
+use rayon::ThreadPool;
 use serde::{Deserialize, Serialize};
 
 lazy_static! {
@@ -100,125 +89,6 @@ impl ExtractDestination {
 	}
 }
 
-impl GMAFile {
-	pub fn decompress<P: AsRef<Path>>(path: P, transaction: Transaction) -> Result<GMAFile, GMAError> {
-		main_thread_forbidden!();
-
-		let input = File::open(path.as_ref())?;
-
-		let bytes_total = input.metadata().map(|metadata| metadata.len()).ok();
-
-		let lzma_decoder = xz2::stream::Stream::new_lzma_decoder(u64::MAX).map_err(|err| {
-			eprintln!("LZMA error: {err:?}");
-			GMAError::LZMA
-		})?;
-
-		let mut xz_decoder = xz2::read::XzDecoder::new_stream(input, lzma_decoder);
-
-		let mut output = if let Some(ref bytes_total) = bytes_total {
-			Vec::with_capacity((*bytes_total * 5) as usize)
-		} else {
-			Vec::new()
-		};
-
-		let result = if let Some(bytes_total) = bytes_total {
-			transaction.data((turbonone!(), bytes_total));
-
-			let bytes_total_f = bytes_total as f64;
-
-			let complete = Arc::new(AtomicBool::new(false));
-			let complete_ref = complete.clone();
-
-			struct StupidlyUnsafeProgressMonitorPtr<T: Read>(*mut xz2::read::XzDecoder<T>);
-			unsafe impl<T: Read> Sync for StupidlyUnsafeProgressMonitorPtr<T> {}
-			unsafe impl<T: Read> Send for StupidlyUnsafeProgressMonitorPtr<T> {}
-
-			let xz_decoder_ptr = StupidlyUnsafeProgressMonitorPtr(&mut xz_decoder as *mut _);
-			rayon::spawn(move || {
-				#[allow(clippy::redundant_locals)]
-				let xz_decoder_ptr = xz_decoder_ptr;
-				while !complete_ref.load(Ordering::Acquire) {
-					let xz_decoder = unsafe { &mut *xz_decoder_ptr.0 };
-
-					let bytes_read = xz_decoder.total_in() as f64;
-					transaction.progress(bytes_read / bytes_total_f);
-
-					let decompressed_bytes = xz_decoder.total_out();
-					if decompressed_bytes > bytes_total {
-						transaction.data((turbonone!(), decompressed_bytes));
-					}
-
-					sleep_ms!(25);
-				}
-			});
-
-			let result = xz_decoder.read_to_end(&mut output);
-			complete.store(true, Ordering::Release);
-			result
-		} else {
-			xz_decoder.read_to_end(&mut output)
-		};
-
-		if let Err(err) = result {
-			// No idea why, but XZ always errors with "corrupt xz stream" even when the decompression succeeds.
-			// Maybe a difference in the way Gmod encoded the XZ stream?
-			// Let's just check if the file has been fully read, then naively continue.
-			let mut input = xz_decoder.into_inner();
-			if input.read(&mut [0u8]).ok() != Some(0) {
-				eprintln!("LZMA error: {err:#?}");
-				return Err(GMAError::LZMA);
-			}
-		}
-
-		output.shrink_to_fit();
-
-		let decompressed_size = output.len() as u64;
-
-		let mut gma = GMAFile::read_header(GMAReader::MemBuffer(Cursor::new(output.into())), path)?;
-		gma.size = decompressed_size;
-
-		Ok(gma)
-	}
-
-	fn stream_entry_bytes_with_transaction(
-		handle: &mut GMAReader,
-		entries_start: u64,
-		entry_path: &PathBuf,
-		entry: &GMAEntry,
-		transaction: &Transaction,
-	) -> Result<(), GMAError> {
-		use std::io::Write;
-
-		fs::create_dir_all(entry_path.with_file_name(""))?;
-		let f = File::create(entry_path)?;
-
-		handle.seek(SeekFrom::Start(entries_start + entry.index))?;
-
-		let mut w = BufWriter::new(f);
-		crate::stream_bytes_with_transaction(&mut **handle, &mut w, entry.size as usize, transaction)?;
-
-		w.flush()?;
-
-		Ok(())
-	}
-
-	fn stream_entry_bytes(handle: &mut GMAReader, entries_start: u64, entry_path: &PathBuf, entry: &GMAEntry) -> Result<(), GMAError> {
-		use std::io::Write;
-
-		fs::create_dir_all(entry_path.with_file_name(""))?;
-		let f = File::create(entry_path)?;
-
-		handle.seek(SeekFrom::Start(entries_start + entry.index))?;
-
-		let mut w = BufWriter::new(f);
-		crate::stream_bytes(&mut **handle, &mut w, entry.size as usize)?;
-
-		w.flush()?;
-
-		Ok(())
-	}
-}
-
 pub trait ExtractGMAImmut {
 	fn extract(
 		&self,
@@ -256,73 +126,23 @@ impl ExtractGMAImmut for GMAFile {
 	) -> Result<PathBuf, GMAError> {
 		let result = THREAD_POOL.install(move || {
 			let dest_path = dest.prepare(&self.extracted_name);
-			let entries_start = self.pointers.entries;
-
-			let entries = self.entries.as_ref().unwrap();
-			let entries_len_f = entries.len() as f64;
-			let entries_len_i = entries.len();
-
-			self.read()?; // Don't waste time with the threads if the file fails to open
-
-			let i = AtomicUsize::new(0);
-
-			let finished = |mut dest_path: PathBuf| {
-				if i.fetch_add(1, Ordering::AcqRel) > entries_len_i || transaction.aborted() {
-					return;
-				}
-
-				transaction.finished(dest_path.to_owned());
+			self.extract_to(&dest_path, ignore_whitelist, &**transaction).map(|_| dest_path)
+		});
 
-				if open_after_extract {
-					crate::path::open(&dest_path);
-				}
+		match result {
+			Ok(ref dest_path) => {
+				if !transaction.aborted() {
+					transaction.finished(dest_path.to_owned());
 
-				let metadata = self.metadata.as_ref().unwrap();
-				if let GMAMetadata::Standard { .. } = metadata {
-					if let Ok(json) = serde_json::ser::to_string_pretty(metadata) {
-						dest_path.push("addon.json");
-						if let Some(parent) = dest_path.parent() {
-							ignore! { fs::create_dir_all(parent) };
-						}
-						ignore! { fs::write(dest_path, json.as_bytes()) };
+					if open_after_extract {
+						crate::path::open(dest_path);
 					}
 				}
-			};
-
-			entries
-				.par_iter()
-				.try_for_each(|(entry_path, entry)| -> Result<(), GMAError> {
-					let mut handle = self.read()?;
-
-					if ignore_whitelist || whitelist::check(entry_path) {
-						if transaction.aborted() {
-							return Err(GMAError::Cancelled);
-						}
-
-						// FIXME count errors, check if errors == number of entries, return an error instead of finished
-						ignore! { GMAFile::stream_entry_bytes(&mut handle, entries_start, &dest_path.join(entry_path), entry) };
-
-						let i = i.fetch_add(1, Ordering::AcqRel) + 1;
-						transaction.progress((i as f64) / entries_len_f);
-
-						if i == entries_len_i {
-							(finished)(dest_path.to_owned());
-						}
-					} else {
-						transaction.error("ERR_WHITELIST", entry_path.clone()); // TODO
-					}
-
-					Ok(())
-				})
-				.map(|_| {
-					(finished)(dest_path.to_owned());
-					dest_path
-				})
-		});
-
-		if !transaction.aborted() {
-			if let Err(ref error) = result {
-				transaction.error(error.to_string(), turbonone!());
+			}
+			Err(ref error) => {
+				if !transaction.aborted() {
+					transaction.error(error, turbonone!());
+				}
 			}
 		}
 
@@ -341,24 +161,11 @@ impl ExtractGMAImmut for GMAFile {
 		path.push(&self.extracted_name);
 		path.push(&entry_path);
 
-		let mut handle = match handle {
-			Some(handle) => handle,
-			None => self.read()?,
-		};
-
-		let entry = self
-			.entries
-			.as_ref()
-			.expect("Expected entries to be read by this point")
-			.get(&entry_path)
-			.ok_or(GMAError::EntryNotFound)?;
-
-		let result =
-			GMAFile::stream_entry_bytes_with_transaction(&mut handle, self.pointers.entries, &path, entry, transaction).map(|_| path.to_owned());
+		let result = self.extract_entry_to(&entry_path, &path, handle, &**transaction).map(|_| path.to_owned());
 
 		if let Err(ref error) = result {
 			if !transaction.aborted() {
-				transaction.error(error.to_string(), turbonone!());
+				transaction.error(error, turbonone!());
 			}
 		} else if !transaction.aborted() {
 			if open_after_extract {
@@ -397,6 +204,7 @@ impl ExtractGMAMut for GMAFile {
 	}
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn extract_gma(gma_path: PathBuf, dest: ExtractDestination) -> Option<u32> {
 	let mut gma = GMAFile::open(gma_path).ok()?;
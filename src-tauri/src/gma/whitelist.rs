@@ -1,364 +1,198 @@
-// https://github.com/garrynewman/bootil/blob/beb4cec8ad29533965491b767b177dc549e62d23/src/3rdParty/globber.cpp
-// https://github.com/Facepunch/gmad/blob/master/include/AddonWhiteList.h
-
-use std::{sync::LazyLock, time::Duration};
-
-const ADDON_WHITELIST_OFFLINE: &[&str] = &[
-	"lua/*.lua",
-	"scenes/*.vcd",
-	"particles/*.pcf",
-	"resource/fonts/*.ttf",
-	"scripts/vehicles/*.txt",
-	"resource/localization/*/*.properties",
-	"maps/*.bsp",
-	"maps/*.lmp",
-	"maps/*.nav",
-	"maps/*.ain",
-	"maps/thumb/*.png",
-	"sound/*.wav",
-	"sound/*.mp3",
-	"sound/*.ogg",
-	"materials/*.vmt",
-	"materials/*.vtf",
-	"materials/*.png",
-	"materials/*.jpg",
-	"materials/*.jpeg",
-	"materials/colorcorrection/*.raw",
-	"models/*.mdl",
-	"models/*.phy",
-	"models/*.ani",
-	"models/*.vvd",
-	"models/*.vtx",
-	"!models/*.sw.vtx",
-	"!models/*.360.vtx",
-	"!models/*.xbox.vtx",
-	"gamemodes/*/*.txt",
-	"!gamemodes/*/*/*.txt",
-	"gamemodes/*/*.fgd",
-	"!gamemodes/*/*/*.fgd",
-	"gamemodes/*/logo.png",
-	"gamemodes/*/icon24.png",
-	"gamemodes/*/gamemode/*.lua",
-	"gamemodes/*/entities/effects/*.lua",
-	"gamemodes/*/entities/weapons/*.lua",
-	"gamemodes/*/entities/entities/*.lua",
-	"gamemodes/*/backgrounds/*.png",
-	"gamemodes/*/backgrounds/*.jpg",
-	"gamemodes/*/backgrounds/*.jpeg",
-	"gamemodes/*/content/models/*.mdl",
-	"gamemodes/*/content/models/*.phy",
-	"gamemodes/*/content/models/*.ani",
-	"gamemodes/*/content/models/*.vvd",
-	"gamemodes/*/content/models/*.vtx",
-	"!gamemodes/*/content/models/*.sw.vtx",
-	"!gamemodes/*/content/models/*.360.vtx",
-	"!gamemodes/*/content/models/*.xbox.vtx",
-	"gamemodes/*/content/materials/*.vmt",
-	"gamemodes/*/content/materials/*.vtf",
-	"gamemodes/*/content/materials/*.png",
-	"gamemodes/*/content/materials/*.jpg",
-	"gamemodes/*/content/materials/*.jpeg",
-	"gamemodes/*/content/materials/colorcorrection/*.raw",
-	"gamemodes/*/content/scenes/*.vcd",
-	"gamemodes/*/content/particles/*.pcf",
-	"gamemodes/*/content/resource/fonts/*.ttf",
-	"gamemodes/*/content/scripts/vehicles/*.txt",
-	"gamemodes/*/content/resource/localization/*/*.properties",
-	"gamemodes/*/content/maps/*.bsp",
-	"gamemodes/*/content/maps/*.nav",
-	"gamemodes/*/content/maps/*.ain",
-	"gamemodes/*/content/maps/thumb/*.png",
-	"gamemodes/*/content/sound/*.wav",
-	"gamemodes/*/content/sound/*.mp3",
-	"gamemodes/*/content/sound/*.ogg",
-	"data_static/*.txt",
-	"data_static/*.dat",
-	"data_static/*.json",
-	"data_static/*.xml",
-	"data_static/*.csv",
-	"shaders/*.vcs",
-];
-
-pub const DEFAULT_IGNORE: &[&str] = &[
-	".git/*",
-	"*.psd",
-	"*.pdn",
-	"*.xcf",
-	"*.kra",
-	"*.svn",
-	"*.ini",
-	"*.rtf",
-	"*.pdf",
-	"*.log",
-	"*.prt",
-	"*.vmf",
-	"*.vmx",
-	".DS_Store",
-	".gitignore",
-	".gitmodules",
-	".gitattributes",
-	".vscode/*",
-	".github/*",
-	".vs/*",
-	".editorconfig",
-	"LICENSE",
-	"LICENSE.*",
-	"license",
-	"license.*",
-	"README",
-	"README.*",
-	"readme",
-	"readme.*",
-	"addon.json",
-	"addon.txt",
-	"addon.jpg",
-	"thumbs.db",
-	"desktop.ini",
-	"models/*.sw.vtx",
-	"models/*.360.vtx",
-	"models/*.xbox.vtx",
-	"gamemodes/*/content/models/*.sw.vtx",
-	"gamemodes/*/content/models/*.360.vtx",
-	"gamemodes/*/content/models/*.xbox.vtx",
-];
-
-pub static ADDON_WHITELIST: LazyLock<&'static [&'static str]> = LazyLock::new(download_addon_whitelist);
-
-fn download_addon_whitelist() -> &'static [&'static str] {
-	if std::env::var_os("ADDON_WHITELIST_OFFLINE").is_some() {
-		return ADDON_WHITELIST_OFFLINE;
-	}
-
-	ureq::get("https://raw.githubusercontent.com/Facepunch/gmad/master/include/AddonWhiteList.h")
-		.timeout(Duration::from_secs(2))
-		.call()
-		.map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))
-		.and_then(|response| response.into_string())
-		.and_then(|response| {
-			let mut wildcard = Vec::new();
-
-			let captures = regex::Regex::new(r#"static +const +char\* +Wildcard\s*\[\s*\]\s*=\s*\{\s*([\s\S]*?)\s*NULL,?\s*};"#)
-				.unwrap()
-				.captures(response.leak())
-				.and_then(|captures| captures.get(1))
-				.ok_or_else(|| std::io::Error::other("Failed to parse addon whitelist"))?;
-
-			let line_regex = regex::Regex::new(r#""(.+?)","#).unwrap();
-
-			for line in captures.as_str().lines() {
-				let line = line.trim();
-				if line.is_empty() {
-					continue;
-				} else if line == "NULL" {
-					break;
-				} else if let Some(capture) = line_regex.captures(line) {
-					let glob = capture.get(1).unwrap().as_str();
-					wildcard.push(&*glob.to_string().leak());
-				}
-			}
-
-			if wildcard.is_empty() {
-				return Err(std::io::Error::other("Failed to parse addon whitelist (empty)"));
-			}
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	sync::OnceLock,
+	time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub use libgma::whitelist::*;
+
+const ADDON_WHITELIST_URL: &str = "https://raw.githubusercontent.com/Facepunch/gmad/master/include/AddonWhiteList.h";
+
+/// How long a downloaded whitelist is used before checking for a newer one
+const CACHE_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24);
+
+/// Points at a local AddonWhiteList.h or JSON array of patterns to use instead of downloading one, overriding the setting
+const ADDON_WHITELIST_ENV: &str = "GMPUBLISHER_ADDON_WHITELIST";
+
+/// Where the whitelist in use came from
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum WhitelistSource {
+	/// The copy compiled into gmpublisher
+	BuiltIn,
+	/// Freshly downloaded from gmad's repository, or confirmed to be up to date
+	Downloaded { fetched: u64, etag: Option<String> },
+	/// Downloaded earlier, used because downloading failed or it isn't stale yet
+	Cached { fetched: u64, etag: Option<String> },
+	/// A local file from the settings or environment
+	Local { path: PathBuf },
+}
 
-			if !wildcard.contains(&"lua/*.lua") {
-				// This should definitely be in there, so if it isn't, something has gone wrong. Probably.
-				return Err(std::io::Error::other("Failed to parse addon whitelist (missing lua/*.lua)"));
-			}
+#[derive(Debug, Clone, Serialize)]
+pub struct WhitelistInfo {
+	#[serde(flatten)]
+	pub source: WhitelistSource,
+	pub patterns: usize,
+}
 
-			println!("Downloaded up to date addon whitelist: {wildcard:#?}");
+#[derive(Debug, Serialize, Deserialize)]
+struct WhitelistCache {
+	fetched: u64,
+	etag: Option<String>,
+	whitelist: Vec<String>,
+}
 
-			Ok(&*wildcard.leak())
-		})
-		.map_err(|err| {
-			eprintln!("Failed to download addon whitelist: {:#?}", err);
-			err
-		})
-		.unwrap_or(ADDON_WHITELIST_OFFLINE)
+/// A freshly downloaded AddonWhiteList.h
+struct Downloaded {
+	whitelist: Vec<String>,
+	etag: Option<String>,
 }
 
-const WILD_BYTE: u8 = b'*';
-const QUESTION_BYTE: u8 = b'?';
-const EXCLAMATION_BYTE: u8 = b'!';
+static WHITELIST_INFO: OnceLock<WhitelistInfo> = OnceLock::new();
 
-fn globber(wild: &str, str: &str) -> bool {
-	unsafe {
-		let mut cp: *const u8 = core::ptr::null();
-		let mut mp: *const u8 = core::ptr::null();
+/// Loads the addon whitelist for libgma to use, from (in order of preference) a local file, the cache if it's fresh,
+/// gmad's repository, the cache even if it's stale, and finally libgma's built in copy
+///
+/// libgma uses its built in copy until this has finished, so it can run in the background.
+pub fn init() {
+	let (whitelist, source) = load();
 
-		let (mut wild, wild_max) = (wild.as_ptr(), wild.as_ptr().add(wild.len()));
-		let (mut str, str_max) = (str.as_ptr(), str.as_ptr().add(str.len()));
+	println!("Using {} addon whitelist patterns from {:?}", whitelist.len(), source);
 
-		while wild < wild_max && str < str_max && *wild != WILD_BYTE {
-			if *wild != *str && *wild != QUESTION_BYTE {
-				return false;
-			}
-			wild = wild.add(1);
-			str = str.add(1);
-		}
+	WHITELIST_INFO.get_or_init(|| WhitelistInfo {
+		source,
+		patterns: whitelist.len(),
+	});
 
-		while str < str_max {
-			if *wild == WILD_BYTE {
-				wild = wild.add(1);
-				if wild >= wild_max {
-					return true;
-				}
-				mp = wild;
-				cp = str.add(1);
-			} else if *wild == *str || *wild == QUESTION_BYTE {
-				wild = wild.add(1);
-				str = str.add(1);
-			} else {
-				wild = mp;
-				str = cp;
-				cp = cp.add(1);
-			}
-		}
+	set_addon_whitelist(whitelist);
+}
 
-		while wild < wild_max && *wild == WILD_BYTE {
-			wild = wild.add(1);
-		}
+/// Where the whitelist in use came from, if it has been loaded yet
+pub fn info() -> Option<&'static WhitelistInfo> {
+	WHITELIST_INFO.get()
+}
 
-		wild >= wild_max
-	}
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn addon_whitelist_info() -> Option<WhitelistInfo> {
+	info().cloned()
 }
 
-/// Check if a path is allowed in a GMA file
-pub fn check(str: &str) -> bool {
-	let mut valid = false;
+fn load() -> (Vec<String>, WhitelistSource) {
+	let local = std::env::var_os(ADDON_WHITELIST_ENV)
+		.map(PathBuf::from)
+		.or_else(|| app_data!().settings.read().addon_whitelist.clone());
 
-	for glob in ADDON_WHITELIST.iter() {
-		if glob.as_bytes().first() == Some(&EXCLAMATION_BYTE) {
-			if globber(&glob[1..], str) {
-				valid = false;
+	if let Some(path) = local {
+		match load_local(&path) {
+			Ok(whitelist) => return (whitelist, WhitelistSource::Local { path }),
+			Err(err) => {
+				eprintln!("Failed to load addon whitelist from {:?}: {}", path, err);
 			}
-		} else if !valid && globber(glob, str) {
-			valid = true;
 		}
 	}
 
-	valid
-}
+	if std::env::var_os("ADDON_WHITELIST_OFFLINE").is_some() {
+		return (builtin(), WhitelistSource::BuiltIn);
+	}
 
-pub fn filter_default_ignored(str: &str) -> bool {
-	for glob in DEFAULT_IGNORE {
-		if globber(glob, str) {
-			return false;
+	let cache_path = app_data!().user_data_dir().join("addon_whitelist.json");
+	let cache = fs::read_to_string(&cache_path)
+		.ok()
+		.and_then(|json| serde_json::from_str::<WhitelistCache>(&json).ok())
+		.filter(|cache| !cache.whitelist.is_empty());
+
+	if let Some(cache) = &cache {
+		if unix_now().saturating_sub(cache.fetched) < CACHE_MAX_AGE.as_secs() {
+			return (
+				cache.whitelist.clone(),
+				WhitelistSource::Cached {
+					fetched: cache.fetched,
+					etag: cache.etag.clone(),
+				},
+			);
 		}
 	}
 
-	true
-}
-
-/// Check if a path is ignored by a list of custom globs
-pub fn is_ignored(str: &str, ignore: &[String]) -> bool {
-	if ignore.is_empty() {
-		return false;
-	}
+	match download(cache.as_ref().and_then(|cache| cache.etag.as_deref())) {
+		Ok(downloaded) => {
+			let fetched = unix_now();
 
-	for glob in ignore {
-		if globber(glob, str) {
-			return true;
-		}
-	}
+			let (whitelist, etag) = match downloaded {
+				Some(Downloaded { whitelist, etag }) => (whitelist, etag),
 
-	false
-}
+				// 304 Not Modified, so the cache is still up to date
+				None => {
+					let cache = cache.unwrap();
+					(cache.whitelist, cache.etag)
+				}
+			};
+
+			let cache = WhitelistCache { fetched, etag, whitelist };
+			if let Err(err) = fs::create_dir_all(app_data!().user_data_dir().as_path())
+				.map_err(anyhow::Error::from)
+				.and_then(|_| Ok(fs::write(&cache_path, serde_json::to_vec(&cache)?)?))
+			{
+				eprintln!("Failed to cache addon whitelist: {}", err);
+			}
 
-#[test]
-fn test_whitelist() {
-	let good: &'static [&'static str] = &[
-		"lua/test.lua",
-		"lua/lol/test.lua",
-		"lua/lua/testing.lua",
-		"gamemodes/test/something.txt",
-		"gamemodes/test/content/sound/lol.wav",
-		"materials/lol.jpeg",
-		"gamemodes/the_gamemode_name/backgrounds/file_name.jpg",
-		"gamemodes/my_base_defence/backgrounds/1.jpg",
-	];
-
-	let bad: &'static [&'static str] = &[
-		"test.lua",
-		"lua/test.exe",
-		"lua/lol/test.exe",
-		"gamemodes/test",
-		"gamemodes/test/something",
-		"gamemodes/test/something/something.exe",
-		"gamemodes/test/content/sound/lol.vvv",
-		"materials/lol.vvv",
-	];
-
-	for good in good {
-		assert!(check(good), "{}", good);
-	}
+			(cache.whitelist, WhitelistSource::Downloaded { fetched, etag: cache.etag })
+		}
 
-	for good in ADDON_WHITELIST.iter() {
-		assert!(check(good.replace('*', "test").strip_suffix('\0').unwrap()));
+		Err(err) => {
+			eprintln!("Failed to download addon whitelist: {}", err);
+
+			match cache {
+				Some(cache) => (
+					cache.whitelist,
+					WhitelistSource::Cached {
+						fetched: cache.fetched,
+						etag: cache.etag,
+					},
+				),
+				None => (builtin(), WhitelistSource::BuiltIn),
+			}
+		}
 	}
+}
 
-	for good in ADDON_WHITELIST.iter() {
-		assert!(check(good.replace('*', "a").strip_suffix('\0').unwrap()));
-	}
+fn builtin() -> Vec<String> {
+	ADDON_WHITELIST_OFFLINE.iter().map(|glob| glob.to_string()).collect()
+}
 
-	for bad in bad {
-		assert!(!check(bad));
+fn load_local(path: &Path) -> Result<Vec<String>, anyhow::Error> {
+	let contents = fs::read_to_string(path)?;
+	if crate::path::has_extension(path, "json") {
+		Ok(parse_json(&contents)?)
+	} else {
+		Ok(parse_header(&contents)?)
 	}
 }
 
-#[test]
-fn test_ignore() {
-	let ignored: &'static [&'static str] = &[
-		".git/index",
-		".git/info/exclude",
-		".git/logs/head",
-		".git/logs/refs/heads/4.0.0",
-		".git/logs/refs/heads/master",
-		".git/logs/refs/remotes/origin/4.0.0",
-		".git/logs/refs/remotes/origin/cracker",
-		".git/logs/refs/remotes/origin/cracker-no-minigames",
-		".git/logs/refs/remotes/origin/master",
-		".git/objects/00/007c75922055623f4177467fd50a7d573c2c86",
-		"blah.psd",
-		"some/location/blah.psd",
-		"some/blah/blah.pdn",
-		"hi.xcf",
-		"addon.jpg",
-		"addon.json",
-	];
-
-	for ignored in ignored {
-		assert!(!filter_default_ignored(ignored));
+/// Downloads AddonWhiteList.h, returning `None` if it hasn't changed since `etag`
+fn download(etag: Option<&str>) -> Result<Option<Downloaded>, anyhow::Error> {
+	let mut request = ureq::get(ADDON_WHITELIST_URL).timeout(Duration::from_secs(2));
+	if let Some(etag) = etag {
+		request = request.set("If-None-Match", etag);
 	}
 
-	let default_ignore: Vec<String> = DEFAULT_IGNORE.iter().cloned().map(|x| x.to_string()).collect();
-	for ignored in ignored {
-		assert!(is_ignored(ignored, &default_ignore));
+	let response = request.call()?;
+	if response.status() == 304 {
+		return Ok(None);
 	}
 
-	assert!(is_ignored("lol.txt", &["lol.txt\0".to_string()]));
-	assert!(is_ignored("lua/hello.lua", &["lua/*.lua\0".to_string()]));
-	assert!(is_ignored("lua/hello.lua", &["lua/*\0".to_string()]));
-	assert!(!is_ignored("lol.txt", &[]));
+	let etag = response.header("ETag").map(str::to_owned);
+	let whitelist = parse_header(&response.into_string()?)?;
+
+	Ok(Some(Downloaded { whitelist, etag }))
 }
 
-#[test]
-fn test_exclusions() {
-	assert!(check("models/player.vtx"));
-	assert!(check("models/weapons/gun.vtx"));
-
-	assert!(!check("models/player.sw.vtx"));
-	assert!(!check("models/player.360.vtx"));
-	assert!(!check("models/player.xbox.vtx"));
-	assert!(!check("models/weapons/gun.sw.vtx"));
-
-	assert!(check("gamemodes/test/content/models/player.vtx"));
-	assert!(!check("gamemodes/test/content/models/player.sw.vtx"));
-	assert!(!check("gamemodes/test/content/models/player.360.vtx"));
-	assert!(!check("gamemodes/test/content/models/player.xbox.vtx"));
-
-	assert!(check("gamemodes/sandbox/info.txt"));
-	assert!(check("gamemodes/sandbox/sandbox.fgd"));
-	assert!(!check("gamemodes/sandbox/nested/info.txt"));
-	assert!(!check("gamemodes/sandbox/entities/weapons/info.txt"));
+fn unix_now() -> u64 {
+	SystemTime::now()
+		.duration_since(SystemTime::UNIX_EPOCH)
+		.map(|unix| unix.as_secs())
+		.unwrap_or(0)
 }
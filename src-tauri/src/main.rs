@@ -1,5 +1,9 @@
-#![cfg_attr(all(not(debug_assertions), target_os = "windows"), windows_subsystem = "windows")]
+#![cfg_attr(all(not(debug_assertions), target_os = "windows", feature = "gui"), windows_subsystem = "windows")]
 
+#[cfg(not(any(feature = "gui", feature = "cli")))]
+compile_error!("gmpublisher must be built with the `gui` and/or `cli` feature");
+
+#[cfg(feature = "gui")]
 use tauri::Manager;
 
 #[macro_use]
@@ -23,35 +27,61 @@ pub use util::*;
 pub mod transactions;
 pub use transactions::Transaction;
 
+pub use libgma::PublishedFileId;
+
+#[cfg(feature = "gui")]
 pub mod base64_image;
+#[cfg(feature = "gui")]
 pub use base64_image::Base64Image;
 
 pub mod appdata;
 pub use appdata::AppData;
 
+#[cfg(feature = "gui")]
 pub mod game_addons;
+#[cfg(feature = "gui")]
 pub use game_addons::GameAddons;
 
+#[cfg(feature = "gui")]
 pub mod addon_size_analyzer;
+#[cfg(feature = "gui")]
 pub use addon_size_analyzer::AddonSizeAnalyzer;
 
 pub mod gma;
 pub use gma::{GMAError, GMAFile, GMAMetadata};
 
+pub mod error_code;
+pub use error_code::ErrorCode;
+
+// Only used by Steam Workshop downloads, which need the webview
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub mod download_queue;
+
+// Headless builds link Steamworks, but only the webview has anything to ask Steam for
+#[cfg(feature = "steam")]
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
 pub mod steam;
+#[cfg(feature = "gui")]
 pub use steam::workshop::WorkshopItem;
 
+#[cfg(feature = "steam")]
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
 pub mod octopus;
+#[cfg(feature = "steam")]
 pub use octopus::*;
 
+#[cfg(feature = "gui")]
 pub mod content_generator;
+#[cfg(feature = "gui")]
 pub mod search;
+#[cfg(feature = "gui")]
 pub mod webview;
 
 mod cli;
+#[cfg(feature = "gui")]
 mod commands;
 
-#[cfg(debug_assertions)]
+#[cfg(all(debug_assertions, feature = "gui"))]
 fn deadlock_watchdog() {
 	std::thread::spawn(move || loop {
 		sleep!(10);
@@ -73,17 +103,24 @@ fn deadlock_watchdog() {
 }
 
 fn main() {
-	// https://github.com/WilliamVenner/gmpublisher/issues/210
-	if cfg!(target_os = "linux") {
-		std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
-	}
-
 	std::panic::set_hook(Box::new(logging::panic));
 
 	rayon::ThreadPoolBuilder::new().num_threads(*crate::NUM_THREADS).build_global().unwrap();
 
-	if cli::stdin() {
-		return;
+	#[cfg(feature = "gui")]
+	if !cli::stdin() {
+		gui();
+	}
+
+	#[cfg(not(feature = "gui"))]
+	cli::stdin();
+}
+
+#[cfg(feature = "gui")]
+fn gui() {
+	// https://github.com/WilliamVenner/gmpublisher/issues/210
+	if cfg!(target_os = "linux") {
+		std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
 	}
 
 	println!("gmpublisher v{}", env!("CARGO_PKG_VERSION"));
@@ -385,6 +385,7 @@ impl Search {
 			items
 				.par_iter()
 				.try_for_each_with(i, |i, search_item| {
+					transaction.pause_point();
 					if transaction.aborted() {
 						return Err(());
 					} else {
@@ -2,7 +2,7 @@ use parking_lot::{Condvar, Mutex, MutexGuard};
 use rayon::ThreadPool;
 
 use std::{
-	collections::HashSet, path::PathBuf, sync::{atomic::AtomicBool, Arc}
+	collections::{HashMap, HashSet}, path::PathBuf, sync::{atomic::AtomicBool, Arc}, time::Duration
 };
 
 use steamworks::{ClientManager, ItemState, PublishedFileId, QueryResults, UGC};
@@ -11,9 +11,12 @@ use crate::{
 	gma::{ExtractDestination, ExtractGMAMut},
 	transaction,
 	transactions::Transaction,
-	webview_emit, GMAFile, GMOD_APP_ID,
+	webview_emit, ErrorCode, GMAFile, GMOD_APP_ID,
 };
 
+use crate::download_queue::{DownloadQueue, DownloadScheduler};
+pub use crate::download_queue::{DownloadState, QueuedDownload};
+
 lazy_static! {
 	pub static ref DOWNLOADS: Downloads = Downloads::init();
 	static ref THREAD_POOL: ThreadPool = thread_pool!();
@@ -23,8 +26,13 @@ lazy_static! {
 pub struct DownloadInner {
 	item: PublishedFileId,
 	transaction: Transaction,
+	/// Started once the item has downloaded, as a sibling of `transaction`
+	extraction: Transaction,
 	sent_total: AtomicBool,
 	extract_destination: ExtractDestination,
+	/// The collection it's being downloaded as part of
+	group: Option<PublishedFileId>,
+	priority: i32,
 }
 impl std::hash::Hash for DownloadInner {
 	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -66,102 +74,130 @@ impl From<Vec<PublishedFileId>> for IDList {
 
 pub struct Downloads {
 	pending: Mutex<Vec<Download>>, // TODO consider using VecDeque?
-	downloading: Mutex<Vec<Download>>,
+	scheduler: Mutex<DownloadScheduler<Download>>,
 	watchdog: Condvar,
+	queue: Mutex<DownloadQueue>,
 }
 impl Downloads {
 	fn init() -> Self {
 		Self {
 			pending: Mutex::new(Vec::new()),
-			downloading: Mutex::new(Vec::new()),
+			scheduler: Mutex::new(DownloadScheduler::new(app_data!().settings.read().download_concurrency)),
 			watchdog: Condvar::new(),
+			queue: Mutex::new(DownloadQueue::load(app_data!().user_data_dir().join("download_queue.json"))),
 		}
 	}
 
-	fn extract(folder: PathBuf, item: PublishedFileId, extract_destination: ExtractDestination) {
+	fn extract(folder: PathBuf, item: PublishedFileId, extract_destination: ExtractDestination, transaction: Transaction) {
 		THREAD_POOL.spawn(move || {
-			let transaction = transaction!();
-			transaction.status("locating");
-
-			webview_emit!("ExtractionStarted", (transaction.id, turbonone!(), turbonone!(), Some(item)));
+			if transaction.aborted() {
+				DOWNLOADS.queue.lock().failed(item, ErrorCode::Cancelled);
+				return;
+			}
 
-			let mut gma = if folder.is_dir() {
-				let mut gma_path = None;
+			DOWNLOADS.queue.lock().set_state(item, DownloadState::Extracting);
 
-				if let Ok(read_dir) = folder.read_dir() {
-					for entry in read_dir.flatten() {
-						if !crate::path::has_extension(entry.path(), "gma") {
-							continue;
-						}
-						if gma_path.is_some() {
-							// TODO better handling here - just include the extra files in the addon
-							gma_path = None;
-							break;
-						} else {
-							gma_path = Some(entry.path());
-						}
+			match Downloads::extract_gma(folder, item, extract_destination, &transaction) {
+				Ok(()) => DOWNLOADS.queue.lock().set_state(item, DownloadState::Done),
+				Err(error) => {
+					if !transaction.aborted() {
+						transaction.error(error.clone(), turbonone!());
 					}
+					DOWNLOADS.queue.lock().failed(item, error);
 				}
+			}
+		});
+	}
+
+	fn extract_gma(
+		folder: PathBuf,
+		item: PublishedFileId,
+		extract_destination: ExtractDestination,
+		transaction: &Transaction,
+	) -> Result<(), ErrorCode> {
+		transaction.status("locating");
 
-				if let Some(path) = gma_path {
-					match GMAFile::open(path) {
-						Ok(gma) => gma,
-						Err(err) => return transaction.error(err.to_string(), turbonone!()),
+		webview_emit!("ExtractionStarted", (transaction.id, turbonone!(), turbonone!(), Some(item)));
+
+		let mut gma = if folder.is_dir() {
+			let mut gma_path = None;
+
+			if let Ok(read_dir) = folder.read_dir() {
+				for entry in read_dir.flatten() {
+					if !crate::path::has_extension(entry.path(), "gma") {
+						continue;
 					}
-				} else {
-					return transaction.error("ERR_DOWNLOAD_MISSING", turbonone!());
-				}
-			} else if folder.is_file() && crate::path::has_extension(&folder, "bin") {
-				match GMAFile::open(&folder) {
-					Ok(gma) => gma,
-					Err(_) => {
-						transaction.status("decompressing");
-						match GMAFile::decompress(folder, transaction.clone()) {
-							Ok(gma) => {
-								transaction.progress_reset();
-								gma
-							}
-							Err(err) => return transaction.error(err.to_string(), turbonone!()),
-						}
+					if gma_path.is_some() {
+						// TODO better handling here - just include the extra files in the addon
+						gma_path = None;
+						break;
+					} else {
+						gma_path = Some(entry.path());
 					}
 				}
+			}
+
+			if let Some(path) = gma_path {
+				GMAFile::open(path)?
 			} else {
-				return transaction.error("ERR_DOWNLOAD_MISSING", turbonone!());
-			};
+				return Err(ErrorCode::DownloadMissing);
+			}
+		} else if folder.is_file() && crate::path::has_extension(&folder, "bin") {
+			match GMAFile::open(&folder) {
+				Ok(gma) => gma,
+				Err(_) => {
+					transaction.status("decompressing");
+					let gma = GMAFile::decompress(folder, &**transaction)?;
+					transaction.progress_reset();
+					gma
+				}
+			}
+		} else {
+			return Err(ErrorCode::DownloadMissing);
+		};
 
-			gma.id = Some(item);
+		gma.id = Some(item);
 
-			transaction.status("reading_metadata");
-			transaction.data((Some(gma.metadata.as_ref().map(|metadata| metadata.title().to_owned())), gma.size));
+		transaction.status("reading_metadata");
+		transaction.data((Some(gma.metadata.as_ref().map(|metadata| metadata.title().to_owned())), gma.size));
 
-			if let Err(err) = gma.extract(extract_destination, &transaction, false, true) {
-				transaction.error(err.to_string(), turbonone!());
-			}
-		});
+		gma.extract(extract_destination, transaction, false, true)?;
+
+		Ok(())
 	}
 
+	/// Downloads (unless it's already installed) and extracts an item, as a child of `transaction`
 	fn push_download(
 		ugc: &UGC<ClientManager>,
 		pending: &mut MutexGuard<Vec<Arc<DownloadInner>>>,
-		extract_destination: &Arc<ExtractDestination>,
-		item: PublishedFileId,
+		download: QueuedDownload,
+		transaction: &Transaction,
 	) {
+		let item = download.item;
+		let item_transaction = transaction.child(1.);
+
 		let state = ugc.item_state(item);
 		if state.intersects(ItemState::INSTALLED) && !state.intersects(ItemState::NEEDS_UPDATE) {
+			DOWNLOADS.queue.lock().queue(download.clone());
+
 			if let Some(info) = ugc.item_install_info(item) {
-				Downloads::extract(PathBuf::from(info.folder), item, (**extract_destination).clone());
+				Downloads::extract(PathBuf::from(info.folder), item, download.extract_destination, item_transaction.child(1.));
 			} else {
-				let transaction = transaction!();
+				let transaction = item_transaction.child(1.);
 				webview_emit!("DownloadStarted", transaction.id);
 				transaction.data((0, item));
-				transaction.error("ERR_DOWNLOAD_MISSING", turbonone!());
+				transaction.error(ErrorCode::DownloadMissing, turbonone!());
+				DOWNLOADS.queue.lock().failed(item, ErrorCode::DownloadMissing);
 			}
 		} else {
 			let download = Arc::new(DownloadInner {
 				item,
 				sent_total: AtomicBool::new(false),
-				transaction: transaction!(),
-				extract_destination: (**extract_destination).clone(),
+				transaction: item_transaction.child(1.),
+				extraction: item_transaction.child(1.),
+				extract_destination: download.extract_destination,
+				group: download.group,
+				priority: download.priority,
 			});
 
 			webview_emit!("DownloadStarted", download.transaction.id);
@@ -169,20 +205,30 @@ impl Downloads {
 
 			pending.push(download);
 		}
+
+		item_transaction.finish_with_children();
 	}
 
-	pub fn download<IDs: Into<IDList>>(&self, ids: IDs) {
+	/// Downloads and extracts items, and the items in any collections among them
+	///
+	/// Returns a transaction that tracks every item, which finishes once they all have.
+	pub fn download<IDs: Into<IDList>>(&self, ids: IDs) -> Transaction {
+		let transaction = transaction!();
+
 		let mut ids: Vec<PublishedFileId> = ids.into().into();
 		let extract_destination = Arc::new(app_data!().settings.read().extract_destination.to_owned());
 
 		struct PossibleCollectionsState {
 			queue: Vec<PublishedFileId>,
 			downloaded: HashSet<PublishedFileId>,
+			/// The collection each item in a collection is being downloaded as part of, which for nested collections is the outermost one
+			collections: HashMap<PublishedFileId, PublishedFileId>,
 		}
 		impl PossibleCollectionsState {
 			fn new(queue: Vec<PublishedFileId>) -> Self {
 				Self {
 					downloaded: HashSet::from_iter(queue.iter().copied()),
+					collections: HashMap::new(),
 					queue,
 				}
 			}
@@ -221,6 +267,7 @@ impl Downloads {
 
 			let extract_destination = extract_destination.clone();
 			let possible_collections = possible_collections.clone();
+			let transaction = transaction.clone();
 
 			let done = Arc::new(());
 
@@ -243,23 +290,31 @@ impl Downloads {
 						let ugc = steam!().client().ugc();
 						for (i, item) in results.iter().enumerate() {
 							if let Some(item) = item {
+								let group = possible_collections.collections.get(&item.published_file_id).copied();
 								if item.file_type == steamworks::FileType::Collection {
+									let group = group.unwrap_or(item.published_file_id);
 									let children = results.get_children(i as u32).unwrap();
 									steam!().fetch_workshop_items(children.clone());
 									for item in children {
 										if possible_collections.downloaded.insert(item) {
 											possible_collections.queue.push(item);
+											possible_collections.collections.insert(item, group);
 										}
 									}
 								} else {
 									not_collections.push(item.published_file_id);
-									Downloads::push_download(&ugc, &mut pending, &extract_destination, item.published_file_id);
+									Downloads::push_download(
+										&ugc,
+										&mut pending,
+										QueuedDownload::new(item.published_file_id, (*extract_destination).clone(), group),
+										&transaction,
+									);
 								}
 							} else {
-								let transaction = transaction!();
+								let transaction = transaction.child(1.);
 								webview_emit!("DownloadStarted", transaction.id);
 								transaction.data((0, possible_collections_query[i]));
-								transaction.error("ERR_ITEM_NOT_FOUND", turbonone!());
+								transaction.error(ErrorCode::ItemNotFound, turbonone!());
 							}
 						}
 
@@ -281,18 +336,101 @@ impl Downloads {
 
 		let ugc = steam!().client().ugc();
 		for item in ids {
-			Downloads::push_download(&ugc, &mut pending, &extract_destination, item);
+			Downloads::push_download(
+				&ugc,
+				&mut pending,
+				QueuedDownload::new(item, (*extract_destination).clone(), None),
+				&transaction,
+			);
 		}
 
 		if !pending.is_empty() {
 			drop(pending);
 			self.start();
 		}
+
+		transaction.finish_with_children();
+		transaction
+	}
+
+	/// Downloads and extracts items that were queued before, each to where it was going to be extracted to
+	fn download_queued(&self, downloads: Vec<QueuedDownload>) -> Transaction {
+		let transaction = transaction!();
+
+		if !downloads.is_empty() {
+			let mut pending = self.pending.lock();
+			pending.reserve(downloads.len());
+
+			let ugc = steam!().client().ugc();
+			for download in downloads {
+				Downloads::push_download(&ugc, &mut pending, download, &transaction);
+			}
+
+			drop(pending);
+			self.start();
+		}
+
+		transaction.finish_with_children();
+		transaction
+	}
+
+	/// Picks up the downloads that weren't done when gmpublisher was last closed
+	pub fn resume(&self) -> Transaction {
+		let unfinished = self.queue.lock().unfinished();
+		self.download_queued(unfinished)
+	}
+
+	/// Tries the downloads that failed again
+	pub fn retry(&self) -> Transaction {
+		let failures = self.queue.lock().failures();
+		self.download_queued(failures)
+	}
+
+	pub fn queue(&self) -> Vec<QueuedDownload> {
+		self.queue.lock().to_vec()
+	}
+
+	/// Moves a queued item, or every queued item in a collection, to the front of the queue
+	///
+	/// Returns false if it isn't queued.
+	pub fn move_to_front(&self, item: PublishedFileId) -> bool {
+		let mut scheduler = self.scheduler.lock();
+		if !scheduler.move_to_front(item) {
+			return false;
+		}
+		self.queue.lock().move_to_front(item);
+		self.watchdog.notify_one();
+		true
+	}
+
+	/// Sets the priority of a queued item, or of every queued item in a collection
+	///
+	/// Returns false if it isn't queued.
+	pub fn set_priority(&self, item: PublishedFileId, priority: i32) -> bool {
+		let mut scheduler = self.scheduler.lock();
+		if !scheduler.set_priority(item, priority) {
+			return false;
+		}
+		self.queue.lock().set_priority(item, priority);
+		self.watchdog.notify_one();
+		true
 	}
 
 	pub fn start(&self) {
-		let mut downloading = self.downloading.lock();
-		downloading.append(&mut self.pending.lock());
+		let mut scheduler = self.scheduler.lock();
+		for download in self.pending.lock().drain(..) {
+			if scheduler.push(download.item, download.group, download.priority, download.clone()) {
+				// Only saved once it's known to be new, so asking for an item again doesn't reset where it's up to
+				self.queue.lock().queue(QueuedDownload {
+					priority: download.priority,
+					..QueuedDownload::new(download.item, download.extract_destination.clone(), download.group)
+				});
+			} else {
+				// It's already queued or downloading, which will extract it
+				download.transaction.cancel();
+				download.extraction.cancel();
+			}
+		}
 
 		self.watchdog.notify_one();
 	}
@@ -305,20 +443,28 @@ impl Downloads {
 				let mut in_progress = in_progress_ref.lock();
 				if let Ok(pos) = in_progress.binary_search_by_key(&result.published_file_id.0, |download| download.0) {
 					let download = in_progress.remove(pos);
+					DOWNLOADS.scheduler.lock().finished(download.item);
 					if let Some(error) = result.error {
 						dprintln!("ISteamUGC Download ERROR: {:?}", download.item);
-						download.transaction.error("ERR_STEAM_ERROR", error);
+						let error = ErrorCode::SteamError(error.to_string());
+						download.transaction.error(error.clone(), turbonone!());
+						download.extraction.cancel();
+						DOWNLOADS.queue.lock().failed(download.item, error);
 					} else if let Some(info) = steam!().client().ugc().item_install_info(result.published_file_id) {
 						dprintln!("ISteamUGC Download SUCCESS: {:?}", download.item);
 						download.transaction.finished(turbonone!());
+						let download = Arc::try_unwrap(download).unwrap();
 						Downloads::extract(
 							PathBuf::from(info.folder),
 							download.item,
-							Arc::try_unwrap(download).unwrap().extract_destination,
+							download.extract_destination,
+							download.extraction,
 						);
 					} else {
 						dprintln!("ISteamUGC Download MISSING: {:?}", download.item);
-						download.transaction.error("ERR_DOWNLOAD_MISSING", turbonone!());
+						download.transaction.error(ErrorCode::DownloadMissing, turbonone!());
+						download.extraction.cancel();
+						DOWNLOADS.queue.lock().failed(download.item, ErrorCode::DownloadMissing);
 					}
 				} else {
 					dprintln!("ISteamUGC Download ???: {:?}", result.published_file_id);
@@ -327,64 +473,96 @@ impl Downloads {
 		});
 
 		loop {
-			let downloading = std::mem::take(&mut *DOWNLOADS.downloading.lock());
-			if downloading.is_empty() {
-				DOWNLOADS.watchdog.wait(&mut DOWNLOADS.downloading.lock());
-				continue;
-			}
-
 			let ugc = steam!().client().ugc();
 
+			let mut started = Vec::new();
 			{
-				let mut in_progress = in_progress.lock();
-				in_progress.reserve(downloading.len());
+				let mut scheduler = DOWNLOADS.scheduler.lock();
+				scheduler.set_concurrency(app_data!().settings.read().download_concurrency);
 
-				for download in downloading {
-					let pos = match in_progress.binary_search_by_key(&download.item, |x| x.item) {
-						Ok(_) => continue,
-						Err(pos) => pos,
-					};
+				for download in scheduler.remove(|download| download.transaction.aborted()) {
+					download.extraction.cancel();
+					DOWNLOADS.queue.lock().failed(download.item, ErrorCode::Cancelled);
+				}
 
-					if !ugc.download_item(download.item, true) {
-						download.transaction.error("ERR_DOWNLOAD_FAILED", turbonone!());
-						continue;
-					} else {
+				// Steam can't pause a download once it has started, so paused downloads are held back until they're resumed
+				while let Some(download) = scheduler.next(|download| !download.transaction.paused()) {
+					if ugc.download_item(download.item, true) {
 						dprintln!("Starting ISteamUGC Download for {:?}", download.item);
+						DOWNLOADS.queue.lock().set_state(download.item, DownloadState::Downloading);
+						started.push(download);
+					} else {
+						download.transaction.error(ErrorCode::DownloadFailed, turbonone!());
+						download.extraction.cancel();
+						DOWNLOADS.queue.lock().failed(download.item, ErrorCode::DownloadFailed);
+						scheduler.finished(download.item);
 					}
+				}
 
-					in_progress.insert(pos, download);
+				if scheduler.is_empty() {
+					DOWNLOADS.watchdog.wait(&mut scheduler);
+					continue;
 				}
 			}
 
-			loop {
-				if let Some(mut in_progress) = in_progress.try_lock() {
-					if in_progress.is_empty() {
-						break;
-					} else {
-						let mut i = 0;
-						while i < in_progress.len() {
-							let download = &in_progress[i];
-							if download.transaction.aborted() {
-								in_progress.remove(i);
-							} else if let Some((current, total)) = ugc.item_download_info(download.item) {
-								if total > 0 {
-									if !download.sent_total.fetch_or(true, std::sync::atomic::Ordering::SeqCst) {
-										download.transaction.data((1, total));
-									}
-									download.transaction.progress(current as f64 / total as f64);
-								}
+			{
+				let mut in_progress = in_progress.lock();
+
+				for download in started {
+					let pos = in_progress.binary_search_by_key(&download.item, |x| x.item).unwrap_or_else(|pos| pos);
+					in_progress.insert(pos, download);
+				}
+
+				in_progress.retain(|download| {
+					if download.transaction.aborted() {
+						download.extraction.cancel();
+						DOWNLOADS.queue.lock().failed(download.item, ErrorCode::Cancelled);
+						DOWNLOADS.scheduler.lock().finished(download.item);
+						return false;
+					}
+
+					if let Some((current, total)) = ugc.item_download_info(download.item) {
+						if total > 0 {
+							if !download.sent_total.fetch_or(true, std::sync::atomic::Ordering::SeqCst) {
+								download.transaction.data((1, total));
 							}
-							i += 1;
+							download.transaction.progress(current as f64 / total as f64);
 						}
 					}
-				}
-				steam!().run_callbacks();
+
+					true
+				});
 			}
+
+			steam!().run_callbacks();
+
+			// Woken early when downloads are queued or reordered
+			DOWNLOADS.watchdog.wait_for(&mut DOWNLOADS.scheduler.lock(), Duration::from_millis(50));
 		}
 	}
 }
 
 #[tauri::command]
-pub fn workshop_download(ids: Vec<PublishedFileId>) {
-	downloads!().download(ids);
+pub fn workshop_download(ids: Vec<PublishedFileId>) -> Transaction {
+	downloads!().download(ids)
+}
+
+#[tauri::command]
+pub fn download_queue() -> Vec<QueuedDownload> {
+	downloads!().queue()
+}
+
+#[tauri::command]
+pub fn retry_downloads() -> Transaction {
+	downloads!().retry()
+}
+
+#[tauri::command]
+pub fn move_download_to_front(item: PublishedFileId) -> bool {
+	downloads!().move_to_front(item)
+}
+
+#[tauri::command]
+pub fn set_download_priority(item: PublishedFileId, priority: i32) -> bool {
+	downloads!().set_priority(item, priority)
 }
@@ -1,31 +1,42 @@
 use std::{
-	collections::{HashMap, HashSet},
 	mem::MaybeUninit,
 	sync::{atomic::AtomicBool, Arc},
 };
 
 use steamworks::{
-	Callback, CallbackHandle, Client, ClientManager, PublishedFileId, SingleClient, SteamId, SteamServerConnectFailure, SteamServersConnected,
-	SteamServersDisconnected,
+	Callback, CallbackHandle, Client, ClientManager, SingleClient, SteamId, SteamServerConnectFailure, SteamServersConnected, SteamServersDisconnected,
 };
 
+#[cfg(feature = "gui")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "gui")]
+use steamworks::PublishedFileId;
+
 use atomic_refcell::AtomicRefCell;
 
-use self::{downloads::Downloads, users::SteamUser};
+use crate::octopus::AtomicRefSome;
 
+#[cfg(feature = "gui")]
+use self::{downloads::Downloads, users::SteamUser};
+#[cfg(feature = "gui")]
 use crate::{
-	octopus::{AtomicRefSome, PromiseCache, PromiseHashCache, RelaxedRwLock},
-	Transaction,
+	octopus::{PromiseCache, PromiseHashCache, RelaxedRwLock},
+	webview_emit, Transaction,
 };
 
-use crate::webview_emit;
-
+// Everything the webview asks Steam for
+#[cfg(feature = "gui")]
 pub mod downloads;
+#[cfg(feature = "gui")]
 pub mod publishing;
+#[cfg(feature = "gui")]
 pub mod subscriptions;
+#[cfg(feature = "gui")]
 pub mod users;
+#[cfg(feature = "gui")]
 pub mod workshop;
 
+#[cfg(feature = "gui")]
 pub use downloads::DOWNLOADS;
 
 pub const RESULTS_PER_PAGE: usize = steamworks::RESULTS_PER_PAGE as usize;
@@ -77,9 +88,12 @@ pub struct Steam {
 
 	interface: AtomicRefCell<Option<Interface>>,
 
+	#[cfg(feature = "gui")]
 	users: PromiseHashCache<SteamId, SteamUser>,
 
+	#[cfg(feature = "gui")]
 	workshop: RelaxedRwLock<(HashSet<PublishedFileId>, Vec<PublishedFileId>)>,
+	#[cfg(feature = "gui")]
 	workshop_channel: Transaction,
 }
 
@@ -92,9 +106,12 @@ impl Steam {
 		Steam {
 			connected: AtomicBool::new(false),
 			interface: AtomicRefCell::new(None),
+			#[cfg(feature = "gui")]
 			users: PromiseCache::new(HashMap::new()),
 
+			#[cfg(feature = "gui")]
 			workshop: RelaxedRwLock::new((HashSet::new(), Vec::new())),
+			#[cfg(feature = "gui")]
 			workshop_channel: transaction!(),
 		}
 	}
@@ -123,14 +140,20 @@ impl Steam {
 
 	fn on_initialized() {
 		std::thread::spawn(Steam::watchdog);
-		std::thread::spawn(Steam::workshop_fetcher);
 
-		lazy_static::initialize(&DOWNLOADS);
-		std::thread::spawn(Downloads::watchdog);
+		#[cfg(feature = "gui")]
+		{
+			std::thread::spawn(Steam::workshop_fetcher);
+
+			lazy_static::initialize(&DOWNLOADS);
+			std::thread::spawn(Downloads::watchdog);
+			DOWNLOADS.resume();
+		}
 
 		steam!().client().friends().set_rich_presence("steam_display", Some("#Status_Generic"));
 		steam!().client().friends().set_rich_presence("generic", Some("In gmpublisher"));
 
+		#[cfg(feature = "gui")]
 		if app_data!().settings.read().gmod.is_none() {
 			app_data!().send();
 		}
@@ -165,6 +188,7 @@ impl Steam {
 
 	fn set_connected(&self, connected: bool) {
 		self.connected.store(connected, std::sync::atomic::Ordering::Release);
+		#[cfg(feature = "gui")]
 		webview_emit!(if connected { "SteamConnected" } else { "SteamDisconnected" });
 	}
 
@@ -274,11 +298,13 @@ impl Steam {
 	}
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn is_steam_connected() -> bool {
 	steam!().connected()
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn get_current_user() -> (String, Option<crate::Base64Image>) {
 	steam!().client_wait();
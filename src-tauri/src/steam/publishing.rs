@@ -1,10 +1,16 @@
 use crate::{
-	gma::{GMAEntry, GMAFile, GMAFilePointers, GMAMetadata},
-	Transaction, GMOD_APP_ID,
+	gma::{
+		addon_json::{self, AddonJsonIssue},
+		explain::{self, Explainer, Explanation},
+		plan::{Fate, PackPlan},
+		report::WhitelistReport,
+		CaseCollision, GMAEntry, GMAError, GMAFile, GMAMetadata,
+	},
+	ErrorCode, Transaction, GMOD_APP_ID,
 };
 use image::{DynamicImage, GenericImageView, ImageError, ImageFormat};
 use parking_lot::Mutex;
-use path_slash::{PathBufExt, PathExt};
+use path_slash::PathBufExt;
 use std::{
 	fs::File,
 	io::BufReader,
@@ -13,39 +19,26 @@ use std::{
 	sync::Arc,
 };
 use steamworks::{PublishedFileId, SteamError};
-use walkdir::WalkDir;
-
-#[cfg(not(target_os = "windows"))]
-use std::collections::HashSet;
 
 pub enum PublishError {
-	NotWhitelisted(Vec<String>),
+	NotWhitelisted(WhitelistReport),
+	InvalidAddonJson(Vec<AddonJsonIssue>),
 	NoEntries,
-	DuplicateEntry(String),
+	DuplicateEntry(Vec<CaseCollision>),
+	Symlink(String),
 	InvalidContentPath,
 	MultipleGMAs,
 	IconTooLarge,
 	IconTooSmall,
 	IconInvalidFormat,
 	IOError,
+	Cancelled,
 	SteamError(SteamError),
 	ImageError(ImageError),
 }
 impl std::fmt::Display for PublishError {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		match self {
-			PublishError::NotWhitelisted(whitelisted) => write!(f, "ERR_WHITELIST:{}", whitelisted.join("\n")),
-			PublishError::NoEntries => write!(f, "ERR_NO_ENTRIES"),
-			PublishError::DuplicateEntry(path) => write!(f, "ERR_DUPLICATE_ENTRIES:{}", path),
-			PublishError::InvalidContentPath => write!(f, "ERR_INVALID_CONTENT_PATH"),
-			PublishError::MultipleGMAs => write!(f, "ERR_MULTIPLE_GMAS"),
-			PublishError::IconTooLarge => write!(f, "ERR_ICON_TOO_LARGE"),
-			PublishError::IconTooSmall => write!(f, "ERR_ICON_TOO_SMALL"),
-			PublishError::IconInvalidFormat => write!(f, "ERR_ICON_INVALID_FORMAT"),
-			PublishError::IOError => write!(f, "ERR_IO_ERROR"),
-			PublishError::SteamError(error) => write!(f, "ERR_STEAM_ERROR:{}", error),
-			PublishError::ImageError(error) => write!(f, "ERR_IMAGE_ERROR:{}", error),
-		}
+		std::fmt::Display::fmt(&ErrorCode::from(self), f)
 	}
 }
 impl serde::Serialize for PublishError {
@@ -232,6 +225,10 @@ impl Steam {
 	pub fn update(&self, id: PublishedFileId, details: WorkshopUpdateType, transaction: &Transaction) -> Result<bool, PublishError> {
 		use WorkshopUpdateType::*;
 
+		if transaction.aborted() {
+			return Err(PublishError::Cancelled);
+		}
+
 		let result = Arc::new(Mutex::new(None));
 		let result_ref = result.clone();
 		let update_handle = match details {
@@ -288,6 +285,14 @@ impl Steam {
 
 		let mut last_processed;
 		let result = loop {
+			if !result.is_locked() && result.lock().is_some() {
+				break Arc::try_unwrap(result).unwrap().into_inner().unwrap();
+			} else if transaction.aborted() {
+				// Steamworks can't cancel a submitted update, so the best we can do is stop waiting for it
+				// The caller deletes the content it was uploading, which makes Steam fail the update if it hasn't finished reading it yet
+				return Err(PublishError::Cancelled);
+			}
+
 			let (processed, progress, total) = update_handle.progress();
 			last_processed = processed;
 			if !matches!(processed, steamworks::UpdateStatus::Invalid) {
@@ -307,11 +312,7 @@ impl Steam {
 				transaction.progress(progress as f64 / total as f64);
 			}
 
-			if !result.is_locked() && result.lock().is_some() {
-				break Arc::try_unwrap(result).unwrap().into_inner().unwrap();
-			} else {
-				self.run_callbacks();
-			}
+			self.run_callbacks();
 		};
 
 		match result {
@@ -348,6 +349,11 @@ impl Steam {
 			Err(error) => return (None, Err(PublishError::SteamError(error))),
 		};
 
+		// The item has been created by now, so return its ID for the caller to delete it
+		if transaction.aborted() {
+			return (Some(id), Err(PublishError::Cancelled));
+		}
+
 		(Some(id), self.update(id, details, transaction))
 	}
 
@@ -402,93 +408,108 @@ impl Steam {
 }
 
 #[tauri::command]
-pub fn verify_whitelist(path: PathBuf) -> Result<(Vec<GMAEntry>, u64), PublishError> {
-	if !path.is_dir() || !path.is_absolute() {
-		return Err(PublishError::InvalidContentPath);
-	}
+pub fn verify_whitelist(path: PathBuf) -> Result<(Vec<GMAEntry>, u64, Option<ErrorCode>), PublishError> {
+	let warnings = verify_addon_json(&path)?;
 
-	let content_root = path.clone();
+	let plan = plan_content(&path)?;
 
-	let ignore = app_data!().settings.read().ignore_globs.clone();
+	// TODO some tasks shouldnt be cancelable (i.e. showing the cross button)
 
-	let mut size = 0;
-	let mut failed_extra = false;
-	let mut failed = Vec::with_capacity(10);
-	let mut files = Vec::new();
+	// Paths are lowercased when packing, so files that only differ by case would overwrite each other
+	if !plan.collisions.is_empty() {
+		return Err(PublishError::DuplicateEntry(plan.collisions));
+	}
 
-	#[cfg(not(target_os = "windows"))]
-	let mut dedup: HashSet<String> = HashSet::new();
+	if plan.files.iter().any(|file| file.fate == Fate::NotWhitelisted) {
+		return Err(PublishError::NotWhitelisted(WhitelistReport::new(&plan)));
+	}
 
-	for (path, relative_path) in WalkDir::new(&path)
-		.follow_links(true)
-		.contents_first(true)
-		.into_iter()
-		.filter_map(|entry| {
-			let path = match entry {
-				Ok(entry) => entry.into_path(),
-				Err(err) => match err.path() {
-					Some(path) => path.to_path_buf(),
-					None => return None,
-				},
-			};
+	let files: Vec<GMAEntry> = plan
+		.included()
+		.map(|file| GMAEntry {
+			path: file.archive_path.clone(),
+			size: file.size,
+			crc: 0,
+			index: 0,
+		})
+		.collect();
 
-			if path.is_dir() {
-				return None;
-			}
+	if files.is_empty() {
+		Err(PublishError::NoEntries)
+	} else {
+		let size = files.iter().map(|entry| entry.size).sum();
+		let warnings = (!warnings.is_empty()).then(|| ErrorCode::AddonJsonWarning(warnings.iter().map(ToString::to_string).collect()));
+		Ok((files, size, warnings))
+	}
+}
 
-			let relative_path = match path.strip_prefix(&content_root) {
-				Ok(rel_path) => rel_path.to_slash_lossy().to_string(),
-				Err(_) => return None,
-			};
+/// Validates the addon.json in the addon folder at `path`, if there is one, returning the issues gmad ignores (like unknown keys) as warnings
+fn verify_addon_json(path: &Path) -> Result<Vec<AddonJsonIssue>, PublishError> {
+	let json = match std::fs::read_to_string(path.join("addon.json")) {
+		Ok(json) => json,
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(err) => return Err(err.into()),
+	};
 
-			Some((path, relative_path))
-		})
-		.filter(|(_, relative_path)| crate::gma::whitelist::filter_default_ignored(relative_path))
-		.filter(|(_, relative_path)| !crate::gma::whitelist::is_ignored(relative_path, &ignore))
-	{
-		#[cfg(not(target_os = "windows"))]
-		{
-			if !dedup.insert(relative_path.to_owned()) {
-				return Err(PublishError::DuplicateEntry(relative_path));
-			}
-		}
+	verify_addon_json_issues(addon_json::validate(&json))
+}
 
-		if !crate::gma::whitelist::check(&relative_path) {
-			if failed.len() == 9 {
-				failed_extra = true;
-				break;
-			} else {
-				failed.push(relative_path);
-			}
-		} else if failed.is_empty() {
-			let entry_size = path.metadata().map(|metadata| metadata.len()).unwrap_or(0);
-			size += entry_size;
-			files.push(GMAEntry {
-				path: relative_path,
-				size: entry_size,
-				crc: 0,
-				index: 0,
-			});
-		}
+fn verify_addon_json_issues(issues: Vec<AddonJsonIssue>) -> Result<Vec<AddonJsonIssue>, PublishError> {
+	if issues.iter().any(AddonJsonIssue::is_error) {
+		Err(PublishError::InvalidAddonJson(issues))
+	} else {
+		Ok(issues)
 	}
+}
 
-	// TODO some tasks shouldnt be cancelable (i.e. showing the cross button)
+/// Groups the files in the addon folder at `path` that don't pass the whitelist, with suggested fixes
+#[tauri::command]
+pub fn whitelist_report(path: PathBuf) -> Result<WhitelistReport, PublishError> {
+	Ok(WhitelistReport::new(&plan_content(&path)?))
+}
 
-	if failed.is_empty() {
-		if files.is_empty() {
-			Err(PublishError::NoEntries)
-		} else {
-			Ok((files, size))
-		}
-	} else {
-		failed.sort_unstable();
+/// Plans packing the addon folder at `path` exactly like publishing it will, with the user's settings and the addon.json `ignore` list
+fn plan_content(path: &Path) -> Result<PackPlan, PublishError> {
+	if !path.is_dir() || !path.is_absolute() {
+		return Err(PublishError::InvalidContentPath);
+	}
 
-		if failed_extra {
-			failed.push("...".to_string());
-		}
+	let options = app_data!().settings.read().pack_options();
+
+	// Only the addon.json `ignore` list matters for planning, the rest of the metadata comes from the publish form
+	let metadata = GMAMetadata::Standard {
+		title: String::new(),
+		addon_type: String::new(),
+		tags: Vec::new(),
+		ignore: explain::addon_json_ignore(path),
+	};
+
+	GMAFile::new(path.with_extension("gma"), metadata)
+		.plan(path, &options)
+		.map_err(|error| match error {
+			GMAError::Symlink(path) => PublishError::Symlink(path),
+			_ => PublishError::IOError,
+		})
+}
 
-		Err(PublishError::NotWhitelisted(failed))
+/// Explains why each of `relative_paths` in the addon folder at `path` will or won't be packed
+#[tauri::command]
+pub fn explain_paths(path: PathBuf, relative_paths: Vec<String>) -> Result<Vec<Explanation>, PublishError> {
+	if !path.is_dir() || !path.is_absolute() {
+		return Err(PublishError::InvalidContentPath);
 	}
+
+	let explainer = {
+		let settings = app_data!().settings.read();
+		Explainer::new(
+			&path,
+			settings.ignore_globs.clone(),
+			explain::addon_json_ignore(&path),
+			&settings.pack_options(),
+		)
+	};
+
+	Ok(relative_paths.iter().map(|relative_path| explainer.explain(relative_path)).collect())
 }
 
 #[tauri::command]
@@ -500,7 +521,7 @@ pub fn publish_icon(icon_path: PathBuf, upscale: bool, addon_id: PublishedFileId
 		let preview = match WorkshopIcon::new(icon_path, upscale) {
 			Ok(icon) => icon,
 			Err(error) => {
-				transaction.error(error.to_string(), turbonone!());
+				transaction.error(error, turbonone!());
 				return;
 			}
 		};
@@ -515,7 +536,7 @@ pub fn publish_icon(icon_path: PathBuf, upscale: bool, addon_id: PublishedFileId
 				transaction.finished(turbonone!());
 			}
 			Err(error) => {
-				transaction.error(error.to_string(), turbonone!());
+				transaction.error(error, turbonone!());
 			}
 		};
 	});
@@ -540,6 +561,14 @@ pub fn publish(
 	let is_updating = update_id.is_some();
 
 	rayon::spawn(move || {
+		// Warnings were already shown when the addon folder was verified
+		if let Err(error) =
+			verify_addon_json_issues(addon_json::validate_metadata(&addon_type, &tags)).and_then(|_| verify_addon_json(&content_path_src))
+		{
+			transaction.error(error, turbonone!());
+			return;
+		}
+
 		let preview = match icon_path {
 			Some(icon_path) => {
 				transaction.status("PUBLISH_PROCESSING_ICON");
@@ -547,7 +576,7 @@ pub fn publish(
 				match WorkshopIcon::new(icon_path, upscale) {
 					Ok(icon) => Some(icon),
 					Err(error) => {
-						transaction.error(error.to_string(), turbonone!());
+						transaction.error(error, turbonone!());
 						return;
 					}
 				}
@@ -561,6 +590,10 @@ pub fn publish(
 			}
 		};
 
+		// Uploading usually takes longer than packing
+		let packing = transaction.child(1.);
+		let uploading = transaction.child(2.);
+
 		transaction.status("PUBLISH_PACKING");
 
 		let mut path = app_data!().temp_dir().to_owned();
@@ -568,47 +601,47 @@ pub fn publish(
 		path.push("gmpublisher_publishing");
 
 		if std::fs::create_dir_all(&path).is_err() {
-			transaction.error("ERR_IO_ERROR", turbonone!());
+			transaction.error(ErrorCode::IOError, turbonone!());
 			return;
 		}
 
 		path.push("gmpublisher.gma");
 
 		{
-			let gma = GMAFile {
-				// TODO convert to GMAFile::new()
-				path: path.clone(),
-				size: 0,
-				id: None,
-				metadata: Some(GMAMetadata::Standard {
+			let gma = GMAFile::new(
+				&path,
+				GMAMetadata::Standard {
 					title: title.clone(),
 					addon_type: addon_type.clone(),
 					tags: tags.clone(),
-					ignore: app_data!().settings.read().ignore_globs.clone(),
-				}),
-				entries: None,
-				pointers: GMAFilePointers::default(),
-				version: 3,
-				extracted_name: String::new(),
-				modified: None,
-				membuffer: None,
-			};
-
-			if let Err(error) = gma.create(&content_path_src, transaction.clone()) {
-				if !transaction.aborted() {
-					transaction.error(error.to_string(), turbonone!());
+					ignore: explain::addon_json_ignore(&content_path_src),
+				},
+			);
+			let options = app_data!().settings.read().pack_options();
+
+			match gma.create(&content_path_src, &options, &*packing) {
+				Ok(_) => packing.finished(turbonone!()),
+				Err(error) => {
+					if !transaction.aborted() {
+						transaction.error(error, turbonone!());
+					}
+					return;
 				}
-				return;
 			}
 		}
 
+		if transaction.aborted() {
+			ignore! { std::fs::remove_file(path) };
+			return;
+		}
+
 		let mut content_path = path.clone();
 		content_path.pop();
 
 		let content_path = match ContentPath::new(content_path) {
 			Ok(content_path) => content_path,
 			Err(error) => {
-				transaction.error(error.to_string(), turbonone!());
+				transaction.error(error, turbonone!());
 				return;
 			}
 		};
@@ -627,7 +660,7 @@ pub fn publish(
 						preview,
 						changes,
 					},
-					&transaction,
+					&uploading,
 				),
 			)
 		} else {
@@ -639,7 +672,7 @@ pub fn publish(
 					addon_type,
 					preview: preview.unwrap(),
 				},
-				&transaction,
+				&uploading,
 			)
 		};
 
@@ -655,6 +688,7 @@ pub fn publish(
 
 				crate::path::open(format!("https://steamcommunity.com/sharedfiles/filedetails/?id={}", id.0));
 
+				uploading.finished(turbonone!());
 				transaction.finished(turbonone!());
 
 				app_data!().settings.write().my_workshop_local_paths.insert(id, content_path_src);
@@ -662,7 +696,9 @@ pub fn publish(
 				app_data!().send();
 			}
 			Err(error) => {
-				transaction.error(error.to_string(), turbonone!());
+				if !transaction.aborted() {
+					transaction.error(error, turbonone!());
+				}
 				if !is_updating {
 					if let Some(id) = id {
 						steam!().client().ugc().delete_item(id, |_| {});
@@ -699,7 +735,7 @@ pub fn verify_icon(path: PathBuf) -> Result<(String, bool), Transaction> {
 		})
 		.map_err(|error| {
 			let transaction = transaction!();
-			transaction.error(error.to_string(), turbonone!());
+			transaction.error(error, turbonone!());
 			transaction
 		})
 }
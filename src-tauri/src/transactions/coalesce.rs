@@ -0,0 +1,110 @@
+use std::time::{Duration, Instant};
+
+/// Progress updates that change the progress by less than this, out of 10000, are dropped
+pub const MIN_PROGRESS_DELTA: u16 = 10;
+
+/// Progress updates sooner than this after the last one are dropped
+#[cfg(not(test))]
+pub const MIN_PROGRESS_INTERVAL: Duration = Duration::from_millis(50);
+#[cfg(test)]
+pub const MIN_PROGRESS_INTERVAL: Duration = Duration::ZERO;
+
+/// Decides which of a transaction's progress updates are worth sending
+///
+/// Extracting reports progress once per entry from every worker thread, which would flood the webview. Starting (0) and completing (10000)
+/// are always sent, anything else has to be far enough from the last progress sent, and long enough after it.
+#[derive(Debug)]
+pub struct ProgressCoalescer {
+	min_delta: u16,
+	min_interval: Duration,
+	last: u16,
+	last_at: Option<Instant>,
+}
+impl Default for ProgressCoalescer {
+	fn default() -> Self {
+		ProgressCoalescer::new(MIN_PROGRESS_DELTA, MIN_PROGRESS_INTERVAL)
+	}
+}
+impl ProgressCoalescer {
+	pub fn new(min_delta: u16, min_interval: Duration) -> ProgressCoalescer {
+		ProgressCoalescer {
+			min_delta,
+			min_interval,
+			last: 0,
+			last_at: None,
+		}
+	}
+
+	/// Whether to send `progress`, which is then remembered as the last progress sent
+	pub fn coalesce(&mut self, progress: u16, now: Instant) -> bool {
+		if progress == self.last {
+			return false;
+		}
+
+		if progress != 0 && progress != 10000 {
+			if self.last.abs_diff(progress) < self.min_delta {
+				return false;
+			}
+			if let Some(last_at) = self.last_at {
+				if now.duration_since(last_at) < self.min_interval {
+					return false;
+				}
+			}
+		}
+
+		self.sent(progress, now);
+		true
+	}
+
+	/// Whether `progress` was dropped and should be sent now, regardless of how recently the last progress was sent
+	pub fn flush(&mut self, progress: u16, now: Instant) -> bool {
+		if progress == self.last {
+			false
+		} else {
+			self.sent(progress, now);
+			true
+		}
+	}
+
+	/// The progress has been reset to 0 by other means
+	pub fn reset(&mut self, now: Instant) {
+		self.sent(0, now);
+	}
+
+	fn sent(&mut self, progress: u16, now: Instant) {
+		self.last = progress;
+		self.last_at = Some(now);
+	}
+}
+
+#[test]
+fn test_coalesce() {
+	let start = Instant::now();
+	let at = |ms: u64| start + Duration::from_millis(ms);
+
+	let mut coalescer = ProgressCoalescer::new(10, Duration::from_millis(50));
+	assert!(!coalescer.coalesce(0, at(0)));
+	assert!(coalescer.coalesce(100, at(0)));
+
+	// Too soon
+	assert!(!coalescer.coalesce(200, at(10)));
+	assert!(coalescer.coalesce(300, at(60)));
+
+	// Too small
+	assert!(!coalescer.coalesce(305, at(200)));
+	assert!(coalescer.coalesce(310, at(200)));
+
+	// Going backwards counts too
+	assert!(coalescer.coalesce(100, at(300)));
+
+	// Completing is never dropped
+	assert!(coalescer.coalesce(10000, at(301)));
+	assert!(!coalescer.coalesce(10000, at(400)));
+
+	coalescer.reset(at(500));
+	assert!(!coalescer.coalesce(0, at(600)));
+	assert!(coalescer.coalesce(5000, at(600)));
+	assert!(!coalescer.coalesce(6000, at(601)));
+	assert!(coalescer.flush(6000, at(602)));
+	assert!(!coalescer.flush(6000, at(603)));
+}
@@ -0,0 +1,102 @@
+use std::{
+	collections::VecDeque,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+/// How many finished transactions are remembered
+pub const HISTORY_LENGTH: usize = 200;
+
+/// How a transaction ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", content = "error", rename_all = "snake_case")]
+pub enum TransactionOutcome {
+	Finished,
+	/// The [`ErrorCode`](crate::ErrorCode) it failed with, as it was sent to the webview
+	Error(String),
+	Cancelled,
+}
+
+/// A transaction that has finished, failed or been cancelled
+#[derive(Debug, Clone, Serialize)]
+pub struct FinishedTransaction {
+	pub id: u32,
+	pub parent: Option<u32>,
+	/// The last status it reported
+	pub status: Option<String>,
+	#[serde(flatten)]
+	pub outcome: TransactionOutcome,
+	/// How long it ran for, in milliseconds
+	pub duration: u64,
+	/// When it ended, in milliseconds since the Unix epoch
+	pub ended: u64,
+}
+impl FinishedTransaction {
+	pub fn new(id: u32, parent: Option<u32>, status: Option<String>, outcome: TransactionOutcome, duration: Duration) -> FinishedTransaction {
+		FinishedTransaction {
+			id,
+			parent,
+			status,
+			outcome,
+			duration: duration.as_millis() as u64,
+			ended: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map(|ended| ended.as_millis() as u64)
+				.unwrap_or(0),
+		}
+	}
+}
+
+/// The most recently finished transactions, oldest first
+///
+/// This lives as long as the backend does, so the webview can rebuild its activity log after it's reloaded.
+#[derive(Debug)]
+pub struct TransactionHistory {
+	len: usize,
+	finished: VecDeque<FinishedTransaction>,
+}
+impl Default for TransactionHistory {
+	fn default() -> Self {
+		TransactionHistory::new(HISTORY_LENGTH)
+	}
+}
+impl TransactionHistory {
+	pub fn new(len: usize) -> TransactionHistory {
+		TransactionHistory {
+			len,
+			finished: VecDeque::with_capacity(len),
+		}
+	}
+
+	/// Remembers `transaction`, forgetting the oldest one if the history is full
+	pub fn push(&mut self, transaction: FinishedTransaction) {
+		if self.finished.len() >= self.len {
+			self.finished.pop_front();
+		}
+		self.finished.push_back(transaction);
+	}
+
+	pub fn to_vec(&self) -> Vec<FinishedTransaction> {
+		self.finished.iter().cloned().collect()
+	}
+}
+
+#[test]
+fn test_history() {
+	let finished = |id: u32, outcome: TransactionOutcome| FinishedTransaction::new(id, None, None, outcome, Duration::from_millis(1500));
+
+	let mut history = TransactionHistory::new(2);
+	history.push(finished(0, TransactionOutcome::Finished));
+	history.push(finished(1, TransactionOutcome::Cancelled));
+	history.push(finished(2, TransactionOutcome::Error("ERR_IO_ERROR".to_string())));
+
+	let finished = history.to_vec();
+	assert_eq!(finished.iter().map(|transaction| transaction.id).collect::<Vec<_>>(), [1, 2]);
+
+	let json = serde_json::to_value(&finished[1]).unwrap();
+	assert_eq!(json["outcome"], "error");
+	assert_eq!(json["error"], "ERR_IO_ERROR");
+	assert_eq!(json["duration"], 1500);
+	assert_eq!(serde_json::to_value(&finished[0]).unwrap()["outcome"], "cancelled");
+}
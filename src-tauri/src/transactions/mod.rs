@@ -1,17 +1,30 @@
+mod coalesce;
+mod history;
+mod protocol;
+mod sinks;
 mod websocket;
 
 use lazy_static::lazy_static;
-use parking_lot::RwLock;
+use parking_lot::{Condvar, Mutex, RwLock};
 use rayon::ThreadPool;
 use serde::Serialize;
-use std::sync::{
-	atomic::{AtomicBool, AtomicU32, Ordering},
-	Arc, Weak,
+use std::{
+	sync::{
+		atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering},
+		Arc, Weak,
+	},
+	time::Instant,
 };
 
-use crate::dprintln;
+use crate::{dprintln, ErrorCode};
 
-use self::websocket::{TransactionMessage, TransactionServer};
+use self::coalesce::ProgressCoalescer;
+
+pub use self::history::{FinishedTransaction, TransactionOutcome};
+pub use self::protocol::TransactionMessage;
+pub use self::sinks::*;
+#[cfg(feature = "gui")]
+use self::websocket::TransactionServer;
 
 lazy_static! {
 	static ref TRANSACTIONS: Transactions = Transactions::init();
@@ -21,7 +34,10 @@ lazy_static! {
 pub struct Transactions {
 	inner: RwLock<Vec<TransactionRef>>,
 	id: AtomicU32,
-	websocket: Option<TransactionServer>,
+	sinks: RwLock<Vec<Arc<dyn TransactionSink>>>,
+	history: Mutex<history::TransactionHistory>,
+	#[cfg(feature = "gui")]
+	websocket: Option<Arc<TransactionServer>>,
 }
 impl std::ops::Deref for Transactions {
 	type Target = RwLock<Vec<TransactionRef>>;
@@ -31,10 +47,40 @@ impl std::ops::Deref for Transactions {
 }
 impl Transactions {
 	fn init() -> Transactions {
+		let mut sinks: Vec<Arc<dyn TransactionSink>> = Vec::new();
+
+		#[cfg(feature = "gui")]
+		let websocket = if *crate::cli::CLI_MODE {
+			None
+		} else {
+			let websocket = TransactionServer::init().ok().map(Arc::new);
+			match &websocket {
+				Some(websocket) => sinks.push(websocket.clone()),
+				None => sinks.push(Arc::new(TauriEventSink)),
+			}
+			websocket
+		};
+
+		if std::env::var_os(VERBOSE_ENV).is_some() {
+			sinks.push(Arc::new(TerminalSink::default()));
+		}
+
+		if let Some(path) = std::env::var_os(TRANSACTION_LOG_ENV) {
+			match JsonLinesSink::create(&path) {
+				Ok(sink) => sinks.push(Arc::new(sink)),
+				Err(err) => {
+					eprintln!("Failed to open the transaction log {:?}: {}", path, err);
+				}
+			}
+		}
+
 		Transactions {
 			inner: RwLock::new(Vec::new()),
 			id: AtomicU32::new(0),
-			websocket: if *crate::cli::CLI_MODE { None } else { TransactionServer::init().ok() },
+			sinks: RwLock::new(sinks),
+			history: Mutex::new(history::TransactionHistory::default()),
+			#[cfg(feature = "gui")]
+			websocket,
 		}
 	}
 
@@ -52,6 +98,56 @@ impl Transactions {
 
 		None
 	}
+
+	/// The transactions in progress, sorted by ID
+	pub fn active(&self) -> Vec<ActiveTransaction> {
+		self.inner
+			.read()
+			.iter()
+			.filter_map(|transaction| transaction.upgrade())
+			.filter(|transaction| !transaction.aborted())
+			.map(|transaction| ActiveTransaction {
+				id: transaction.id,
+				parent: transaction.parent.as_ref().map(|parent| parent.id),
+				status: transaction.status.lock().clone(),
+				progress: transaction.progress.load(Ordering::Acquire),
+				paused: transaction.paused(),
+				duration: transaction.started.elapsed().as_millis() as u64,
+			})
+			.collect()
+	}
+
+	/// [`Transactions::active`] as it's sent over the websocket
+	pub fn list(&self) -> Vec<protocol::TransactionSummary> {
+		self.active()
+			.into_iter()
+			.map(|transaction| protocol::TransactionSummary {
+				id: transaction.id,
+				parent: transaction.parent,
+				progress: transaction.progress,
+				paused: transaction.paused,
+			})
+			.collect()
+	}
+
+	/// The most recently finished transactions, oldest first
+	pub fn history(&self) -> Vec<FinishedTransaction> {
+		self.history.lock().to_vec()
+	}
+}
+
+/// A transaction in progress, as the webview needs it to rebuild its tasks after it's reloaded
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveTransaction {
+	pub id: u32,
+	pub parent: Option<u32>,
+	/// The last status it reported
+	pub status: Option<String>,
+	/// From 0 to 10000
+	pub progress: u16,
+	pub paused: bool,
+	/// How long it has been running for, in milliseconds
+	pub duration: u64,
 }
 
 pub struct TransactionRef {
@@ -86,27 +182,169 @@ fn progress_as_int(progress: f64) -> u16 {
 	u16::min((progress * 10000.) as u16, 10000)
 }
 
+#[derive(Debug)]
+struct TransactionChild {
+	transaction: Weak<TransactionInner>,
+	weight: f64,
+}
+
 pub type Transaction = Arc<TransactionInner>;
 #[derive(Debug)]
 pub struct TransactionInner {
 	pub id: u32,
 	aborted: AtomicBool,
+	/// The last progress reported, from 0 to 10000
+	progress: AtomicU16,
+	/// Held while emitting, so that events arrive in the order they were emitted in
+	coalescer: Mutex<ProgressCoalescer>,
+	paused: Mutex<bool>,
+	resumed: Condvar,
+	/// The last status reported, for [`Transactions::active`] and the history
+	status: Mutex<Option<String>>,
+	started: Instant,
+	parent: Option<Transaction>,
+	children: Mutex<Vec<TransactionChild>>,
+	finish_with_children: AtomicBool,
 }
 impl TransactionInner {
-	fn emit(&self, message: TransactionMessage) {
-		if *crate::cli::CLI_MODE {
+	/// Starts a transaction whose progress counts towards this one's
+	///
+	/// Once a transaction has children, its progress is the average of theirs, weighted by `weight`. Children that have finished, failed or
+	/// been cancelled count as complete. Their statuses are also this transaction's, and cancelling or pausing this transaction does the same
+	/// to them.
+	pub fn child(self: &Arc<Self>, weight: f64) -> Transaction {
+		let child = new_transaction(Some(self.clone()));
+
+		self.children.lock().push(TransactionChild {
+			transaction: Arc::downgrade(&child),
+			weight,
+		});
+
+		self.emit(TransactionMessage::Child(self.id, child.id, weight));
+
+		if self.aborted() {
+			child.cancel();
+		} else if self.paused() {
+			child.pause();
+		}
+
+		child
+	}
+
+	fn children(&self) -> Vec<Transaction> {
+		self.children.lock().iter().filter_map(|child| child.transaction.upgrade()).collect()
+	}
+
+	/// Finishes this transaction once all of its children have, or right away if they already have
+	pub fn finish_with_children(&self) {
+		self.finish_with_children.store(true, Ordering::Release);
+		self.child_progressed();
+	}
+
+	fn child_progressed(&self) {
+		if self.aborted() {
 			return;
 		}
 
-		if let Some(ref websocket) = TRANSACTIONS.websocket {
-			websocket.send(message);
+		let (progress, complete) = {
+			// Dropping the last reference to a child reports its progress to us again, so that mustn't happen while the lock is held
+			let children: Vec<(f64, Option<Transaction>)> = self
+				.children
+				.lock()
+				.iter()
+				.map(|child| (child.weight, child.transaction.upgrade()))
+				.collect();
+
+			let total_weight: f64 = children.iter().map(|(weight, _)| weight).sum();
+			let mut progress = 0.;
+			let mut complete = true;
+			for (weight, child) in children.iter() {
+				// Children that have finished, failed, been cancelled or dropped count as complete
+				progress += weight
+					* match child {
+						Some(child) if !child.aborted() => {
+							complete = false;
+							child.progress.load(Ordering::Acquire) as f64 / 10000.
+						}
+						_ => 1.,
+					};
+			}
+
+			(if total_weight > 0. { progress / total_weight } else { 1. }, complete)
+		};
+
+		if complete && self.finish_with_children.swap(false, Ordering::AcqRel) {
+			self.finished(turbonone!());
 		} else {
-			TransactionServer::send_tauri_event(message);
+			self.progress(progress);
 		}
 	}
 
-	fn abort(&self) {
-		self.aborted.store(true, Ordering::Release);
+	fn update_parent(&self) {
+		if let Some(parent) = &self.parent {
+			parent.child_progressed();
+		}
+	}
+
+	fn emit(&self, message: TransactionMessage) {
+		let mut coalescer = self.coalescer.lock();
+		let now = Instant::now();
+
+		match &message {
+			TransactionMessage::Progress(_, progress) => {
+				// Another thread might have finished or failed the transaction since it reported this
+				if self.aborted() || !coalescer.coalesce(*progress, now) {
+					return;
+				}
+			}
+
+			TransactionMessage::ResetProgress(_) => coalescer.reset(now),
+
+			// These might describe the progress, so send any progress that was dropped first
+			TransactionMessage::Status(..) | TransactionMessage::Data(..) => {
+				let progress = self.progress.load(Ordering::Acquire);
+				if !self.aborted() && coalescer.flush(progress, now) {
+					self.send(&TransactionMessage::Progress(self.id, progress));
+				}
+			}
+
+			_ => {}
+		}
+
+		self.send(&message);
+	}
+
+	fn send(&self, message: &TransactionMessage) {
+		for sink in TRANSACTIONS.sinks.read().iter() {
+			sink.emit(message);
+		}
+	}
+
+	/// Stops this transaction and its children, and records how it ended in the history the first time it's called
+	///
+	/// Returns whether this call was the one that aborted it.
+	fn abort(&self, outcome: TransactionOutcome) -> bool {
+		if self.aborted.swap(true, Ordering::AcqRel) {
+			return false;
+		}
+
+		TRANSACTIONS.history.lock().push(FinishedTransaction::new(
+			self.id,
+			self.parent.as_ref().map(|parent| parent.id),
+			self.status.lock().clone(),
+			outcome,
+			self.started.elapsed(),
+		));
+
+		// Wake up anything waiting at a pause point, so it sees it has been cancelled
+		drop(self.paused.lock());
+		self.resumed.notify_all();
+
+		for child in self.children() {
+			if !child.aborted() {
+				child.cancel();
+			}
+		}
 
 		let id = self.id;
 		TRANSACTIONS_SLAVE.spawn(move || {
@@ -115,6 +353,8 @@ impl TransactionInner {
 				transactions.remove(pos);
 			}
 		});
+
+		true
 	}
 
 	pub fn data<D: Serialize + Send + 'static>(&self, data: D) {
@@ -122,22 +362,39 @@ impl TransactionInner {
 	}
 
 	pub fn status<S: Into<String>>(&self, status: S) {
-		self.emit(TransactionMessage::Status(self.id, status.into()))
+		let status = status.into();
+		if let Some(parent) = &self.parent {
+			parent.status(status.clone());
+		}
+		*self.status.lock() = Some(status.clone());
+		self.emit(TransactionMessage::Status(self.id, status))
 	}
 
 	pub fn progress(&self, progress: f64) {
 		if self.aborted() {
 			dprintln!("Tried to progress an aborted transaction!");
 		} else {
-			self.emit(TransactionMessage::Progress(self.id, progress_as_int(progress)));
+			let progress = progress_as_int(progress);
+			self.progress.store(progress, Ordering::Release);
+			self.emit(TransactionMessage::Progress(self.id, progress));
+			self.update_parent();
 		}
 	}
 
+	/// Adds to the progress, which is emitted as a [`TransactionMessage::Progress`] so that increments can be dropped like any other progress
 	pub fn progress_incr(&self, progress: f64) {
 		if self.aborted() {
 			dprintln!("Tried to progress an aborted transaction!");
 		} else {
-			self.emit(TransactionMessage::IncrProgress(self.id, progress_as_int(progress)));
+			let incr = progress_as_int(progress);
+			let incremented = |progress: u16| u16::min(progress.saturating_add(incr), 10000);
+			let progress = incremented(
+				self.progress
+					.fetch_update(Ordering::AcqRel, Ordering::Acquire, |progress| Some(incremented(progress)))
+					.unwrap(),
+			);
+			self.emit(TransactionMessage::Progress(self.id, progress));
+			self.update_parent();
 		}
 	}
 
@@ -145,34 +402,114 @@ impl TransactionInner {
 		if self.aborted() {
 			dprintln!("Tried to reset the progress of an aborted transaction!");
 		} else {
+			self.progress.store(0, Ordering::Release);
 			self.emit(TransactionMessage::ResetProgress(self.id));
+			self.update_parent();
 		}
 	}
 
-	pub fn error<S: Into<String>, D: Serialize + Send + 'static>(&self, msg: S, data: D) {
-		self.abort();
-		self.emit(TransactionMessage::Error(self.id, msg.into(), json!(data)));
+	pub fn error<E: Into<ErrorCode>, D: Serialize + Send + 'static>(&self, error: E, data: D) {
+		let error = error.into().to_string();
+		if !self.abort(TransactionOutcome::Error(error.clone())) {
+			dprintln!("Tried to error an aborted transaction!");
+			return;
+		}
+		self.emit(TransactionMessage::Error(self.id, error, json!(data)));
+		self.update_parent();
 	}
 
 	pub fn finished<D: Serialize + Send + 'static>(&self, data: D) {
-		debug_assert!(!self.aborted(), "Tried to finish an aborted transaction!");
-		self.abort();
+		if !self.abort(TransactionOutcome::Finished) {
+			dprintln!("Tried to finish an aborted transaction!");
+			return;
+		}
+		self.progress.store(10000, Ordering::Release);
 		self.emit(TransactionMessage::Finished(self.id, json!(data)));
+		self.update_parent();
 	}
 
 	pub fn cancel(&self) {
-		self.abort();
+		self.abort(TransactionOutcome::Cancelled);
+		self.update_parent();
 	}
 
 	pub fn aborted(&self) -> bool {
 		self.aborted.load(Ordering::Acquire)
 	}
+
+	/// Pauses this transaction and its children
+	///
+	/// Work carries on until its next [`pause_point`](TransactionInner::pause_point), so only transactions that have them can really be
+	/// paused.
+	pub fn pause(&self) {
+		self.set_paused(true);
+	}
+
+	pub fn resume(&self) {
+		self.set_paused(false);
+	}
+
+	fn set_paused(&self, paused: bool) {
+		if self.aborted() {
+			return;
+		}
+
+		let mut state = self.paused.lock();
+		if *state == paused {
+			return;
+		}
+		*state = paused;
+		self.emit(TransactionMessage::Paused(self.id, paused));
+		drop(state);
+
+		if !paused {
+			self.resumed.notify_all();
+		}
+
+		for child in self.children() {
+			child.set_paused(paused);
+		}
+	}
+
+	pub fn paused(&self) -> bool {
+		*self.paused.lock() && !self.aborted()
+	}
+
+	/// Blocks while this transaction is paused, unless it's cancelled
+	///
+	/// Long running work should call this between units of work.
+	pub fn pause_point(&self) {
+		let mut paused = self.paused.lock();
+		while *paused && !self.aborted() {
+			self.resumed.wait(&mut paused);
+		}
+	}
+}
+impl libgma::Progress for TransactionInner {
+	fn progress(&self, progress: f64) {
+		TransactionInner::progress(self, progress);
+	}
+
+	fn total(&self, bytes: u64) {
+		self.data((turbonone!(), bytes));
+	}
+
+	fn skipped(&self, path: &str) {
+		self.data(ErrorCode::Whitelist(vec![path.to_owned()]));
+	}
+
+	fn cancelled(&self) -> bool {
+		self.aborted()
+	}
+
+	fn pause_point(&self) {
+		TransactionInner::pause_point(self);
+	}
 }
 impl Drop for TransactionInner {
 	fn drop(&mut self) {
 		if !self.aborted() {
-			self.error("ERR_UNKNOWN", turbonone!());
-			self.abort();
+			self.error(ErrorCode::Unknown, turbonone!());
 
 			#[cfg(debug_assertions)]
 			println!("{:#?}", backtrace::Backtrace::new());
@@ -193,9 +530,22 @@ pub fn init() {
 }
 
 pub fn new() -> Transaction {
+	new_transaction(None)
+}
+
+fn new_transaction(parent: Option<Transaction>) -> Transaction {
 	let transaction = Arc::new(TransactionInner {
 		id: TRANSACTIONS.id.fetch_add(1, Ordering::SeqCst),
 		aborted: AtomicBool::new(false),
+		progress: AtomicU16::new(0),
+		coalescer: Mutex::new(ProgressCoalescer::default()),
+		paused: Mutex::new(false),
+		resumed: Condvar::new(),
+		status: Mutex::new(None),
+		started: Instant::now(),
+		parent,
+		children: Mutex::new(Vec::new()),
+		finish_with_children: AtomicBool::new(false),
 	});
 
 	{
@@ -210,6 +560,15 @@ pub fn new() -> Transaction {
 	transaction
 }
 
+/// Sends every transaction's events to `sink` as well, from now on
+pub fn add_sink(sink: Arc<dyn TransactionSink>) {
+	TRANSACTIONS.sinks.write().push(sink);
+}
+
+pub fn remove_sink(sink: &Arc<dyn TransactionSink>) {
+	TRANSACTIONS.sinks.write().retain(|registered| !Arc::ptr_eq(registered, sink));
+}
+
 #[macro_export]
 macro_rules! transaction {
 	() => {
@@ -217,6 +576,7 @@ macro_rules! transaction {
 	};
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
 pub fn cancel_transaction(id: u32) {
 	if let Some(transaction) = TRANSACTIONS.find(id) {
@@ -224,7 +584,270 @@ pub fn cancel_transaction(id: u32) {
 	}
 }
 
+#[cfg(feature = "gui")]
 #[tauri::command]
-pub fn websocket() -> Option<u16> {
-	TRANSACTIONS.websocket.as_ref().map(|socket| socket.port)
+pub fn pause_transaction(id: u32) {
+	if let Some(transaction) = TRANSACTIONS.find(id) {
+		transaction.pause();
+	}
+}
+
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn resume_transaction(id: u32) {
+	if let Some(transaction) = TRANSACTIONS.find(id) {
+		transaction.resume();
+	}
+}
+
+/// The transactions in progress, sorted by ID
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn active_transactions() -> Vec<ActiveTransaction> {
+	TRANSACTIONS.active()
+}
+
+/// The most recently finished transactions, oldest first
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn transaction_history() -> Vec<FinishedTransaction> {
+	TRANSACTIONS.history()
+}
+
+/// The port and token to connect to the transaction websocket with, if it's running
+#[cfg(feature = "gui")]
+#[tauri::command]
+pub fn websocket() -> Option<Arc<TransactionServer>> {
+	TRANSACTIONS.websocket.clone()
+}
+
+#[test]
+fn test_children() {
+	use TransactionMessage::*;
+
+	let recorder = Arc::new(TransactionRecorder::default());
+	add_sink(recorder.clone());
+
+	let parent = new();
+	let packing = parent.child(1.);
+	let uploading = parent.child(3.);
+
+	packing.status("PUBLISH_PACKING");
+	packing.progress(0.5);
+	packing.finished(turbonone!());
+	uploading.progress(0.5);
+
+	assert_eq!(
+		recorder.messages(parent.id),
+		[
+			Child(parent.id, packing.id, 1.),
+			Child(parent.id, uploading.id, 3.),
+			Status(parent.id, "PUBLISH_PACKING".to_string()),
+			Progress(parent.id, 1250),
+			Progress(parent.id, 2500),
+			Progress(parent.id, 6250),
+		]
+	);
+
+	// Cancelling a parent cancels its children, and children of a cancelled parent start cancelled
+	parent.cancel();
+	assert!(uploading.aborted());
+	assert!(parent.child(1.).aborted());
+
+	// Finishing or failing after being cancelled does nothing
+	uploading.finished(turbonone!());
+	uploading.error(ErrorCode::Unknown, turbonone!());
+	assert!(!recorder
+		.messages(uploading.id)
+		.iter()
+		.any(|message| matches!(message, Finished(..) | Error(..))));
+
+	// Errors count as complete
+	let parent = new();
+	let downloading = parent.child(1.);
+	let extracting = parent.child(1.);
+	parent.finish_with_children();
+	downloading.error(ErrorCode::DownloadFailed, turbonone!());
+	assert!(!parent.aborted());
+	extracting.finished(turbonone!());
+	assert!(matches!(recorder.messages(parent.id).last(), Some(Finished(..))));
+
+	let parent = new();
+	parent.finish_with_children();
+	assert!(parent.aborted());
+
+	let packing = new();
+	libgma::Progress::skipped(&*packing, "a.exe");
+	assert_eq!(recorder.messages(packing.id), [Data(packing.id, json!("ERR_WHITELIST:a.exe"))]);
+	packing.finished(turbonone!());
+
+	remove_sink(&(recorder as Arc<dyn TransactionSink>));
+}
+
+#[test]
+fn test_dropping_children() {
+	use std::{sync::Barrier, time::Duration};
+
+	// Dropping a child while a sibling reports progress mustn't deadlock the parent
+	let (tx, rx) = std::sync::mpsc::channel();
+	std::thread::spawn(move || {
+		for _ in 0..200 {
+			let parent = new();
+			let dropped = parent.child(1.);
+			let sibling = parent.child(1.);
+
+			let barrier = Arc::new(Barrier::new(2));
+			let dropping = std::thread::spawn({
+				let barrier = barrier.clone();
+				move || {
+					barrier.wait();
+					drop(dropped);
+				}
+			});
+
+			barrier.wait();
+			for i in 0..100 {
+				sibling.progress(i as f64 / 100.);
+			}
+			dropping.join().unwrap();
+
+			sibling.finished(turbonone!());
+			parent.finished(turbonone!());
+		}
+		tx.send(()).unwrap();
+	});
+
+	rx.recv_timeout(Duration::from_secs(30)).expect("Deadlocked");
+}
+
+#[test]
+fn test_coalescing() {
+	use rayon::iter::{IntoParallelIterator, ParallelIterator};
+	use TransactionMessage::*;
+
+	let recorder = Arc::new(TransactionRecorder::default());
+	add_sink(recorder.clone());
+
+	let transaction = new();
+	for i in 1..=1000 {
+		transaction.progress(i as f64 / 100000.);
+	}
+	transaction.status("EXTRACTING");
+	transaction.progress(0.01055);
+	transaction.status("EXTRACTING");
+	transaction.finished(turbonone!());
+
+	let mut expected: Vec<TransactionMessage> = (1..=10).map(|i| Progress(transaction.id, i * 10)).collect();
+	expected.extend([
+		Status(transaction.id, "EXTRACTING".to_string()),
+		Progress(transaction.id, 105),
+		Status(transaction.id, "EXTRACTING".to_string()),
+		Finished(transaction.id, serde_json::Value::Null),
+	]);
+	assert_eq!(recorder.messages(transaction.id), expected);
+
+	// Progress reported from many threads at once can't arrive after the transaction has finished
+	let transaction = new();
+	let reported = AtomicU32::new(0);
+	(0..10000).into_par_iter().for_each(|_| {
+		let i = reported.fetch_add(1, Ordering::AcqRel) + 1;
+		if i == 5000 {
+			transaction.finished(turbonone!());
+		} else if !transaction.aborted() {
+			transaction.progress(i as f64 / 10000.);
+		}
+	});
+
+	let messages = recorder.messages(transaction.id);
+	assert!(messages.len() < 5000);
+	assert!(matches!(messages.last(), Some(Finished(..))));
+
+	remove_sink(&(recorder as Arc<dyn TransactionSink>));
+}
+
+#[test]
+fn test_pausing() {
+	use std::time::Duration;
+	use TransactionMessage::*;
+
+	let recorder = Arc::new(TransactionRecorder::default());
+	add_sink(recorder.clone());
+
+	// Pausing a parent pauses its children, and children of a paused parent start paused
+	let parent = new();
+	let extracting = parent.child(1.);
+	parent.pause();
+	parent.pause();
+	assert!(extracting.paused());
+	let queued = parent.child(1.);
+	assert!(queued.paused());
+
+	let worker = {
+		let extracting = extracting.clone();
+		std::thread::spawn(move || extracting.pause_point())
+	};
+	std::thread::sleep(Duration::from_millis(100));
+	assert!(!worker.is_finished());
+
+	parent.resume();
+	worker.join().unwrap();
+	assert!(!extracting.paused());
+
+	assert_eq!(
+		recorder.messages(parent.id),
+		[
+			Child(parent.id, extracting.id, 1.),
+			Paused(parent.id, true),
+			Child(parent.id, queued.id, 1.),
+			Paused(parent.id, false),
+		]
+	);
+	assert_eq!(
+		recorder.messages(extracting.id),
+		[Paused(extracting.id, true), Paused(extracting.id, false)]
+	);
+
+	// Cancelling wakes anything waiting for it to be resumed
+	parent.pause();
+	let worker = {
+		let extracting = extracting.clone();
+		std::thread::spawn(move || extracting.pause_point())
+	};
+	parent.cancel();
+	worker.join().unwrap();
+	assert!(!extracting.paused());
+
+	remove_sink(&(recorder as Arc<dyn TransactionSink>));
+}
+
+#[test]
+fn test_registry() {
+	let publishing = new();
+	let packing = publishing.child(1.);
+	packing.status("PUBLISH_PACKING");
+	packing.progress(0.5);
+
+	let active = TRANSACTIONS.active();
+	let active = active.iter().find(|transaction| transaction.id == packing.id).unwrap();
+	assert_eq!(active.parent, Some(publishing.id));
+	assert_eq!(active.status.as_deref(), Some("PUBLISH_PACKING"));
+	assert_eq!(active.progress, 5000);
+
+	packing.finished(turbonone!());
+	publishing.error(ErrorCode::SteamError("Timeout".to_string()), turbonone!());
+	publishing.cancel();
+
+	let history = TRANSACTIONS.history();
+	let finished = |id: u32| history.iter().find(|transaction| transaction.id == id).unwrap();
+	assert_eq!(finished(packing.id).outcome, TransactionOutcome::Finished);
+	assert_eq!(finished(packing.id).parent, Some(publishing.id));
+	assert_eq!(finished(publishing.id).status.as_deref(), Some("PUBLISH_PACKING"));
+
+	// Only the first outcome counts
+	assert_eq!(
+		finished(publishing.id).outcome,
+		TransactionOutcome::Error("ERR_STEAM_ERROR:Timeout".to_string())
+	);
+	assert_eq!(history.iter().filter(|transaction| transaction.id == publishing.id).count(), 1);
+	assert!(!TRANSACTIONS.active().iter().any(|transaction| transaction.id == publishing.id));
 }
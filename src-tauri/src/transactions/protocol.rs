@@ -0,0 +1,577 @@
+//! The binary protocol spoken over the transaction websocket
+//!
+//! Every frame is a binary websocket message that starts with a `u8` type, followed by its fields in order. Integers and floats are big
+//! endian. Strings are UTF-8 and null terminated. JSON is a `u8` that is 0 for `null`, or 1 followed by the JSON as a string.
+//!
+//! The client has to send [`Request::Hello`] first, with the [`PROTOCOL_VERSION`] it speaks and the session's token, which the webview gets
+//! from the `websocket` command. The server answers with [`Event::Welcome`], or [`Event::Rejected`] and closes the connection if the client
+//! speaks a different version or doesn't know the token. After that, the client receives every transaction's
+//! events until it subscribes to specific transactions, see [`Subscription`].
+//!
+//! `app/protocol.js` implements the client side, and both are tested against `protocol_fixtures.json`.
+
+// The websocket server doesn't exist in headless builds
+#![cfg_attr(not(feature = "gui"), allow(dead_code))]
+
+use std::collections::HashSet;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+/// Bumped whenever a frame changes in a way older clients or servers can't read
+pub const PROTOCOL_VERSION: u16 = 3;
+
+pub const REQUEST_HELLO: u8 = 0x00;
+pub const REQUEST_CANCEL: u8 = 0x01;
+pub const REQUEST_PAUSE: u8 = 0x02;
+pub const REQUEST_RESUME: u8 = 0x03;
+pub const REQUEST_LIST: u8 = 0x04;
+pub const REQUEST_SUBSCRIBE: u8 = 0x05;
+pub const REQUEST_SUBSCRIBE_ALL: u8 = 0x06;
+
+pub const EVENT_WELCOME: u8 = 0x00;
+pub const EVENT_REJECTED: u8 = 0x01;
+pub const EVENT_LIST: u8 = 0x02;
+pub const EVENT_FINISHED: u8 = 0x10;
+pub const EVENT_ERROR: u8 = 0x11;
+pub const EVENT_DATA: u8 = 0x12;
+pub const EVENT_STATUS: u8 = 0x13;
+pub const EVENT_PROGRESS: u8 = 0x14;
+pub const EVENT_INCR_PROGRESS: u8 = 0x15;
+pub const EVENT_RESET_PROGRESS: u8 = 0x16;
+pub const EVENT_CHILD: u8 = 0x17;
+pub const EVENT_PAUSED: u8 = 0x18;
+
+/// Written in place of a missing parent in [`Event::List`]
+const NO_PARENT: u32 = u32::MAX;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+	Empty,
+	UnknownType(u8),
+	/// A [`Request::Hello`] for a version other than [`PROTOCOL_VERSION`], whose fields can't be read
+	UnsupportedVersion(u16),
+	/// The frame ended before all of its fields were read
+	Truncated,
+	/// The frame has bytes left over after all of its fields were read
+	TrailingBytes,
+	InvalidString,
+	InvalidJson,
+	InvalidBool,
+}
+impl std::fmt::Display for ProtocolError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ProtocolError::Empty => write!(f, "Empty frame"),
+			ProtocolError::UnknownType(ty) => write!(f, "Unknown frame type {:#04x}", ty),
+			ProtocolError::UnsupportedVersion(version) => write!(f, "Unsupported protocol version {}", version),
+			ProtocolError::Truncated => write!(f, "Truncated frame"),
+			ProtocolError::TrailingBytes => write!(f, "Trailing bytes after frame"),
+			ProtocolError::InvalidString => write!(f, "Invalid string"),
+			ProtocolError::InvalidJson => write!(f, "Invalid JSON"),
+			ProtocolError::InvalidBool => write!(f, "Invalid boolean"),
+		}
+	}
+}
+impl From<std::io::Error> for ProtocolError {
+	fn from(_: std::io::Error) -> Self {
+		ProtocolError::Truncated
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransactionMessage {
+	Finished(u32, serde_json::Value),
+	Error(u32, String, serde_json::Value),
+	Data(u32, serde_json::Value),
+	Status(u32, String),
+	Progress(u32, u16),
+	IncrProgress(u32, u16),
+	ResetProgress(u32),
+	/// A transaction started a child transaction with this weight, see [`TransactionInner::child`](super::TransactionInner::child)
+	Child(u32, u32, f64),
+	/// A transaction was paused (true) or resumed (false), see [`TransactionInner::pause`](super::TransactionInner::pause)
+	Paused(u32, bool),
+}
+impl TransactionMessage {
+	pub fn id(&self) -> u32 {
+		match self {
+			TransactionMessage::Finished(id, _)
+			| TransactionMessage::Error(id, _, _)
+			| TransactionMessage::Data(id, _)
+			| TransactionMessage::Status(id, _)
+			| TransactionMessage::Progress(id, _)
+			| TransactionMessage::IncrProgress(id, _)
+			| TransactionMessage::ResetProgress(id)
+			| TransactionMessage::Child(id, _, _)
+			| TransactionMessage::Paused(id, _) => *id,
+		}
+	}
+
+	/// The message as a JSON object, with its kind in `type` and progress from 0.0 to 1.0
+	pub fn to_json(&self) -> serde_json::Value {
+		match self {
+			TransactionMessage::Finished(id, data) => serde_json::json!({ "transaction": id, "type": "finished", "data": data }),
+			TransactionMessage::Error(id, msg, data) => serde_json::json!({ "transaction": id, "type": "error", "error": msg, "data": data }),
+			TransactionMessage::Data(id, data) => serde_json::json!({ "transaction": id, "type": "data", "data": data }),
+			TransactionMessage::Status(id, status) => serde_json::json!({ "transaction": id, "type": "status", "status": status }),
+			TransactionMessage::Progress(id, progress) => {
+				serde_json::json!({ "transaction": id, "type": "progress", "progress": *progress as f64 / 10000. })
+			}
+			TransactionMessage::IncrProgress(id, incr) => {
+				serde_json::json!({ "transaction": id, "type": "incr_progress", "progress": *incr as f64 / 10000. })
+			}
+			TransactionMessage::ResetProgress(id) => serde_json::json!({ "transaction": id, "type": "reset_progress" }),
+			TransactionMessage::Child(id, child, weight) => {
+				serde_json::json!({ "transaction": id, "type": "child", "child": child, "weight": weight })
+			}
+			TransactionMessage::Paused(id, paused) => serde_json::json!({ "transaction": id, "type": "paused", "paused": paused }),
+		}
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let mut bytes = Vec::new();
+
+		match self {
+			TransactionMessage::Finished(id, data) => {
+				bytes.write_u8(EVENT_FINISHED).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				write_json(&mut bytes, data);
+			}
+			TransactionMessage::Error(id, msg, data) => {
+				bytes.write_u8(EVENT_ERROR).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				write_string(&mut bytes, msg);
+				write_json(&mut bytes, data);
+			}
+			TransactionMessage::Data(id, data) => {
+				bytes.write_u8(EVENT_DATA).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				write_json(&mut bytes, data);
+			}
+			TransactionMessage::Status(id, status) => {
+				bytes.write_u8(EVENT_STATUS).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				write_string(&mut bytes, status);
+			}
+			TransactionMessage::Progress(id, progress) => {
+				bytes.write_u8(EVENT_PROGRESS).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				bytes.write_u16::<BigEndian>(*progress).unwrap();
+			}
+			TransactionMessage::IncrProgress(id, incr) => {
+				bytes.write_u8(EVENT_INCR_PROGRESS).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				bytes.write_u16::<BigEndian>(*incr).unwrap();
+			}
+			TransactionMessage::ResetProgress(id) => {
+				bytes.write_u8(EVENT_RESET_PROGRESS).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+			}
+			TransactionMessage::Child(id, child, weight) => {
+				bytes.write_u8(EVENT_CHILD).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				bytes.write_u32::<BigEndian>(*child).unwrap();
+				bytes.write_f64::<BigEndian>(*weight).unwrap();
+			}
+			TransactionMessage::Paused(id, paused) => {
+				bytes.write_u8(EVENT_PAUSED).unwrap();
+				bytes.write_u32::<BigEndian>(*id).unwrap();
+				bytes.write_u8(*paused as u8).unwrap();
+			}
+		}
+
+		bytes
+	}
+}
+
+/// A frame sent by the client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+	/// The version always comes first, so that other versions can be told apart from garbage
+	Hello {
+		version: u16,
+		token: String,
+	},
+	Cancel(u32),
+	Pause(u32),
+	Resume(u32),
+	/// Asks for an [`Event::List`] of the transactions in progress
+	List,
+	/// Only receive events for this transaction, its children and any other transactions subscribed to
+	Subscribe(u32),
+	/// Receive events for every transaction again
+	SubscribeAll,
+}
+impl Request {
+	pub fn kind(&self) -> u8 {
+		match self {
+			Request::Hello { .. } => REQUEST_HELLO,
+			Request::Cancel(_) => REQUEST_CANCEL,
+			Request::Pause(_) => REQUEST_PAUSE,
+			Request::Resume(_) => REQUEST_RESUME,
+			Request::List => REQUEST_LIST,
+			Request::Subscribe(_) => REQUEST_SUBSCRIBE,
+			Request::SubscribeAll => REQUEST_SUBSCRIBE_ALL,
+		}
+	}
+
+	pub fn encode(&self) -> Vec<u8> {
+		let mut bytes = vec![self.kind()];
+		match self {
+			Request::Hello { version, token } => {
+				bytes.write_u16::<BigEndian>(*version).unwrap();
+				write_string(&mut bytes, token);
+			}
+			Request::Cancel(id) | Request::Pause(id) | Request::Resume(id) | Request::Subscribe(id) => bytes.write_u32::<BigEndian>(*id).unwrap(),
+			Request::List | Request::SubscribeAll => {}
+		}
+		bytes
+	}
+
+	pub fn decode(mut bytes: &[u8]) -> Result<Request, ProtocolError> {
+		let bytes = &mut bytes;
+		let request = match read_type(bytes)? {
+			REQUEST_HELLO => match bytes.read_u16::<BigEndian>()? {
+				PROTOCOL_VERSION => Request::Hello {
+					version: PROTOCOL_VERSION,
+					token: read_string(bytes)?,
+				},
+				version => return Err(ProtocolError::UnsupportedVersion(version)),
+			},
+			REQUEST_CANCEL => Request::Cancel(bytes.read_u32::<BigEndian>()?),
+			REQUEST_PAUSE => Request::Pause(bytes.read_u32::<BigEndian>()?),
+			REQUEST_RESUME => Request::Resume(bytes.read_u32::<BigEndian>()?),
+			REQUEST_LIST => Request::List,
+			REQUEST_SUBSCRIBE => Request::Subscribe(bytes.read_u32::<BigEndian>()?),
+			REQUEST_SUBSCRIBE_ALL => Request::SubscribeAll,
+			ty => return Err(ProtocolError::UnknownType(ty)),
+		};
+		finish(bytes)?;
+		Ok(request)
+	}
+}
+
+/// A transaction in [`Event::List`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionSummary {
+	pub id: u32,
+	pub parent: Option<u32>,
+	/// From 0 to 10000
+	pub progress: u16,
+	pub paused: bool,
+}
+
+/// A frame sent by the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	Welcome {
+		version: u16,
+	},
+	/// A request couldn't be carried out, with the type of the request and an error code
+	Rejected {
+		request: u8,
+		reason: String,
+	},
+	List(Vec<TransactionSummary>),
+	Transaction(TransactionMessage),
+}
+impl Event {
+	pub fn encode(&self) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		match self {
+			Event::Welcome { version } => {
+				bytes.write_u8(EVENT_WELCOME).unwrap();
+				bytes.write_u16::<BigEndian>(*version).unwrap();
+			}
+			Event::Rejected { request, reason } => {
+				bytes.write_u8(EVENT_REJECTED).unwrap();
+				bytes.write_u8(*request).unwrap();
+				write_string(&mut bytes, reason);
+			}
+			Event::List(transactions) => {
+				bytes.write_u8(EVENT_LIST).unwrap();
+				bytes.write_u32::<BigEndian>(transactions.len() as u32).unwrap();
+				for transaction in transactions {
+					bytes.write_u32::<BigEndian>(transaction.id).unwrap();
+					bytes.write_u32::<BigEndian>(transaction.parent.unwrap_or(NO_PARENT)).unwrap();
+					bytes.write_u16::<BigEndian>(transaction.progress).unwrap();
+					bytes.write_u8(transaction.paused as u8).unwrap();
+				}
+			}
+			Event::Transaction(message) => return message.encode(),
+		}
+		bytes
+	}
+
+	pub fn decode(mut bytes: &[u8]) -> Result<Event, ProtocolError> {
+		let bytes = &mut bytes;
+		let event = match read_type(bytes)? {
+			EVENT_WELCOME => Event::Welcome {
+				version: bytes.read_u16::<BigEndian>()?,
+			},
+			EVENT_REJECTED => Event::Rejected {
+				request: bytes.read_u8()?,
+				reason: read_string(bytes)?,
+			},
+			EVENT_LIST => {
+				let len = bytes.read_u32::<BigEndian>()?;
+				let mut transactions = Vec::with_capacity(usize::min(len as usize, bytes.len() / 11));
+				for _ in 0..len {
+					transactions.push(TransactionSummary {
+						id: bytes.read_u32::<BigEndian>()?,
+						parent: Some(bytes.read_u32::<BigEndian>()?).filter(|parent| *parent != NO_PARENT),
+						progress: bytes.read_u16::<BigEndian>()?,
+						paused: read_bool(bytes)?,
+					});
+				}
+				Event::List(transactions)
+			}
+
+			EVENT_FINISHED => Event::Transaction(TransactionMessage::Finished(bytes.read_u32::<BigEndian>()?, read_json(bytes)?)),
+			EVENT_ERROR => Event::Transaction(TransactionMessage::Error(
+				bytes.read_u32::<BigEndian>()?,
+				read_string(bytes)?,
+				read_json(bytes)?,
+			)),
+			EVENT_DATA => Event::Transaction(TransactionMessage::Data(bytes.read_u32::<BigEndian>()?, read_json(bytes)?)),
+			EVENT_STATUS => Event::Transaction(TransactionMessage::Status(bytes.read_u32::<BigEndian>()?, read_string(bytes)?)),
+			EVENT_PROGRESS => Event::Transaction(TransactionMessage::Progress(
+				bytes.read_u32::<BigEndian>()?,
+				bytes.read_u16::<BigEndian>()?,
+			)),
+			EVENT_INCR_PROGRESS => Event::Transaction(TransactionMessage::IncrProgress(
+				bytes.read_u32::<BigEndian>()?,
+				bytes.read_u16::<BigEndian>()?,
+			)),
+			EVENT_RESET_PROGRESS => Event::Transaction(TransactionMessage::ResetProgress(bytes.read_u32::<BigEndian>()?)),
+			EVENT_CHILD => Event::Transaction(TransactionMessage::Child(
+				bytes.read_u32::<BigEndian>()?,
+				bytes.read_u32::<BigEndian>()?,
+				bytes.read_f64::<BigEndian>()?,
+			)),
+			EVENT_PAUSED => Event::Transaction(TransactionMessage::Paused(bytes.read_u32::<BigEndian>()?, read_bool(bytes)?)),
+
+			ty => return Err(ProtocolError::UnknownType(ty)),
+		};
+		finish(bytes)?;
+		Ok(event)
+	}
+}
+
+/// Which transactions a connection receives events for
+///
+/// Everything until [`Subscription::subscribe`] is called. Children of subscribed transactions are subscribed to as they start.
+#[derive(Debug, Default)]
+pub struct Subscription {
+	ids: Option<HashSet<u32>>,
+}
+impl Subscription {
+	pub fn subscribe(&mut self, id: u32) {
+		self.ids.get_or_insert_with(HashSet::new).insert(id);
+	}
+
+	pub fn subscribe_all(&mut self) {
+		self.ids = None;
+	}
+
+	/// Whether the connection should receive `message`
+	pub fn wants(&mut self, message: &TransactionMessage) -> bool {
+		let ids = match &mut self.ids {
+			Some(ids) => ids,
+			None => return true,
+		};
+
+		if !ids.contains(&message.id()) {
+			return false;
+		}
+
+		match message {
+			TransactionMessage::Child(_, child, _) => {
+				ids.insert(*child);
+			}
+			TransactionMessage::Finished(id, _) | TransactionMessage::Error(id, _, _) => {
+				ids.remove(id);
+			}
+			_ => {}
+		}
+
+		true
+	}
+}
+
+fn write_string(bytes: &mut Vec<u8>, str: &str) {
+	bytes.extend_from_slice(str.as_bytes());
+	bytes.push(0);
+}
+
+fn write_json(bytes: &mut Vec<u8>, json: &serde_json::Value) {
+	if json.is_null() {
+		bytes.push(0);
+	} else {
+		bytes.push(1);
+		write_string(bytes, &serde_json::to_string(json).unwrap());
+	}
+}
+
+fn read_type(bytes: &mut &[u8]) -> Result<u8, ProtocolError> {
+	bytes.read_u8().map_err(|_| ProtocolError::Empty)
+}
+
+fn read_string(bytes: &mut &[u8]) -> Result<String, ProtocolError> {
+	let len = bytes.iter().position(|byte| *byte == 0).ok_or(ProtocolError::Truncated)?;
+	let str = std::str::from_utf8(&bytes[..len]).map_err(|_| ProtocolError::InvalidString)?.to_owned();
+	*bytes = &bytes[len + 1..];
+	Ok(str)
+}
+
+fn read_bool(bytes: &mut &[u8]) -> Result<bool, ProtocolError> {
+	match bytes.read_u8()? {
+		0 => Ok(false),
+		1 => Ok(true),
+		_ => Err(ProtocolError::InvalidBool),
+	}
+}
+
+fn read_json(bytes: &mut &[u8]) -> Result<serde_json::Value, ProtocolError> {
+	match bytes.read_u8()? {
+		0 => Ok(serde_json::Value::Null),
+		1 => serde_json::from_str(&read_string(bytes)?).map_err(|_| ProtocolError::InvalidJson),
+		_ => Err(ProtocolError::InvalidJson),
+	}
+}
+
+fn finish(bytes: &[u8]) -> Result<(), ProtocolError> {
+	if bytes.is_empty() {
+		Ok(())
+	} else {
+		Err(ProtocolError::TrailingBytes)
+	}
+}
+
+#[cfg(test)]
+fn fixture_message(fixture: &serde_json::Value) -> Result<Request, Event> {
+	let message = &fixture["message"];
+	let id = || message["id"].as_u64().unwrap() as u32;
+	let string = |key: &str| message[key].as_str().unwrap().to_owned();
+
+	match message["type"].as_str().unwrap() {
+		"hello" => Ok(Request::Hello {
+			version: message["version"].as_u64().unwrap() as u16,
+			token: string("token"),
+		}),
+		"cancel" => Ok(Request::Cancel(id())),
+		"pause" => Ok(Request::Pause(id())),
+		"resume" => Ok(Request::Resume(id())),
+		"list" => Ok(Request::List),
+		"subscribe" => Ok(Request::Subscribe(id())),
+		"subscribe_all" => Ok(Request::SubscribeAll),
+
+		"welcome" => Err(Event::Welcome {
+			version: message["version"].as_u64().unwrap() as u16,
+		}),
+		"rejected" => Err(Event::Rejected {
+			request: message["request"].as_u64().unwrap() as u8,
+			reason: string("reason"),
+		}),
+		"transactions" => Err(Event::List(
+			message["transactions"]
+				.as_array()
+				.unwrap()
+				.iter()
+				.map(|transaction| TransactionSummary {
+					id: transaction["id"].as_u64().unwrap() as u32,
+					parent: transaction["parent"].as_u64().map(|parent| parent as u32),
+					progress: transaction["progress"].as_u64().unwrap() as u16,
+					paused: transaction["paused"].as_bool().unwrap(),
+				})
+				.collect(),
+		)),
+		"finished" => Err(Event::Transaction(TransactionMessage::Finished(id(), message["data"].clone()))),
+		"error" => Err(Event::Transaction(TransactionMessage::Error(
+			id(),
+			string("error"),
+			message["data"].clone(),
+		))),
+		"data" => Err(Event::Transaction(TransactionMessage::Data(id(), message["data"].clone()))),
+		"status" => Err(Event::Transaction(TransactionMessage::Status(id(), string("status")))),
+		"progress" => Err(Event::Transaction(TransactionMessage::Progress(
+			id(),
+			message["progress"].as_u64().unwrap() as u16,
+		))),
+		"incr_progress" => Err(Event::Transaction(TransactionMessage::IncrProgress(
+			id(),
+			message["progress"].as_u64().unwrap() as u16,
+		))),
+		"reset_progress" => Err(Event::Transaction(TransactionMessage::ResetProgress(id()))),
+		"child" => Err(Event::Transaction(TransactionMessage::Child(
+			id(),
+			message["child"].as_u64().unwrap() as u32,
+			message["weight"].as_f64().unwrap(),
+		))),
+		"paused" => Err(Event::Transaction(TransactionMessage::Paused(id(), message["paused"].as_bool().unwrap()))),
+		ty => panic!("Unknown fixture type {}", ty),
+	}
+}
+
+#[test]
+fn test_fixtures() {
+	let fixtures: Vec<serde_json::Value> = serde_json::from_str(include_str!("protocol_fixtures.json")).unwrap();
+	assert!(!fixtures.is_empty());
+
+	for fixture in fixtures {
+		let hex = fixture["bytes"].as_str().unwrap().replace(' ', "");
+		let bytes: Vec<u8> = (0..hex.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+			.collect();
+
+		match fixture_message(&fixture) {
+			Ok(request) => {
+				assert_eq!(request.encode(), bytes, "{}", fixture);
+				assert_eq!(Request::decode(&bytes), Ok(request), "{}", fixture);
+			}
+			Err(event) => {
+				assert_eq!(event.encode(), bytes, "{}", fixture);
+				assert_eq!(Event::decode(&bytes), Ok(event), "{}", fixture);
+			}
+		}
+	}
+}
+
+#[test]
+fn test_malformed() {
+	assert_eq!(Request::decode(&[]), Err(ProtocolError::Empty));
+	assert_eq!(Request::decode(&[0xFF]), Err(ProtocolError::UnknownType(0xFF)));
+	assert_eq!(Request::decode(&[REQUEST_HELLO, 0, 1]), Err(ProtocolError::UnsupportedVersion(1)));
+	assert_eq!(Request::decode(&[REQUEST_HELLO, 0, 3]), Err(ProtocolError::Truncated));
+	assert_eq!(Event::decode(&[EVENT_PAUSED, 0, 0, 0, 1, 2]), Err(ProtocolError::InvalidBool));
+
+	// The bug this protocol replaced: a cancel with a u32 ID, read from an 8 byte range
+	assert_eq!(Request::decode(&[REQUEST_CANCEL, 0, 0, 1]), Err(ProtocolError::Truncated));
+	assert_eq!(
+		Request::decode(&[REQUEST_CANCEL, 0, 0, 0, 1, 0, 0, 0, 0]),
+		Err(ProtocolError::TrailingBytes)
+	);
+	assert_eq!(Request::decode(&[REQUEST_CANCEL, 0, 0, 0, 1]), Ok(Request::Cancel(1)));
+
+	assert_eq!(Event::decode(&[EVENT_STATUS, 0, 0, 0, 1, b'a']), Err(ProtocolError::Truncated));
+	assert_eq!(Event::decode(&[EVENT_STATUS, 0, 0, 0, 1, 0xFF, 0]), Err(ProtocolError::InvalidString));
+	assert_eq!(Event::decode(&[EVENT_DATA, 0, 0, 0, 1, 1, b'{', 0]), Err(ProtocolError::InvalidJson));
+}
+
+#[test]
+fn test_subscription() {
+	let mut subscription = Subscription::default();
+	assert!(subscription.wants(&TransactionMessage::ResetProgress(1)));
+
+	subscription.subscribe(1);
+	assert!(!subscription.wants(&TransactionMessage::ResetProgress(2)));
+	assert!(subscription.wants(&TransactionMessage::Child(1, 3, 1.)));
+	assert!(subscription.wants(&TransactionMessage::Progress(3, 5000)));
+	assert!(subscription.wants(&TransactionMessage::Finished(3, serde_json::Value::Null)));
+	assert!(!subscription.wants(&TransactionMessage::Progress(3, 10000)));
+	assert!(subscription.wants(&TransactionMessage::Progress(1, 10000)));
+
+	subscription.subscribe_all();
+	assert!(subscription.wants(&TransactionMessage::ResetProgress(2)));
+}
@@ -0,0 +1,204 @@
+use std::{
+	collections::HashMap,
+	fs::{File, OpenOptions},
+	io::{LineWriter, Write},
+	path::Path,
+};
+
+use parking_lot::Mutex;
+
+use super::TransactionMessage;
+
+#[cfg(feature = "gui")]
+use super::websocket::TransactionServer;
+
+/// Prints transaction events to stderr, in any mode
+pub const VERBOSE_ENV: &str = "GMPUBLISHER_VERBOSE";
+
+/// Appends transaction events to this file as JSON lines, in any mode
+pub const TRANSACTION_LOG_ENV: &str = "GMPUBLISHER_TRANSACTION_LOG";
+
+/// Somewhere transaction events are sent to
+///
+/// Every registered sink receives every event, in the order they were emitted. See [`add_sink`](super::add_sink).
+pub trait TransactionSink: Send + Sync {
+	fn emit(&self, message: &TransactionMessage);
+}
+
+#[cfg(feature = "gui")]
+impl TransactionSink for TransactionServer {
+	fn emit(&self, message: &TransactionMessage) {
+		self.send(message.clone());
+	}
+}
+
+/// Sends transaction events to the webview as Tauri events, for when the websocket server couldn't be started
+#[cfg(feature = "gui")]
+pub struct TauriEventSink;
+#[cfg(feature = "gui")]
+impl TransactionSink for TauriEventSink {
+	fn emit(&self, message: &TransactionMessage) {
+		TransactionServer::send_tauri_event(message.clone());
+	}
+}
+
+/// Prints transaction events to stderr, with progress rounded to whole percents
+#[derive(Default)]
+pub struct TerminalSink {
+	progress: Mutex<HashMap<u32, u16>>,
+}
+impl TransactionSink for TerminalSink {
+	fn emit(&self, message: &TransactionMessage) {
+		match message {
+			TransactionMessage::Status(id, status) => std::eprintln!("[#{}] {}", id, status),
+
+			TransactionMessage::Error(id, msg, data) => {
+				self.progress.lock().remove(id);
+				if data.is_null() {
+					std::eprintln!("[#{}] Error: {}", id, msg);
+				} else {
+					std::eprintln!("[#{}] Error: {} {}", id, msg, data);
+				}
+			}
+
+			TransactionMessage::Finished(id, _) => {
+				self.progress.lock().remove(id);
+				std::eprintln!("[#{}] Finished", id);
+			}
+
+			TransactionMessage::Paused(id, true) => std::eprintln!("[#{}] Paused", id),
+			TransactionMessage::Paused(id, false) => std::eprintln!("[#{}] Resumed", id),
+
+			TransactionMessage::Progress(id, _) | TransactionMessage::IncrProgress(id, _) | TransactionMessage::ResetProgress(id) => {
+				let mut progress = self.progress.lock();
+				let progress = progress.entry(*id).or_default();
+
+				let before = *progress / 100;
+				*progress = match message {
+					TransactionMessage::Progress(_, value) => *value,
+					TransactionMessage::IncrProgress(_, incr) => u16::min(progress.saturating_add(*incr), 10000),
+					_ => 0,
+				};
+
+				if *progress / 100 != before {
+					std::eprintln!("[#{}] {}%", id, *progress / 100);
+				}
+			}
+
+			TransactionMessage::Data(..) | TransactionMessage::Child(..) => {}
+		}
+	}
+}
+
+/// Appends transaction events to a file, one JSON object per line
+pub struct JsonLinesSink {
+	file: Mutex<LineWriter<File>>,
+}
+impl JsonLinesSink {
+	pub fn create<P: AsRef<Path>>(path: P) -> Result<JsonLinesSink, std::io::Error> {
+		Ok(JsonLinesSink {
+			file: Mutex::new(LineWriter::new(OpenOptions::new().create(true).append(true).open(path)?)),
+		})
+	}
+}
+impl TransactionSink for JsonLinesSink {
+	fn emit(&self, message: &TransactionMessage) {
+		let mut json = message.to_json();
+		json["time"] = json!(chrono::Utc::now().to_rfc3339());
+
+		let _ = writeln!(self.file.lock(), "{}", json);
+	}
+}
+
+/// Keeps every transaction event in memory, so tests can assert what an operation emitted
+#[derive(Default)]
+pub struct TransactionRecorder {
+	messages: Mutex<Vec<TransactionMessage>>,
+}
+impl TransactionRecorder {
+	/// The events emitted by a transaction so far, in order
+	pub fn messages(&self, transaction_id: u32) -> Vec<TransactionMessage> {
+		self.messages
+			.lock()
+			.iter()
+			.filter(|message| message.id() == transaction_id)
+			.cloned()
+			.collect()
+	}
+}
+impl TransactionSink for TransactionRecorder {
+	fn emit(&self, message: &TransactionMessage) {
+		self.messages.lock().push(message.clone());
+	}
+}
+
+#[test]
+fn test_sinks() {
+	use std::sync::Arc;
+
+	use serde_json::Value;
+	use TransactionMessage::*;
+
+	let first = Arc::new(TransactionRecorder::default());
+	let second = Arc::new(TransactionRecorder::default());
+	super::add_sink(first.clone());
+	super::add_sink(second.clone());
+
+	let transaction = crate::transaction!();
+	transaction.status("PUBLISH_PACKING");
+	transaction.progress(0.5);
+	transaction.progress_incr(0.25);
+	transaction.error(crate::ErrorCode::IOError, turbonone!());
+	transaction.progress(1.); // Aborted, so not emitted
+
+	let expected = [
+		Status(transaction.id, "PUBLISH_PACKING".to_string()),
+		Progress(transaction.id, 5000),
+		Progress(transaction.id, 7500),
+		Error(transaction.id, "ERR_IO_ERROR".to_string(), Value::Null),
+	];
+	assert_eq!(first.messages(transaction.id), expected);
+	assert_eq!(second.messages(transaction.id), expected);
+
+	// Packing reports its progress, and cancelling it emits nothing else
+	let dir = std::env::temp_dir().join(format!("gmpublisher_test_sinks_{}", std::process::id()));
+	let _ = std::fs::remove_dir_all(&dir);
+	std::fs::create_dir_all(dir.join("src/lua/autorun")).unwrap();
+	std::fs::write(dir.join("src/lua/autorun/a.lua"), "").unwrap();
+	std::fs::write(dir.join("src/lua/autorun/b.lua"), "").unwrap();
+
+	let gma = crate::GMAFile::new(
+		dir.join("out.gma"),
+		crate::GMAMetadata::Standard {
+			title: "Test".to_string(),
+			addon_type: "tool".to_string(),
+			tags: Vec::new(),
+			ignore: Vec::new(),
+		},
+	);
+
+	let transaction = crate::transaction!();
+	gma.create(dir.join("src"), &crate::gma::PackOptions::default(), &*transaction).unwrap();
+	transaction.finished(turbonone!());
+	assert_eq!(
+		first.messages(transaction.id),
+		[
+			Progress(transaction.id, 5000),
+			Progress(transaction.id, 10000),
+			Finished(transaction.id, Value::Null)
+		]
+	);
+
+	let transaction = crate::transaction!();
+	transaction.cancel();
+	assert!(matches!(
+		gma.create(dir.join("src"), &crate::gma::PackOptions::default(), &*transaction),
+		Err(crate::GMAError::Cancelled)
+	));
+	assert!(first.messages(transaction.id).is_empty());
+
+	super::remove_sink(&(first as Arc<dyn TransactionSink>));
+	super::remove_sink(&(second as Arc<dyn TransactionSink>));
+
+	std::fs::remove_dir_all(dir).unwrap();
+}
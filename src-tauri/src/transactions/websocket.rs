@@ -1,136 +1,203 @@
-use crate::NTStringWriter;
-use byteorder::{BigEndian, WriteBytesExt};
-use crossbeam::channel::{Receiver, Sender};
+#![cfg(feature = "gui")]
+
 use std::{
-	convert::TryInto,
 	net::{TcpListener, TcpStream},
+	sync::Arc,
+	time::Duration,
 };
+
+use crossbeam::channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use serde::Serialize;
 use websocket::{
-	server::{NoTlsAcceptor, WsServer},
-	sync::{Client, Server},
+	sync::{server::IntoWs, Client},
 	OwnedMessage,
 };
 
-const CANCEL_TRANSACTION: &[u8] = &[123];
+use crate::ErrorCode;
+
+use super::protocol::{Event, ProtocolError, Request, Subscription, TransactionMessage, PROTOCOL_VERSION, REQUEST_HELLO};
+
+/// How long a client has to send its [`Request::Hello`] before it's disconnected
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
 
 enum WebSocketMessage {
 	OwnedMessage(OwnedMessage),
 	TransactionMessage(TransactionMessage),
+	Reply(Event),
 }
+
 impl From<WebSocketMessage> for OwnedMessage {
 	fn from(val: WebSocketMessage) -> Self {
 		match val {
 			WebSocketMessage::OwnedMessage(message) => message,
-			WebSocketMessage::TransactionMessage(message) => OwnedMessage::Binary(message.as_bytes()),
-		}
-	}
-}
-
-pub enum TransactionMessage {
-	Finished(u32, serde_json::Value),
-	Error(u32, String, serde_json::Value),
-	Data(u32, serde_json::Value),
-	Status(u32, String),
-	Progress(u32, u16),
-	IncrProgress(u32, u16),
-	ResetProgress(u32),
-}
-impl TransactionMessage {
-	fn write_json(bytes: &mut Vec<u8>, json: &serde_json::Value) {
-		if matches!(json, serde_json::Value::Null) {
-			bytes.write_u8(0).unwrap();
-		} else {
-			bytes.write_u8(1).unwrap(); // indicate that it's JSON
-			bytes.write_nt_string(serde_json::to_string(json).unwrap()).unwrap();
-		}
-	}
-
-	fn as_bytes(&self) -> Vec<u8> {
-		let mut bytes = Vec::new();
-
-		match self {
-			TransactionMessage::Finished(id, json) => {
-				bytes.write_u8(0).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-				TransactionMessage::write_json(&mut bytes, json);
-			}
-			TransactionMessage::Error(id, msg, json) => {
-				bytes.write_u8(1).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-				bytes.write_nt_string(msg).unwrap();
-				TransactionMessage::write_json(&mut bytes, json);
-			}
-			TransactionMessage::Data(id, json) => {
-				bytes.write_u8(2).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-				TransactionMessage::write_json(&mut bytes, json);
-			}
-			TransactionMessage::Status(id, status) => {
-				bytes.write_u8(3).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-				bytes.write_nt_string(status).unwrap();
-			}
-			TransactionMessage::Progress(id, progress) => {
-				bytes.write_u8(4).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-				bytes.write_u16::<BigEndian>(*progress).unwrap();
-			}
-			TransactionMessage::IncrProgress(id, incr) => {
-				bytes.write_u8(5).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-				bytes.write_u16::<BigEndian>(*incr).unwrap();
-			}
-			TransactionMessage::ResetProgress(id) => {
-				bytes.write_u8(6).unwrap();
-				bytes.write_u32::<BigEndian>(*id).unwrap();
-			}
+			WebSocketMessage::TransactionMessage(message) => OwnedMessage::Binary(message.encode()),
+			WebSocketMessage::Reply(event) => OwnedMessage::Binary(event.encode()),
 		}
-
-		bytes
-	}
-}
-impl From<TransactionMessage> for OwnedMessage {
-	fn from(val: TransactionMessage) -> Self {
-		OwnedMessage::Binary(val.as_bytes())
 	}
 }
 
+/// Serves transaction events to the webview, speaking the protocol in [`super::protocol`]
+///
+/// Only clients that know the token are welcomed, as any local process can connect to the port.
+#[derive(Serialize)]
 pub struct TransactionServer {
 	pub port: u16,
+	/// A secret generated for this session, which the webview gets from the `websocket` command
+	pub token: String,
+	#[serde(skip)]
 	tx: Sender<WebSocketMessage>,
 }
 impl TransactionServer {
 	pub fn init() -> Result<TransactionServer, anyhow::Error> {
-		let socket = Server::bind("127.0.0.1:0")?;
+		let socket = TcpListener::bind("127.0.0.1:0")?;
 		let addr = socket.local_addr()?;
 
+		let mut token = [0u8; 32];
+		getrandom::getrandom(&mut token).map_err(|err| anyhow::anyhow!("Failed to generate the websocket token: {}", err))?;
+		let token: String = token.iter().map(|byte| format!("{:02x}", byte)).collect();
+
 		let (tx, rx) = crossbeam::channel::unbounded::<WebSocketMessage>();
 
 		let tx_clone = tx.clone();
-		std::thread::spawn(move || TransactionServer::accept(socket, tx_clone, rx));
+		let token_clone = token.clone();
+		std::thread::spawn(move || TransactionServer::accept(socket, token_clone, tx_clone, rx));
 
-		Ok(TransactionServer { port: addr.port(), tx })
+		Ok(TransactionServer {
+			port: addr.port(),
+			token,
+			tx,
+		})
 	}
 
-	fn accept(mut socket: WsServer<NoTlsAcceptor, TcpListener>, tx: Sender<WebSocketMessage>, rx: Receiver<WebSocketMessage>) {
+	fn accept(socket: TcpListener, token: String, tx: Sender<WebSocketMessage>, rx: Receiver<WebSocketMessage>) {
+		// Clients that passed the handshake, served one at a time as they share the transaction events
+		let (clients_tx, clients_rx) = crossbeam::channel::unbounded::<Client<TcpStream>>();
+		std::thread::spawn(move || {
+			for client in clients_rx {
+				TransactionServer::listen(tx.clone(), &rx, client);
+			}
+		});
+
 		loop {
 			dprintln!("WebSocket: Waiting for client on {:?}", socket.local_addr().unwrap());
-			if let Ok(connection) = socket.accept() {
-				if connection.protocols().contains(&"gmpublisher".to_string()) {
-					if let Ok(client) = connection.use_protocol("gmpublisher").accept() {
-						dprintln!("WebSocket: Connection Established with {:#?}", client.peer_addr().unwrap());
-						TransactionServer::listen(tx.clone(), &rx, client);
+			if let Ok((stream, _)) = socket.accept() {
+				// Connecting happens on its own thread, so a client that never finishes its handshake can't hold up the others
+				let token = token.clone();
+				let clients_tx = clients_tx.clone();
+				std::thread::spawn(move || {
+					if let Some(client) = TransactionServer::connect(stream, &token) {
+						let _ = clients_tx.send(client);
 					}
-				} else {
-					dprintln!("WebSocket Error: Invalid Protocol");
-				}
+				});
 			}
 		}
 	}
 
+	/// Upgrades `stream` to a websocket speaking our protocol, and returns it once the client has passed the handshake
+	fn connect(stream: TcpStream, token: &str) -> Option<Client<TcpStream>> {
+		// The HTTP upgrade request gets the same time limit as the handshake
+		if stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_err() {
+			return None;
+		}
+
+		let connection = match stream.into_ws() {
+			Ok(connection) => connection,
+			Err((_, _, _, err)) => {
+				dprintln!("WebSocket Error: {:#?}", err);
+				return None;
+			}
+		};
+
+		if !connection.protocols().contains(&"gmpublisher".to_string()) {
+			dprintln!("WebSocket Error: Invalid Protocol");
+			return None;
+		}
+
+		let mut client = connection.use_protocol("gmpublisher").accept().ok()?;
+		dprintln!("WebSocket: Connection Established with {:#?}", client.peer_addr().unwrap());
+
+		TransactionServer::handshake(&mut client, token).then_some(client)
+	}
+
+	/// Waits for the client's [`Request::Hello`] and welcomes it if it speaks our protocol version and knows the token
+	fn handshake(client: &mut Client<TcpStream>, token: &str) -> bool {
+		if client.stream_ref().set_read_timeout(Some(HANDSHAKE_TIMEOUT)).is_err() {
+			return false;
+		}
+
+		let rejection = match client.recv_message() {
+			Ok(OwnedMessage::Binary(bytes)) => match Request::decode(&bytes) {
+				Ok(Request::Hello { token: client_token, .. }) if tokens_match(&client_token, token) => None,
+				Ok(Request::Hello { .. }) => Some(ErrorCode::Unauthorized),
+				Err(ProtocolError::UnsupportedVersion(_)) => Some(ErrorCode::ProtocolVersion),
+				_ => Some(ErrorCode::Handshake),
+			},
+			Ok(_) => Some(ErrorCode::Handshake),
+			Err(err) => {
+				dprintln!("WebSocket Handshake Error: {:#?}", err);
+				return false;
+			}
+		};
+
+		if let Some(reason) = rejection {
+			dprintln!("WebSocket Handshake Rejected: {}", reason);
+			let _ = client.send_message(&OwnedMessage::from(WebSocketMessage::Reply(Event::Rejected {
+				request: REQUEST_HELLO,
+				reason: reason.to_string(),
+			})));
+			let _ = client.shutdown();
+			return false;
+		}
+
+		client.stream_ref().set_read_timeout(None).is_ok()
+			&& client
+				.send_message(&OwnedMessage::from(WebSocketMessage::Reply(Event::Welcome { version: PROTOCOL_VERSION })))
+				.is_ok()
+	}
+
+	fn request(tx: &Sender<WebSocketMessage>, subscription: &Mutex<Subscription>, request: Request) {
+		let kind = request.kind();
+		let reject = |reason: ErrorCode| {
+			let _ = tx.send(WebSocketMessage::Reply(Event::Rejected {
+				request: kind,
+				reason: reason.to_string(),
+			}));
+		};
+
+		match request {
+			Request::Hello { .. } => reject(ErrorCode::Handshake),
+
+			Request::Cancel(id) => match super::TRANSACTIONS.find(id) {
+				Some(transaction) => transaction.cancel(),
+				None => reject(ErrorCode::TransactionNotFound),
+			},
+
+			Request::Pause(id) => match super::TRANSACTIONS.find(id) {
+				Some(transaction) => transaction.pause(),
+				None => reject(ErrorCode::TransactionNotFound),
+			},
+
+			Request::Resume(id) => match super::TRANSACTIONS.find(id) {
+				Some(transaction) => transaction.resume(),
+				None => reject(ErrorCode::TransactionNotFound),
+			},
+
+			Request::List => {
+				let _ = tx.send(WebSocketMessage::Reply(Event::List(super::TRANSACTIONS.list())));
+			}
+
+			Request::Subscribe(id) => subscription.lock().subscribe(id),
+			Request::SubscribeAll => subscription.lock().subscribe_all(),
+		}
+	}
+
 	fn listen(tx: Sender<WebSocketMessage>, rx: &Receiver<WebSocketMessage>, client: Client<TcpStream>) {
 		let (mut receiver, mut sender) = client.split().unwrap();
 
+		let subscription = Arc::new(Mutex::new(Subscription::default()));
+		let subscription_ref = subscription.clone();
+
 		std::thread::spawn(move || loop {
 			let message = match receiver.recv_message() {
 				Ok(message) => message,
@@ -161,20 +228,12 @@ impl TransactionServer {
 					tx.send(WebSocketMessage::OwnedMessage(OwnedMessage::Pong(ping))).unwrap();
 				}
 
-				OwnedMessage::Binary(bytes) => {
-					if &bytes[0..0] == CANCEL_TRANSACTION {
-						match &bytes[1..9].try_into() {
-							Ok(bytes) => {
-								super::cancel_transaction(u32::from_be_bytes(*bytes));
-								dprintln!("[1] WebSocket Invalid Message: {:?}", bytes);
-							}
-							Err(bytes) => {
-								dprintln!("[0] WebSocket Invalid Message: {:?}", bytes);
-								continue;
-							}
-						}
+				OwnedMessage::Binary(bytes) => match Request::decode(&bytes) {
+					Ok(request) => TransactionServer::request(&tx, &subscription_ref, request),
+					Err(err) => {
+						dprintln!("WebSocket Invalid Message: {} {:?}", err, bytes);
 					}
-				}
+				},
 
 				OwnedMessage::Text(text) => {
 					#[cfg(debug_assertions)]
@@ -190,15 +249,22 @@ impl TransactionServer {
 		while let Ok(message) = rx.recv() {
 			match message {
 				WebSocketMessage::OwnedMessage(message) => match message {
-					OwnedMessage::Binary(_) => {
+					OwnedMessage::Pong(_) => {
 						ignore! { sender.send_message(&message) };
 					}
 					OwnedMessage::Close(_) => break,
 					_ => unreachable!(),
 				},
 
+				WebSocketMessage::Reply(event) => {
+					ignore! { sender.send_message(&OwnedMessage::Binary(event.encode())) };
+				}
+
 				WebSocketMessage::TransactionMessage(message) => {
-					if sender.send_message::<OwnedMessage>(&OwnedMessage::Binary(message.as_bytes())).is_err() {
+					if !subscription.lock().wants(&message) {
+						continue;
+					}
+					if sender.send_message::<OwnedMessage>(&OwnedMessage::Binary(message.encode())).is_err() {
 						TransactionServer::send_tauri_event(message);
 					}
 				}
@@ -206,7 +272,7 @@ impl TransactionServer {
 		}
 	}
 
-	pub fn send(&'static self, message: TransactionMessage) {
+	pub fn send(&self, message: TransactionMessage) {
 		if let Err(err) = self.tx.send(WebSocketMessage::TransactionMessage(message)) {
 			TransactionServer::send_tauri_event(match err.into_inner() {
 				WebSocketMessage::TransactionMessage(message) => message,
@@ -238,6 +304,84 @@ impl TransactionServer {
 			TransactionMessage::ResetProgress(id) => {
 				webview_emit!("TransactionResetProgress", id);
 			}
+			TransactionMessage::Child(id, child, weight) => {
+				webview_emit!("TransactionChild", (id, child, weight));
+			}
+			TransactionMessage::Paused(id, paused) => {
+				webview_emit!("TransactionPaused", (id, paused));
+			}
 		}
 	}
 }
+
+/// Compares the whole token regardless of where the first difference is, so it can't be guessed byte by byte from response times
+fn tokens_match(a: &str, b: &str) -> bool {
+	a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[test]
+fn test_handshake() {
+	use websocket::ClientBuilder;
+
+	let server = TransactionServer::init().unwrap();
+
+	let hello = |hello: Vec<u8>| -> Event {
+		let mut client = ClientBuilder::new(&format!("ws://127.0.0.1:{}", server.port))
+			.unwrap()
+			.add_protocol("gmpublisher")
+			.connect_insecure()
+			.unwrap();
+
+		client.send_message(&OwnedMessage::Binary(hello)).unwrap();
+		let event = match client.recv_message().unwrap() {
+			OwnedMessage::Binary(bytes) => Event::decode(&bytes).unwrap(),
+			message => panic!("Unexpected message {:?}", message),
+		};
+		let _ = client.send_message(&OwnedMessage::Close(None));
+		event
+	};
+
+	// A client that never sends anything mustn't stop others from connecting
+	let _stalled = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+
+	let rejected = |reason: ErrorCode| Event::Rejected {
+		request: REQUEST_HELLO,
+		reason: reason.to_string(),
+	};
+
+	// Without the token
+	assert_eq!(
+		hello(
+			Request::Hello {
+				version: PROTOCOL_VERSION,
+				token: String::new(),
+			}
+			.encode()
+		),
+		rejected(ErrorCode::Unauthorized)
+	);
+	assert_eq!(
+		hello(
+			Request::Hello {
+				version: PROTOCOL_VERSION,
+				token: "0".repeat(server.token.len()),
+			}
+			.encode()
+		),
+		rejected(ErrorCode::Unauthorized)
+	);
+	assert_eq!(hello(Request::List.encode()), rejected(ErrorCode::Handshake));
+	assert_eq!(hello(vec![REQUEST_HELLO, 0, 1]), rejected(ErrorCode::ProtocolVersion));
+
+	// With the token
+	assert_eq!(
+		hello(
+			Request::Hello {
+				version: PROTOCOL_VERSION,
+				token: server.token.clone(),
+			}
+			.encode()
+		),
+		Event::Welcome { version: PROTOCOL_VERSION }
+	);
+}
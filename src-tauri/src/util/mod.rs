@@ -13,5 +13,4 @@ pub use dedup_unsorted::*;
 mod escape_json;
 pub use escape_json::*;
 
-mod stream;
-pub use stream::*;
+pub use libgma::io::*;
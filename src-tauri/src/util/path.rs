@@ -139,10 +139,15 @@ pub fn has_extension<P: AsRef<Path>, S: AsRef<str>>(path: P, extension: S) -> bo
 pub fn open<P: AsRef<Path>>(path: P) {
 	let path = path.as_ref();
 	if opener::open(path).is_err() {
+		#[cfg(feature = "gui")]
 		tauri::api::dialog::message(None::<&tauri::Window<tauri::Wry>>, "File", path.to_string_lossy());
+
+		#[cfg(not(feature = "gui"))]
+		std::eprintln!("Failed to open \"{}\"", path.display());
 	}
 }
 
+#[cfg(feature = "gui")]
 pub fn open_file_location<P: AsRef<Path>>(path: P) {
 	let path = dunce::canonicalize(path.as_ref()).unwrap_or_else(|_| path.as_ref().to_path_buf());
 